@@ -13,7 +13,7 @@ fn main() {
         .get_servers(channel_id)
         .expect("Couldn't get chat server");
     let (mut client, receiver) =
-        ChatClient::connect(&endpoints[0], client_id).expect("Could not connect to chat");
+        ChatClient::connect_any(&endpoints, client_id).expect("Could not connect to chat");
     debug!("Authenticating");
     client
         .authenticate(channel_id, None, None)
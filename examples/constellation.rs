@@ -1,6 +1,9 @@
 use failure::Error;
-use log::{debug, info};
-use mixer_wrappers::{ConstellationClient, REST};
+use log::{debug, info, warn};
+use mixer_wrappers::{
+    constellation::{events::ChannelEventKind, StreamMessage},
+    ConstellationClient, REST,
+};
 use serde_json::Value;
 use std::{thread, time::Duration};
 
@@ -21,19 +24,28 @@ fn get_channel_id(client_id: &str, username: &str) -> Result<usize, Error> {
 
 fn main() {
     let client_id = "CLIENT_ID_HERE";
-    let channel_id = get_channel_id(&client_id, "CHANNEL_NAME_HERE").unwrap();
+    let channel_id = get_channel_id(client_id, "CHANNEL_NAME_HERE").unwrap();
 
-    let (mut client, receiver) = ConstellationClient::connect(&client_id).unwrap();
+    let (mut client, receiver) = ConstellationClient::connect(client_id).unwrap();
     let read_handler = thread::spawn(move || loop {
-        if let Ok(msg) = receiver.try_recv() {
-            info!(">> {}", msg);
+        match ConstellationClient::try_next(&receiver) {
+            Ok(Some(Ok(StreamMessage::Event(event)))) => {
+                info!(">> {}: {:?}", event.event, event.data)
+            }
+            Ok(Some(Ok(StreamMessage::Reply(reply)))) => debug!("Reply to {}", reply.id),
+            Ok(Some(Err(e))) => warn!("Could not parse message: {}", e),
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(_) => {
+                info!("Disconnected");
+                break;
+            }
         }
     });
 
     thread::sleep(Duration::from_secs(3));
 
     client
-        .subscribe(&[&format!("channel:{}:update", channel_id)])
+        .subscribe_channel(channel_id, &[ChannelEventKind::Update])
         .unwrap();
 
     read_handler.join().unwrap();
@@ -3,10 +3,8 @@ use mixer_wrappers::{
     oauth::{check_shortcode, get_shortcode, get_token_from_code, ShortcodeStatus},
     REST,
 };
-use serde_json::Value;
 use std::{thread, time::Duration};
 
-const USERNAME: &str = "YOUR_USERNAME";
 const CLIENT_ID: &str = "YOUR_CLIENT_ID";
 const CLIENT_SECRET: &str = "CLIENT_SECRET";
 
@@ -39,30 +37,24 @@ fn get_access_token() -> Result<String, Error> {
     Ok(token.access_token)
 }
 
-fn get_user_id(rest: &REST) -> Result<u64, Error> {
-    let text = rest.query(
-        "GET",
-        "users/search",
-        Some(&[("query", USERNAME), ("noCount", "true"), ("fields", "id")]),
-        None,
-        None,
-    )?;
-    let json: Value = serde_json::from_str(&text)?;
-    let id = json.as_array().unwrap()[0]["id"].as_u64().unwrap();
-    Ok(id)
-}
-
 fn main() {
     let token = get_access_token().unwrap();
     let rest = REST::new(CLIENT_ID);
-    let resp = rest
-        .query(
-            "GET",
-            &format!("users/{}/notifications", get_user_id(&rest).unwrap()),
-            Some(&[("limit", "5"), ("noCount", "true")]),
-            None,
-            Some(&token),
-        )
+    let user = rest.users_helper().get_current(&token).unwrap();
+    let helper = rest.users_helper();
+    let notifications = helper.get_notifications(user.id, &token, 5).unwrap();
+    for notification in &notifications {
+        println!(
+            "{} at {}: {}",
+            notification.kind, notification.sent_at, notification.payload
+        );
+    }
+    let unread: Vec<&str> = notifications
+        .iter()
+        .filter(|n| !n.read)
+        .map(|n| n.id.as_str())
+        .collect();
+    helper
+        .mark_notifications_read(user.id, &token, &unread)
         .unwrap();
-    println!("{}", resp);
 }
@@ -1,25 +1,28 @@
-use mixer_wrappers::oauth::{check_shortcode, get_shortcode, ShortcodeStatus};
-use std::{thread, time::Duration};
+use mixer_wrappers::oauth::{get_shortcode, wait_for_shortcode, ShortcodePolling, ShortcodeStatus};
+use std::time::Duration;
 
 fn main() {
-    let resp = get_shortcode("CLIENT_ID_HERE", "CLIENT_SECRET_HERE", &[]).unwrap();
+    let resp = match get_shortcode(
+        "CLIENT_ID_HERE",
+        "CLIENT_SECRET_HERE",
+        &["user:details:self"],
+    ) {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Could not start the shortcode flow: {}", e);
+            return;
+        }
+    };
     println!(
         "Code is {}; go to https://mixer.com/go to enter\n\n",
         resp.code
     );
-    loop {
-        let status = check_shortcode(&resp.handle);
-        println!("Status: {:?}", status);
-        let code: String = match status {
-            ShortcodeStatus::UserGrantedAccess(ref c) => c.to_owned(),
-            ShortcodeStatus::UserDeniedAccess => break,
-            ShortcodeStatus::HandleInvalid => break,
-            _ => {
-                thread::sleep(Duration::from_secs(3));
-                continue;
-            }
-        };
-        println!("Code: {}", code);
-        break;
+    let polling = ShortcodePolling::builder()
+        .interval(Duration::from_secs(3))
+        .jitter(Duration::from_millis(500))
+        .build();
+    match wait_for_shortcode(&resp.handle, &polling) {
+        ShortcodeStatus::UserGrantedAccess(code) => println!("Code: {}", code),
+        status => println!("Status: {:?}", status),
     }
 }
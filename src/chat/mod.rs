@@ -7,15 +7,21 @@
 /// Static models for JSON data
 pub mod models;
 
-use crate::internal::{connect as socket_connect, ClientSocketWrapper};
-use atomic_counter::AtomicCounter;
+pub use crate::internal::{probe, BroadcastReceiver, ConnectionState};
+
+use crate::internal::{connect as socket_connect, ClientSocketWrapper, ConnectOptions};
 use failure::{format_err, Error};
-use log::debug;
+use log::{debug, warn};
 use serde_json::{json, Value};
-use std::{convert::TryFrom, sync::mpsc::Receiver, thread::JoinHandle};
+use std::{convert::TryFrom, sync::mpsc::Receiver, thread::JoinHandle, time::Duration};
 
 use models::{Event, Method, Reply};
 
+/// Shortest timeout that Mixer allows
+const MIN_TIMEOUT: Duration = Duration::from_secs(15);
+/// Longest timeout that Mixer allows
+const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// Possible messages from the socket.
 pub enum StreamMessage {
     /// Event types
@@ -31,6 +37,87 @@ pub struct ChatClient {
     pub join_handle: JoinHandle<()>,
 }
 
+/// Builder for connecting to the chat server with non-default options,
+/// from `ChatClient::builder`.
+pub struct ChatClientBuilder {
+    endpoint: Option<String>,
+    client_id: String,
+    options: ConnectOptions,
+}
+
+impl ChatClientBuilder {
+    /// Set the chat websocket endpoint to connect to, which must be set.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - endpoint, e.g. from `ChatHelper::get_servers`
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_owned());
+        self
+    }
+
+    /// Set the client ID sent when connecting.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    pub fn client_id(mut self, client_id: &str) -> Self {
+        self.client_id = client_id.to_owned();
+        self
+    }
+
+    /// Send a ping at this interval, marking the connection stale if the
+    /// server stops answering; no pings are sent by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - time between pings
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.options.keepalive = Some(interval);
+        self
+    }
+
+    /// Reconnect when the server closes the connection because it's being
+    /// deployed; off by default. The new connection isn't authenticated, so
+    /// `authenticate` has to be called again once `state` shows it's connected.
+    ///
+    /// # Arguments
+    ///
+    /// * `reconnect` - whether to reconnect
+    pub fn auto_reconnect(mut self, reconnect: bool) -> Self {
+        self.options.reconnect_on_deploy = reconnect;
+        self
+    }
+
+    /// Set the longest time to wait for the connection to open before
+    /// `connect` returns an error; defaults to 15 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - maximum time to connect
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = timeout;
+        self
+    }
+
+    /// Connect to the chat server, returning an error if no endpoint was set.
+    pub fn connect(self) -> Result<(ChatClient, Receiver<String>), Error> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint,
+            None => return Err(format_err!("No chat endpoint to connect to")),
+        };
+        let (client, join_handle, receiver) =
+            socket_connect(endpoint, &self.client_id, &self.options, None, None)?;
+        Ok((
+            ChatClient {
+                client,
+                join_handle,
+            },
+            receiver,
+        ))
+    }
+}
+
 impl ChatClient {
     /// Connect to the chat server.
     ///
@@ -41,6 +128,9 @@ impl ChatClient {
     /// function does not handle that process; use the REST API included
     /// in this crate to get that information.
     ///
+    /// The client ID is sent when connecting, so changing it requires
+    /// connecting again.
+    ///
     /// # Arguments
     ///
     /// * `endpoint` - chat websocket endpoint to connect to
@@ -55,16 +145,99 @@ impl ChatClient {
     ///
     /// [documentation]: https://dev.mixer.com/reference/chat/connection
     pub fn connect(endpoint: &str, client_id: &str) -> Result<(Self, Receiver<String>), Error> {
-        let (client, join_handle, receiver) = socket_connect(endpoint, client_id)?;
-        Ok((
-            ChatClient {
-                client,
-                join_handle,
-            },
-            receiver,
+        ChatClient::builder()
+            .endpoint(endpoint)
+            .client_id(client_id)
+            .connect()
+    }
+
+    /// Start building a connection with non-default options.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ChatClient;
+    /// use std::time::Duration;
+    /// let (mut client, receiver) = ChatClient::builder()
+    ///     .endpoint("aaa")
+    ///     .client_id("bbb")
+    ///     .keepalive(Duration::from_secs(30))
+    ///     .connect()
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> ChatClientBuilder {
+        ChatClientBuilder {
+            endpoint: None,
+            client_id: String::new(),
+            // reconnecting would drop the chat authentication
+            options: ConnectOptions::builder().reconnect_on_deploy(false).build(),
+        }
+    }
+
+    /// Connect to the first of the chat servers that accepts the connection.
+    ///
+    /// The endpoints are tried in order, each for up to the connect timeout, so
+    /// a server that's down doesn't stop the client from connecting to the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoints` - chat websocket endpoints, e.g. from `ChatHelper::get_servers`
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::{ChatClient, REST};
+    /// let api = REST::new("bbb");
+    /// let endpoints = api.chat_helper().get_servers(1234).unwrap();
+    /// let (mut client, receiver) = ChatClient::connect_any(&endpoints, "bbb").unwrap();
+    /// ```
+    pub fn connect_any(
+        endpoints: &[String],
+        client_id: &str,
+    ) -> Result<(Self, Receiver<String>), Error> {
+        let mut failures = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            match ChatClient::connect(endpoint, client_id) {
+                Ok(connected) => return Ok(connected),
+                Err(e) => {
+                    warn!("Could not connect to {}: {}", endpoint, e);
+                    failures.push(format!("{}: {}", endpoint, e));
+                }
+            }
+        }
+        if failures.is_empty() {
+            return Err(format_err!("No chat endpoints to connect to"));
+        }
+        Err(format_err!(
+            "Could not connect to any chat endpoint ({})",
+            failures.join("; ")
         ))
     }
 
+    /// Connect to the chat server, returning a receiver that can be cloned so
+    /// that several threads each get every message.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - chat websocket endpoint to connect to
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ChatClient;
+    /// let (mut client, receiver) = ChatClient::connect_broadcast("aaa", "bbb").unwrap();
+    /// let logging = receiver.clone();
+    /// ```
+    pub fn connect_broadcast(
+        endpoint: &str,
+        client_id: &str,
+    ) -> Result<(Self, BroadcastReceiver), Error> {
+        let (client, receiver) = ChatClient::connect(endpoint, client_id)?;
+        Ok((client, BroadcastReceiver::new(receiver)))
+    }
+
     /// Authenticate with the server. This must be done after connecting.
     ///
     /// Per the [documentation], you can either authenticate anonymously,
@@ -94,33 +267,111 @@ impl ChatClient {
         user_id: Option<usize>,
         auth_key: Option<&str>,
     ) -> Result<(), Error> {
-        let method = if user_id.is_none() || auth_key.is_none() {
-            debug!("Authenticating as anonymous");
-            Method {
-                method_type: "method".to_owned(),
-                method: "auth".to_owned(),
-                arguments: vec![json!(channel_id)],
-                id: self.client.method_counter.inc(),
+        let method = match (user_id, auth_key) {
+            (Some(user_id), Some(auth_key)) => {
+                debug!("Authenticating as a user");
+                Method {
+                    method_type: "method".to_owned(),
+                    method: "auth".to_owned(),
+                    arguments: vec![json!(channel_id), json!(user_id), json!(auth_key)],
+                    id: self.client.next_method_id(),
+                }
             }
-        } else {
-            debug!("Authenticating as a user");
-            Method {
-                method_type: "method".to_owned(),
-                method: "auth".to_owned(),
-                arguments: vec![
-                    json!(channel_id),
-                    json!(user_id.unwrap()),
-                    json!(auth_key.unwrap()),
-                ],
-                id: self.client.method_counter.inc(),
+            _ => {
+                debug!("Authenticating as anonymous");
+                Method {
+                    method_type: "method".to_owned(),
+                    method: "auth".to_owned(),
+                    arguments: vec![json!(channel_id)],
+                    id: self.client.next_method_id(),
+                }
             }
         };
-        self.client
-            .socket_out
-            .send(serde_json::to_string(&method)?)?;
+        self.client.send(serde_json::to_string(&method)?)?;
         Ok(())
     }
 
+    /// Number of methods sent to the chat server, including authentication.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (client, _) = ChatClient::connect("", "").unwrap();
+    /// let count = client.methods_sent();
+    /// ```
+    pub fn methods_sent(&self) -> usize {
+        self.client.methods_sent()
+    }
+
+    /// Number of messages received from the chat server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (client, _) = ChatClient::connect("", "").unwrap();
+    /// let count = client.messages_received();
+    /// ```
+    pub fn messages_received(&self) -> u64 {
+        self.client.messages_received()
+    }
+
+    /// Number of bytes received from the chat server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (client, _) = ChatClient::connect("", "").unwrap();
+    /// let bytes = client.bytes_received();
+    /// ```
+    pub fn bytes_received(&self) -> u64 {
+        self.client.bytes_received()
+    }
+
+    /// How long the current connection to the chat server has been open.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (client, _) = ChatClient::connect("", "").unwrap();
+    /// let uptime = client.uptime();
+    /// ```
+    pub fn uptime(&self) -> Duration {
+        self.client.uptime()
+    }
+
+    /// Current state of the connection to the chat server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// let state = client.state();
+    /// ```
+    pub fn state(&mut self) -> ConnectionState {
+        self.client.state()
+    }
+
+    /// Code and reason of the close frame, if the connection to the chat server
+    /// has been closed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (client, _) = ChatClient::connect("", "").unwrap();
+    /// if let Some((code, reason)) = client.last_close() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn last_close(&self) -> Option<(u16, String)> {
+        self.client.last_close()
+    }
+
     /// Call a method, sending data to the socket.
     ///
     /// The `arguments` parameter is so dynamic because while the arguments
@@ -143,20 +394,189 @@ impl ChatClient {
     /// }
     /// ```
     pub fn call_method(&mut self, method: &str, arguments: &[Value]) -> Result<(), Error> {
+        self.send_method(method, arguments)?;
+        Ok(())
+    }
+
+    /// Send a JSON value to the socket as-is, e.g. for a method that this
+    /// crate doesn't model yet.
+    ///
+    /// Unlike `call_method`, no method id is assigned and the value isn't
+    /// counted in `methods_sent`; include an `id` in the value to match its reply.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - JSON to send
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # use serde_json::json;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// client
+    ///     .send_raw_json(&json!({"type": "method", "method": "ping", "arguments": [], "id": 1000}))
+    ///     .unwrap();
+    /// ```
+    pub fn send_raw_json(&mut self, value: &Value) -> Result<(), Error> {
+        if !self.client.check_connection() {
+            return Err(format_err!("Not connected to socket"));
+        }
+        debug!("Sending raw JSON to socket: {}", value);
+        self.client.send(serde_json::to_string(value)?)
+    }
+
+    /// Send a method to the socket, returning its id.
+    fn send_method(&mut self, method: &str, arguments: &[Value]) -> Result<usize, Error> {
         if !self.client.check_connection() {
             return Err(format_err!("Not connected to socket"));
         }
+        let id = self.client.next_method_id();
         let to_send = Method {
             method_type: "method".to_owned(),
             method: method.to_owned(),
             arguments: arguments.to_owned(),
-            id: self.client.method_counter.inc(),
+            id,
         };
         debug!("Sending method call to socket: {:?}", to_send);
-        self.client
-            .socket_out
-            .send(serde_json::to_string(&to_send)?)?;
-        Ok(())
+        self.client.send(serde_json::to_string(&to_send)?)?;
+        Ok(id)
+    }
+
+    /// Send a chat message to the channel.
+    ///
+    /// This calls the `msg` method, whose only argument is the message text:
+    /// `{"type": "method", "method": "msg", "arguments": ["text"], "id": 0}`.
+    /// The reply with the same id contains the sent message, in the same shape
+    /// as a `ChatMessage` event.
+    ///
+    /// Mixer chat has no threaded replies or message metadata, so to reply to
+    /// a user, mention them in the text (e.g. `@username`).
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - message to send
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// let method_id = client.send_message("Hello!").unwrap();
+    /// ```
+    pub fn send_message(&mut self, text: &str) -> Result<usize, Error> {
+        self.send_method("msg", &[json!(text)])
+    }
+
+    /// Send a chat message that only one user can see.
+    ///
+    /// This calls the `whisper` method, whose arguments are the username and
+    /// the message text:
+    /// `{"type": "method", "method": "whisper", "arguments": ["username", "text"], "id": 0}`.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - user to send the message to
+    /// * `text` - message to send
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// let method_id = client.whisper("someone", "Hello!").unwrap();
+    /// ```
+    pub fn whisper(&mut self, username: &str, text: &str) -> Result<usize, Error> {
+        self.send_method("whisper", &[json!(username), json!(text)])
+    }
+
+    /// Stop a user from chatting for a while.
+    ///
+    /// This calls the `timeout` method, whose arguments are the username and
+    /// the duration as a string like `30s`, `5m`, or `1h`:
+    /// `{"type": "method", "method": "timeout", "arguments": ["username", "5m"], "id": 0}`.
+    /// The duration is sent in the largest unit that it's a whole number of,
+    /// rounded down to the second.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - user to time out
+    /// * `duration` - how long for; must be between 15 seconds and 24 hours
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # use std::time::Duration;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// let method_id = client.timeout_user("someone", Duration::from_secs(300)).unwrap();
+    /// ```
+    pub fn timeout_user(&mut self, username: &str, duration: Duration) -> Result<usize, Error> {
+        let duration = format_timeout(duration)?;
+        self.send_method("timeout", &[json!(username), json!(duration)])
+    }
+
+    /// Start a poll in the channel.
+    ///
+    /// This calls the `vote:start` method, whose arguments are the question,
+    /// the answers, and the duration in seconds:
+    /// `{"type": "method", "method": "vote:start", "arguments": ["question", ["a", "b"], 30], "id": 0}`.
+    /// The reply with the same id has a `null` error if the poll was started.
+    ///
+    /// The results are sent as `PollStart` events as votes come in, and as a
+    /// `PollEnd` event once the duration has passed; their data has the question
+    /// (`q`), `answers`, `duration`, `endsAt`, total `voters`, and the votes for
+    /// each answer in `responses`.
+    ///
+    /// A poll can't be ended early: the chat API's only poll methods are
+    /// `vote:start` and `vote:choose`, so wait for the `PollEnd` event instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - question to ask
+    /// * `options` - answers to choose from; there must be at least two
+    /// * `duration_secs` - how long the poll runs for
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// let method_id = client.start_poll("Which game?", &["This one", "That one"], 60).unwrap();
+    /// ```
+    pub fn start_poll(
+        &mut self,
+        question: &str,
+        options: &[&str],
+        duration_secs: u32,
+    ) -> Result<usize, Error> {
+        if options.len() < 2 {
+            return Err(format_err!(
+                "A poll needs at least two options, got {}",
+                options.len()
+            ));
+        }
+        self.send_method(
+            "vote:start",
+            &[json!(question), json!(options), json!(duration_secs)],
+        )
+    }
+
+    /// Start a giveaway, which picks a random user from the channel's chat.
+    ///
+    /// This calls the `giveaway:start` method, which has no arguments. The reply
+    /// with the same id has a `null` error if the giveaway was started, and the
+    /// winner is announced in a chat message.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ChatClient;
+    /// # let (mut client, _) = ChatClient::connect("", "").unwrap();
+    /// let method_id = client.start_giveaway().unwrap();
+    /// ```
+    pub fn start_giveaway(&mut self) -> Result<usize, Error> {
+        self.send_method("giveaway:start", &[])
     }
 
     /// Helper method to parse the JSON messages into structs.
@@ -193,5 +613,245 @@ impl ChatClient {
     }
 }
 
+/// Format a timeout duration the way the `timeout` method expects it,
+/// returning an error if it's outside the allowed range.
+///
+/// # Arguments
+///
+/// * `duration` - length of the timeout
+fn format_timeout(duration: Duration) -> Result<String, Error> {
+    if duration < MIN_TIMEOUT || duration > MAX_TIMEOUT {
+        return Err(format_err!(
+            "Timeout of {}s is outside the allowed range of {}s to {}s",
+            duration.as_secs(),
+            MIN_TIMEOUT.as_secs(),
+            MAX_TIMEOUT.as_secs()
+        ));
+    }
+    let secs = duration.as_secs();
+    Ok(if secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    })
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::{format_timeout, ChatClient};
+    use crate::internal::test_server::{ServerEvent, TestServer};
+    use serde_json::{json, Value};
+    use std::{net::TcpListener, time::Duration};
+
+    #[test]
+    fn methods_sent() {
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::connect(&server.url, "").unwrap();
+        assert_eq!(0, client.methods_sent());
+        client.authenticate(123, None, None).unwrap();
+        client.call_method("msg", &[json!("hello")]).unwrap();
+        assert_eq!(2, client.methods_sent());
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        match server.next_event(Duration::from_secs(1)) {
+            Some(ServerEvent::Message(m)) => assert!(m.contains(r#""method":"auth""#)),
+            e => panic!("Unexpected event {:?}", e),
+        }
+    }
+
+    /// Get the next method the server receives.
+    fn next_method(server: &TestServer) -> Value {
+        match server.next_event(Duration::from_secs(1)) {
+            Some(ServerEvent::Message(m)) => serde_json::from_str(&m).unwrap(),
+            e => panic!("Unexpected event {:?}", e),
+        }
+    }
+
+    #[test]
+    fn send_message_and_whisper() {
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::connect(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        assert_eq!(0, client.send_message("hello").unwrap());
+        let method = next_method(&server);
+        assert_eq!("msg", method["method"]);
+        assert_eq!(json!(["hello"]), method["arguments"]);
+        assert_eq!(0, method["id"]);
+
+        assert_eq!(1, client.whisper("someone", "hi").unwrap());
+        let method = next_method(&server);
+        assert_eq!("whisper", method["method"]);
+        assert_eq!(json!(["someone", "hi"]), method["arguments"]);
+        assert_eq!(1, method["id"]);
+    }
+
+    #[test]
+    fn start_poll_and_giveaway() {
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::connect(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        assert!(client.start_poll("Which?", &["a"], 30).is_err());
+        assert_eq!(0, client.methods_sent());
+
+        assert_eq!(0, client.start_poll("Which?", &["a", "b"], 30).unwrap());
+        let method = next_method(&server);
+        assert_eq!("vote:start", method["method"]);
+        assert_eq!(json!(["Which?", ["a", "b"], 30]), method["arguments"]);
+
+        assert_eq!(1, client.start_giveaway().unwrap());
+        let method = next_method(&server);
+        assert_eq!("giveaway:start", method["method"]);
+        assert_eq!(json!([]), method["arguments"]);
+    }
+
+    #[test]
+    fn timeout_format() {
+        let format = |secs| format_timeout(Duration::from_secs(secs)).unwrap();
+        assert_eq!("30s", format(30));
+        assert_eq!("90s", format(90));
+        assert_eq!("5m", format(300));
+        assert_eq!("90m", format(5400));
+        assert_eq!("1h", format(3600));
+        assert_eq!("24h", format(86400));
+        assert_eq!(
+            "15s",
+            format_timeout(Duration::from_millis(15_900)).unwrap()
+        );
+    }
+
+    #[test]
+    fn timeout_out_of_range() {
+        assert!(format_timeout(Duration::from_secs(14)).is_err());
+        assert!(format_timeout(Duration::from_millis(14_999)).is_err());
+        let err = format_timeout(Duration::from_secs(86401))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "Timeout of 86401s is outside the allowed range of 15s to 86400s",
+            err
+        );
+    }
+
+    #[test]
+    fn timeout_user() {
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::connect(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        assert!(client
+            .timeout_user("someone", Duration::from_secs(5))
+            .is_err());
+        assert_eq!(0, client.methods_sent());
+
+        assert_eq!(
+            0,
+            client
+                .timeout_user("someone", Duration::from_secs(300))
+                .unwrap()
+        );
+        let method = next_method(&server);
+        assert_eq!("timeout", method["method"]);
+        assert_eq!(json!(["someone", "5m"]), method["arguments"]);
+    }
+
+    #[test]
+    fn builder() {
+        assert!(ChatClient::builder().client_id("abc").connect().is_err());
+
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::builder()
+            .endpoint(&server.url)
+            .client_id("abc")
+            .keepalive(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(2))
+            .connect()
+            .unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        client.send_message("hello").unwrap();
+        assert_eq!("msg", next_method(&server)["method"]);
+    }
+
+    #[test]
+    fn builder_auto_reconnect() {
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::builder()
+            .endpoint(&server.url)
+            .auto_reconnect(true)
+            .connect()
+            .unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        server.close(1012, "Restarting");
+        assert!(matches!(
+            server.next_event(Duration::from_secs(1)),
+            Some(ServerEvent::Close(_))
+        ));
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(3))
+        );
+        client.authenticate(123, None, None).unwrap();
+        assert_eq!("auth", next_method(&server)["method"]);
+    }
+
+    #[test]
+    fn connect_broadcast() {
+        let server = TestServer::start();
+        let (_client, receiver) = ChatClient::connect_broadcast(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        let other = receiver.clone();
+
+        server.send(r#"{"type":"event","event":"WelcomeEvent","data":{}}"#);
+        assert!(receiver
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap()
+            .contains("WelcomeEvent"));
+        assert!(other
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap()
+            .contains("WelcomeEvent"));
+    }
+
+    #[test]
+    fn connect_any() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let down = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let server = TestServer::start();
+
+        let endpoints = vec![down.clone(), server.url.clone()];
+        assert!(ChatClient::connect_any(&endpoints, "").is_ok());
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+
+        let err = match ChatClient::connect_any(&endpoints[..1], "") {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("Connected to a closed port"),
+        };
+        assert!(err.contains(&down));
+        assert!(ChatClient::connect_any(&[], "").is_err());
+    }
+
+    #[test]
+    fn send_raw_json() {
+        let server = TestServer::start();
+        let (mut client, _) = ChatClient::connect(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        let value = json!({"type": "method", "method": "experimental", "foo": [1, 2]});
+        client.send_raw_json(&value).unwrap();
+        assert_eq!(value, next_method(&server));
+        assert_eq!(0, client.methods_sent());
+    }
+}
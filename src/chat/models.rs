@@ -1,6 +1,6 @@
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{collections::HashMap, convert::TryFrom};
+use std::{collections::HashMap, convert::TryFrom, fmt};
 
 /// An Event coming in from the socket.
 ///
@@ -19,6 +19,10 @@ pub struct Event {
     /// per the docs, completely unstructured; it depends
     /// on which kind of event was received.
     pub data: Option<Value>,
+    /// Fields that aren't modeled above, kept so that fields Mixer adds
+    /// are still available
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 impl TryFrom<Value> for Event {
@@ -34,6 +38,154 @@ impl TryFrom<Value> for Event {
     }
 }
 
+/// A user joining or leaving the chat, from the `UserJoin` and `UserLeave` events.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatUserPresence {
+    /// User's id
+    pub id: usize,
+    /// User's username
+    pub username: String,
+    /// User's roles in the channel, e.g. "Mod" or "Subscriber"
+    pub roles: Vec<String>,
+}
+
+/// User who moderated the chat, from the `DeleteMessage` and `ClearMessages` events.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatModerator {
+    /// User's id
+    pub user_id: usize,
+    /// User's username
+    pub user_name: String,
+    /// User's roles in the channel, e.g. "Mod" or "Owner"
+    #[serde(default)]
+    pub user_roles: Vec<String>,
+}
+
+/// A message being deleted, from the `DeleteMessage` event.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatMessageDeleted {
+    /// Id of the deleted message
+    pub id: String,
+    /// User who deleted the message
+    pub moderator: ChatModerator,
+}
+
+/// The chat being cleared, from the `ClearMessages` event.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatCleared {
+    /// User who cleared the chat
+    pub clearer: ChatModerator,
+}
+
+/// A poll starting, from the `PollStart` event.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatPollStart {
+    /// Question being asked
+    #[serde(rename = "q")]
+    pub question: String,
+    /// Answers that can be voted for
+    #[serde(rename = "answers")]
+    pub options: Vec<String>,
+    /// When voting ends, in milliseconds since the Unix epoch
+    #[serde(rename = "endsAt")]
+    pub ends_at: u64,
+}
+
+/// A poll ending, with its results, from the `PollEnd` event.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatPollEnd {
+    /// Question that was asked
+    #[serde(rename = "q")]
+    pub question: String,
+    /// Number of votes for each answer
+    pub responses: HashMap<String, u64>,
+    /// Number of users who voted
+    pub voters: u64,
+}
+
+impl ChatPollEnd {
+    /// Answer with the most votes, or `None` if there were no votes or the
+    /// top answers are tied.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::chat::models::ChatPollEnd;
+    /// let end = ChatPollEnd {
+    ///     question: "Which map?".to_owned(),
+    ///     responses: vec![("Dust".to_owned(), 5), ("Nuke".to_owned(), 2)]
+    ///         .into_iter()
+    ///         .collect(),
+    ///     voters: 7,
+    /// };
+    /// assert_eq!(Some("Dust"), end.winner());
+    /// ```
+    pub fn winner(&self) -> Option<&str> {
+        let most = *self.responses.values().max()?;
+        if most == 0 {
+            return None;
+        }
+        let mut top = self.responses.iter().filter(|&(_, &votes)| votes == most);
+        match (top.next(), top.next()) {
+            (Some((answer, _)), None) => Some(answer),
+            _ => None,
+        }
+    }
+}
+
+/// A chat event with its data parsed into the matching payload type.
+///
+/// See https://dev.mixer.com/reference/chat/events
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatEventData {
+    /// `UserJoin` - a user joined the chat
+    UserJoin(ChatUserPresence),
+    /// `UserLeave` - a user left the chat
+    UserLeave(ChatUserPresence),
+    /// `DeleteMessage` - a message was deleted
+    DeleteMessage(ChatMessageDeleted),
+    /// `ClearMessages` - the chat was cleared
+    ClearMessages(ChatCleared),
+    /// `PollStart` - a poll started
+    PollStart(ChatPollStart),
+    /// `PollEnd` - a poll ended
+    PollEnd(ChatPollEnd),
+    /// Any event that doesn't have a typed payload
+    Other {
+        /// Which event
+        event: String,
+        /// Unstructured data associated with the event
+        data: Option<Value>,
+    },
+}
+
+impl TryFrom<&Event> for ChatEventData {
+    type Error = &'static str;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        fn payload<T: serde::de::DeserializeOwned>(event: &Event) -> Result<T, &'static str> {
+            match &event.data {
+                Some(data) => {
+                    serde_json::from_value(data.clone()).map_err(|_| "Could not load payload")
+                }
+                None => Err("Event has no data"),
+            }
+        }
+        match event.event.as_str() {
+            "UserJoin" => Ok(ChatEventData::UserJoin(payload(event)?)),
+            "UserLeave" => Ok(ChatEventData::UserLeave(payload(event)?)),
+            "DeleteMessage" => Ok(ChatEventData::DeleteMessage(payload(event)?)),
+            "ClearMessages" => Ok(ChatEventData::ClearMessages(payload(event)?)),
+            "PollStart" => Ok(ChatEventData::PollStart(payload(event)?)),
+            "PollEnd" => Ok(ChatEventData::PollEnd(payload(event)?)),
+            _ => Ok(ChatEventData::Other {
+                event: event.event.clone(),
+                data: event.data.clone(),
+            }),
+        }
+    }
+}
+
 /// A Method to send to the socket.
 ///
 /// This is how clients send data _to_ the socket.
@@ -68,7 +220,61 @@ pub struct Reply {
     /// Method call result
     pub data: Option<HashMap<String, Value>>,
     /// Method error
-    pub error: Option<String>,
+    pub error: Option<ChatError>,
+    /// Fields that aren't modeled above, kept so that fields Mixer adds
+    /// are still available
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// Error in a reply from the chat server, which is sent either as a bare
+/// message or as an object with an error code.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum ChatError {
+    /// Error with a code
+    Detailed {
+        /// Error's id
+        id: u32,
+        /// Error's message
+        message: String,
+    },
+    /// Error with only a message
+    Message(String),
+}
+
+impl ChatError {
+    /// Error's code, if the server sent one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::chat::models::ChatError;
+    /// let error: ChatError = serde_json::from_str(r#"{"id":4,"message":"Nope"}"#).unwrap();
+    /// assert_eq!(Some(4), error.code());
+    /// ```
+    pub fn code(&self) -> Option<u32> {
+        match self {
+            ChatError::Detailed { id, .. } => Some(*id),
+            ChatError::Message(_) => None,
+        }
+    }
+
+    /// Error's message.
+    pub fn message(&self) -> &str {
+        match self {
+            ChatError::Detailed { message, .. } | ChatError::Message(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code() {
+            Some(code) => write!(f, "{} (error {})", self.message(), code),
+            None => write!(f, "{}", self.message()),
+        }
+    }
 }
 
 impl TryFrom<Value> for Reply {
@@ -86,14 +292,215 @@ impl TryFrom<Value> for Reply {
 
 #[cfg(test)]
 mod tests {
-    use super::{Event, Reply};
+    use super::{
+        ChatCleared, ChatError, ChatEventData, ChatMessageDeleted, ChatModerator, ChatPollEnd,
+        ChatPollStart, ChatUserPresence, Event, Reply,
+    };
     use serde_json::{json, Value};
     use std::{collections::HashMap, convert::TryFrom};
 
+    #[test]
+    fn user_join() {
+        let text = r#"{
+            "type": "event",
+            "event": "UserJoin",
+            "data": {
+                "originatingChannel": 1234,
+                "username": "someone",
+                "roles": ["Mod", "Subscriber", "User"],
+                "id": 5678
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            ChatEventData::UserJoin(ChatUserPresence {
+                id: 5678,
+                username: "someone".to_owned(),
+                roles: vec!["Mod".to_owned(), "Subscriber".to_owned(), "User".to_owned()],
+            }),
+            ChatEventData::try_from(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn user_leave() {
+        let text = r#"{
+            "type": "event",
+            "event": "UserLeave",
+            "data": {
+                "originatingChannel": 1234,
+                "username": "someone",
+                "roles": ["Owner", "User"],
+                "id": 5678
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        match ChatEventData::try_from(&event).unwrap() {
+            ChatEventData::UserLeave(presence) => {
+                assert_eq!(5678, presence.id);
+                assert_eq!(vec!["Owner", "User"], presence.roles);
+            }
+            e => panic!("Unexpected event {:?}", e),
+        }
+    }
+
+    #[test]
+    fn delete_message() {
+        let text = r#"{
+            "type": "event",
+            "event": "DeleteMessage",
+            "data": {
+                "moderator": {
+                    "user_name": "a_mod",
+                    "user_id": 42,
+                    "user_roles": ["Mod", "User"],
+                    "user_level": 10
+                },
+                "id": "8d2bd6a0-ad2b-11e9-9d0c-b5e1e1f3dc7a"
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            ChatEventData::DeleteMessage(ChatMessageDeleted {
+                id: "8d2bd6a0-ad2b-11e9-9d0c-b5e1e1f3dc7a".to_owned(),
+                moderator: ChatModerator {
+                    user_id: 42,
+                    user_name: "a_mod".to_owned(),
+                    user_roles: vec!["Mod".to_owned(), "User".to_owned()],
+                },
+            }),
+            ChatEventData::try_from(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn clear_messages() {
+        let text = r#"{
+            "type": "event",
+            "event": "ClearMessages",
+            "data": {
+                "clearer": {
+                    "user_name": "the_owner",
+                    "user_id": 7,
+                    "user_roles": ["Owner", "User"],
+                    "user_level": 50
+                }
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            ChatEventData::ClearMessages(ChatCleared {
+                clearer: ChatModerator {
+                    user_id: 7,
+                    user_name: "the_owner".to_owned(),
+                    user_roles: vec!["Owner".to_owned(), "User".to_owned()],
+                },
+            }),
+            ChatEventData::try_from(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn poll_start() {
+        let text = r#"{
+            "type": "event",
+            "event": "PollStart",
+            "data": {
+                "originatingChannel": 1234,
+                "q": "Which map next?",
+                "answers": ["Dust", "Nuke", "Mirage"],
+                "author": {"user_name": "the_owner", "user_id": 7, "user_roles": ["Owner"]},
+                "duration": 30000,
+                "endsAt": 1563375600000,
+                "voters": 0,
+                "responses": {"Dust": 0, "Nuke": 0, "Mirage": 0},
+                "responsesByIndex": [0, 0, 0]
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            ChatEventData::PollStart(ChatPollStart {
+                question: "Which map next?".to_owned(),
+                options: vec!["Dust".to_owned(), "Nuke".to_owned(), "Mirage".to_owned()],
+                ends_at: 1_563_375_600_000,
+            }),
+            ChatEventData::try_from(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn poll_end() {
+        let text = r#"{
+            "type": "event",
+            "event": "PollEnd",
+            "data": {
+                "originatingChannel": 1234,
+                "q": "Which map next?",
+                "answers": ["Dust", "Nuke", "Mirage"],
+                "author": {"user_name": "the_owner", "user_id": 7, "user_roles": ["Owner"]},
+                "duration": 0,
+                "endsAt": 1563375600000,
+                "voters": 12,
+                "responses": {"Dust": 7, "Nuke": 4, "Mirage": 1},
+                "responsesByIndex": [7, 4, 1]
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        let end = match ChatEventData::try_from(&event).unwrap() {
+            ChatEventData::PollEnd(end) => end,
+            e => panic!("Unexpected event {:?}", e),
+        };
+        assert_eq!("Which map next?", end.question);
+        assert_eq!(12, end.voters);
+        assert_eq!(Some(&4), end.responses.get("Nuke"));
+        assert_eq!(Some("Dust"), end.winner());
+
+        let tied = ChatPollEnd {
+            responses: vec![("Dust".to_owned(), 3), ("Nuke".to_owned(), 3)]
+                .into_iter()
+                .collect(),
+            ..end.clone()
+        };
+        assert_eq!(None, tied.winner());
+        let no_votes = ChatPollEnd {
+            responses: vec![("Dust".to_owned(), 0)].into_iter().collect(),
+            ..end
+        };
+        assert_eq!(None, no_votes.winner());
+    }
+
+    #[test]
+    fn user_join_bad_payload() {
+        let event: Event = serde_json::from_value(json!({
+            "type": "event",
+            "event": "UserJoin",
+            "data": {"username": "someone"}
+        }))
+        .unwrap();
+        assert!(ChatEventData::try_from(&event).is_err());
+    }
+
+    #[test]
+    fn other_event() {
+        let event: Event = serde_json::from_value(json!({
+            "type": "event",
+            "event": "WelcomeEvent",
+            "data": {"server": "abc"}
+        }))
+        .unwrap();
+        assert_eq!(
+            ChatEventData::Other {
+                event: "WelcomeEvent".to_owned(),
+                data: Some(json!({"server": "abc"})),
+            },
+            ChatEventData::try_from(&event).unwrap()
+        );
+    }
+
     #[test]
     fn event_try_from_json() {
         let text = r#"{"type":"event","event":"foobar","data": null}"#;
-        let json: Value = serde_json::from_str(&text).unwrap();
+        let json: Value = serde_json::from_str(text).unwrap();
         let event = Event::try_from(json).unwrap();
 
         assert_eq!(event.event, "foobar");
@@ -110,7 +517,7 @@ mod tests {
     #[test]
     fn reply_try_from_json() {
         let text = r#"{"type":"reply","id":40,"data":null,"error":null}"#;
-        let json: Value = serde_json::from_str(&text).unwrap();
+        let json: Value = serde_json::from_str(text).unwrap();
         let reply = Reply::try_from(json).unwrap();
 
         assert_eq!(reply.id, 40);
@@ -127,7 +534,7 @@ mod tests {
     #[test]
     fn event_from_json() {
         let text = r#"{"type":"event","event":"hello","data":{}}"#;
-        let event: Event = serde_json::from_str(&text).unwrap();
+        let event: Event = serde_json::from_str(text).unwrap();
 
         assert_eq!("event", event.event_type);
         assert_eq!("hello", event.event);
@@ -139,7 +546,7 @@ mod tests {
     #[test]
     fn reply_from_json() {
         let text = r#"{"type":"reply","id":100,"data":{"foo":123},"error":null}"#;
-        let reply: Reply = serde_json::from_str(&text).unwrap();
+        let reply: Reply = serde_json::from_str(text).unwrap();
 
         assert_eq!("reply", reply.reply_type);
         assert_eq!(100, reply.id);
@@ -150,4 +557,43 @@ mod tests {
 
         assert_eq!(text, serde_json::to_string(&reply).unwrap());
     }
+
+    #[test]
+    fn reply_error_message() {
+        let text = r#"{"type":"reply","id":3,"data":null,"error":"UNOTFOUND"}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        let error = reply.error.as_ref().unwrap();
+        assert_eq!(&ChatError::Message("UNOTFOUND".to_owned()), error);
+        assert_eq!(None, error.code());
+        assert_eq!("UNOTFOUND", error.to_string());
+        assert_eq!(text, serde_json::to_string(&reply).unwrap());
+    }
+
+    #[test]
+    fn reply_error_detailed() {
+        let text =
+            r#"{"type":"reply","id":4,"data":null,"error":{"id":4006,"message":"Not permitted"}}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        let error = reply.error.as_ref().unwrap();
+        assert_eq!(Some(4006), error.code());
+        assert_eq!("Not permitted", error.message());
+        assert_eq!("Not permitted (error 4006)", error.to_string());
+        assert_eq!(text, serde_json::to_string(&reply).unwrap());
+    }
+
+    #[test]
+    fn extra_fields() {
+        let text = r#"{"type":"event","event":"foobar","data":null,"newField":{"a":1}}"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(Some(&json!({"a": 1})), event.extra.get("newField"));
+        let json: Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json!({"a": 1}), json["newField"]);
+        assert!(json.get("extra").is_none());
+
+        let text = r#"{"type":"reply","id":1,"data":null,"error":null,"extraReply":[1]}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        assert_eq!(1, reply.extra.len());
+        let json: Value = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json!([1]), json["extraReply"]);
+    }
 }
@@ -0,0 +1,181 @@
+use failure::Fail;
+use lazy_static::lazy_static;
+use std::{collections::HashMap, error, fmt};
+
+use super::models::MixerError;
+
+lazy_static! {
+    /// Descriptions of the error codes that Constellation can send,
+    /// either in a method reply or in a close frame.
+    ///
+    /// See https://dev.mixer.com/reference/constellation#error-codes
+    pub static ref ERRORS: HashMap<u16, &'static str> = {
+        let mut map = HashMap::new();
+        map.insert(1011, "An unknown internal error occurred");
+        map.insert(
+            1012,
+            "Constellation is being deployed or restarted; clients should reconnect",
+        );
+        map.insert(4006, "Error parsing the payload as JSON");
+        map.insert(4007, "Error decompressing a supposedly-gzipped payload");
+        map.insert(4008, "Unknown packet type");
+        map.insert(4009, "Unknown method name");
+        map.insert(
+            4010,
+            "Error parsing the method arguments (not the right type or structure)",
+        );
+        map.insert(
+            4011,
+            "The user session has expired; log in again or get a new auth token",
+        );
+        map.insert(4106, "Unknown event used in a livesubscribe call");
+        map.insert(4107, "You do not have access to subscribe to that event");
+        map.insert(4108, "You are already subscribed to that event");
+        map.insert(4109, "You are not subscribed to that event");
+        map.insert(4110, "You have reached the liveloading subscription limit");
+        map
+    };
+}
+
+/// Error from Constellation, with the description of the code
+/// looked up from the `ERRORS` map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstellationError {
+    /// Error's numeric code
+    pub code: u16,
+    /// Error's message, as sent by Constellation
+    pub message: String,
+    /// Documented description of the code, if known
+    pub description: Option<&'static str>,
+}
+
+impl ConstellationError {
+    /// Create a new error, looking up the code's description.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - numeric error code
+    /// * `message` - error message
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::constellation::errors::ConstellationError;
+    /// let err = ConstellationError::new(4106, "Unknown event");
+    /// assert!(err.description.is_some());
+    /// ```
+    pub fn new(code: u16, message: &str) -> Self {
+        ConstellationError {
+            code,
+            message: message.to_owned(),
+            description: ERRORS.get(&code).cloned(),
+        }
+    }
+
+    /// Create an error from the code and reason of a close frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - close frame code
+    /// * `reason` - close frame reason
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::constellation::errors::ConstellationError;
+    /// let err = ConstellationError::from_close(1012, "");
+    /// assert_eq!(1012, err.code);
+    /// ```
+    pub fn from_close(code: u16, reason: &str) -> Self {
+        ConstellationError::new(code, reason)
+    }
+}
+
+impl From<&MixerError> for ConstellationError {
+    fn from(err: &MixerError) -> Self {
+        ConstellationError::new(err.id, &err.message)
+    }
+}
+
+impl From<MixerError> for ConstellationError {
+    fn from(err: MixerError) -> Self {
+        ConstellationError::from(&err)
+    }
+}
+
+impl fmt::Display for ConstellationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Constellation error {}: {}", self.code, self.message)?;
+        if let Some(description) = self.description {
+            write!(f, " ({})", description)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConstellationError {}
+
+/// Error for a subscribe that would go over the client's subscription limit.
+#[derive(Debug, Fail, PartialEq)]
+#[fail(
+    display = "Subscribing would make {} subscriptions, over the limit of {}",
+    requested, limit
+)]
+pub struct SubscriptionLimitExceeded {
+    /// Configured subscription limit
+    pub limit: usize,
+    /// Number of subscriptions there would be after subscribing
+    pub requested: usize,
+}
+
+/// Error for a subscribe to events that need authentication, on a connection
+/// that Constellation reported as unauthenticated.
+///
+/// Constellation would reject these with error 4107.
+#[derive(Debug, Fail, PartialEq)]
+#[fail(
+    display = "Subscribing to {:?} requires an authenticated connection; connect with `ConstellationClient::connect_authenticated`",
+    events
+)]
+pub struct AuthenticationRequired {
+    /// Events that need authentication
+    pub events: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstellationError, ERRORS};
+    use crate::constellation::models::MixerError;
+
+    #[test]
+    fn known_codes() {
+        let err = ConstellationError::from(MixerError {
+            id: 4106,
+            message: "Unknown event".to_owned(),
+        });
+        assert_eq!(4106, err.code);
+        assert_eq!("Unknown event", err.message);
+        assert_eq!(Some(ERRORS[&4106]), err.description);
+
+        let err = ConstellationError::new(4110, "Too many subscriptions");
+        assert_eq!(Some(ERRORS[&4110]), err.description);
+    }
+
+    #[test]
+    fn unknown_code() {
+        let err = ConstellationError::new(4999, "Something happened");
+        assert_eq!(None, err.description);
+        assert_eq!(
+            "Constellation error 4999: Something happened",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn close_code() {
+        let err = ConstellationError::from_close(1012, "Restarting");
+        assert_eq!(1012, err.code);
+        assert_eq!(Some(ERRORS[&1012]), err.description);
+        assert!(format!("{}", err).contains("Restarting"));
+    }
+}
@@ -0,0 +1,336 @@
+//! Names of the events that can be subscribed to.
+//!
+//! The functions build single event names, e.g. `channel_update(123)` for
+//! `channel:123:update`; the kind enums build several names at once.
+//!
+//! See https://dev.mixer.com/reference/constellation/events
+
+/// Kinds of `channel:{id}:*` events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelEventKind {
+    /// Every kind of channel event
+    All,
+    /// `channel:{id}:update` - the channel was updated
+    Update,
+    /// `channel:{id}:followed` - a user followed or unfollowed the channel
+    Followed,
+    /// `channel:{id}:hosted` - the channel was hosted
+    Hosted,
+    /// `channel:{id}:unhosted` - the channel was unhosted
+    Unhosted,
+    /// `channel:{id}:subscribed` - a user subscribed to the channel
+    Subscribed,
+    /// `channel:{id}:resubscribed` - a user resubscribed to the channel
+    Resubscribed,
+    /// `channel:{id}:resubShared` - a user shared their resubscription
+    ResubShared,
+    /// `channel:{id}:subscriptionGifted` - a user gifted a subscription
+    SubscriptionGifted,
+    /// `channel:{id}:skill` - a skill was used in the channel
+    Skill,
+    /// `channel:{id}:patronageUpdate` - the channel's patronage was updated
+    PatronageUpdate,
+}
+
+impl ChannelEventKind {
+    const EVERY: [ChannelEventKind; 10] = [
+        ChannelEventKind::Update,
+        ChannelEventKind::Followed,
+        ChannelEventKind::Hosted,
+        ChannelEventKind::Unhosted,
+        ChannelEventKind::Subscribed,
+        ChannelEventKind::Resubscribed,
+        ChannelEventKind::ResubShared,
+        ChannelEventKind::SubscriptionGifted,
+        ChannelEventKind::Skill,
+        ChannelEventKind::PatronageUpdate,
+    ];
+
+    fn suffix(self) -> Option<&'static str> {
+        match self {
+            ChannelEventKind::All => None,
+            ChannelEventKind::Update => Some("update"),
+            ChannelEventKind::Followed => Some("followed"),
+            ChannelEventKind::Hosted => Some("hosted"),
+            ChannelEventKind::Unhosted => Some("unhosted"),
+            ChannelEventKind::Subscribed => Some("subscribed"),
+            ChannelEventKind::Resubscribed => Some("resubscribed"),
+            ChannelEventKind::ResubShared => Some("resubShared"),
+            ChannelEventKind::SubscriptionGifted => Some("subscriptionGifted"),
+            ChannelEventKind::Skill => Some("skill"),
+            ChannelEventKind::PatronageUpdate => Some("patronageUpdate"),
+        }
+    }
+
+    /// Expand the kinds into full event names for the channel.
+    ///
+    /// `All` expands to every kind, and duplicates are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `kinds` - kinds of events
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::constellation::events::ChannelEventKind;
+    /// let names = ChannelEventKind::event_names(123, &[ChannelEventKind::Update]);
+    /// assert_eq!(vec!["channel:123:update"], names);
+    /// ```
+    pub fn event_names(channel_id: usize, kinds: &[ChannelEventKind]) -> Vec<String> {
+        let suffixes = expand(kinds, &ChannelEventKind::EVERY, |k| k.suffix());
+        suffixes
+            .iter()
+            .map(|s| format!("channel:{}:{}", channel_id, s))
+            .collect()
+    }
+}
+
+/// Kinds of `user:{id}:*` events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserEventKind {
+    /// Every kind of user event
+    All,
+    /// `user:{id}:update` - the user was updated
+    Update,
+    /// `user:{id}:followed` - the user followed or unfollowed a channel
+    Followed,
+    /// `user:{id}:subscribed` - the user subscribed to a channel
+    Subscribed,
+    /// `user:{id}:resubscribed` - the user resubscribed to a channel
+    Resubscribed,
+    /// `user:{id}:achievement` - the user earned an achievement
+    Achievement,
+    /// `user:{id}:notify` - the user received a notification
+    Notify,
+}
+
+impl UserEventKind {
+    const EVERY: [UserEventKind; 6] = [
+        UserEventKind::Update,
+        UserEventKind::Followed,
+        UserEventKind::Subscribed,
+        UserEventKind::Resubscribed,
+        UserEventKind::Achievement,
+        UserEventKind::Notify,
+    ];
+
+    fn suffix(self) -> Option<&'static str> {
+        match self {
+            UserEventKind::All => None,
+            UserEventKind::Update => Some("update"),
+            UserEventKind::Followed => Some("followed"),
+            UserEventKind::Subscribed => Some("subscribed"),
+            UserEventKind::Resubscribed => Some("resubscribed"),
+            UserEventKind::Achievement => Some("achievement"),
+            UserEventKind::Notify => Some("notify"),
+        }
+    }
+
+    /// Expand the kinds into full event names for the user.
+    ///
+    /// `All` expands to every kind, and duplicates are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `kinds` - kinds of events
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::constellation::events::UserEventKind;
+    /// let names = UserEventKind::event_names(123, &[UserEventKind::Notify]);
+    /// assert_eq!(vec!["user:123:notify"], names);
+    /// ```
+    pub fn event_names(user_id: usize, kinds: &[UserEventKind]) -> Vec<String> {
+        let suffixes = expand(kinds, &UserEventKind::EVERY, |k| k.suffix());
+        suffixes
+            .iter()
+            .map(|s| format!("user:{}:{}", user_id, s))
+            .collect()
+    }
+}
+
+fn channel_event(channel_id: usize, kind: ChannelEventKind) -> String {
+    format!(
+        "channel:{}:{}",
+        channel_id,
+        kind.suffix().unwrap_or_default()
+    )
+}
+
+fn user_event(user_id: usize, kind: UserEventKind) -> String {
+    format!("user:{}:{}", user_id, kind.suffix().unwrap_or_default())
+}
+
+/// Name of the event for when the channel was updated.
+pub fn channel_update(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Update)
+}
+
+/// Name of the event for when a user followed or unfollowed the channel.
+pub fn channel_followed(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Followed)
+}
+
+/// Name of the event for when the channel was hosted.
+pub fn channel_hosted(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Hosted)
+}
+
+/// Name of the event for when the channel was unhosted.
+pub fn channel_unhosted(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Unhosted)
+}
+
+/// Name of the event for when a user subscribed to the channel.
+pub fn channel_subscribed(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Subscribed)
+}
+
+/// Name of the event for when a user resubscribed to the channel.
+pub fn channel_resubscribed(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Resubscribed)
+}
+
+/// Name of the event for when a user shared their resubscription.
+pub fn channel_resub_shared(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::ResubShared)
+}
+
+/// Name of the event for when a user gifted a subscription.
+pub fn channel_subscription_gifted(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::SubscriptionGifted)
+}
+
+/// Name of the event for when a skill was used in the channel.
+pub fn channel_skill(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::Skill)
+}
+
+/// Name of the event for when the channel's patronage was updated.
+pub fn channel_patronage_update(channel_id: usize) -> String {
+    channel_event(channel_id, ChannelEventKind::PatronageUpdate)
+}
+
+/// Name of the event for when the user was updated.
+pub fn user_update(user_id: usize) -> String {
+    user_event(user_id, UserEventKind::Update)
+}
+
+/// Name of the event for when the user followed or unfollowed a channel.
+pub fn user_followed(user_id: usize) -> String {
+    user_event(user_id, UserEventKind::Followed)
+}
+
+/// Name of the event for when the user subscribed to a channel.
+pub fn user_subscribed(user_id: usize) -> String {
+    user_event(user_id, UserEventKind::Subscribed)
+}
+
+/// Name of the event for when the user resubscribed to a channel.
+pub fn user_resubscribed(user_id: usize) -> String {
+    user_event(user_id, UserEventKind::Resubscribed)
+}
+
+/// Name of the event for when the user earned an achievement.
+pub fn user_achievement(user_id: usize) -> String {
+    user_event(user_id, UserEventKind::Achievement)
+}
+
+/// Name of the event for when the user received a notification.
+pub fn user_notify(user_id: usize) -> String {
+    user_event(user_id, UserEventKind::Notify)
+}
+
+/// Name of the event for system-wide announcements.
+pub fn announcement() -> String {
+    "announcement:announce".to_owned()
+}
+
+/// Expand kinds into their name suffixes, in order and without duplicates;
+/// a kind without a suffix stands for every kind.
+fn expand<K: Copy>(
+    kinds: &[K],
+    every: &[K],
+    suffix: impl Fn(&K) -> Option<&'static str>,
+) -> Vec<&'static str> {
+    let mut suffixes = Vec::new();
+    for kind in kinds {
+        let names: Vec<&'static str> = match suffix(kind) {
+            Some(s) => vec![s],
+            None => every.iter().filter_map(&suffix).collect(),
+        };
+        for name in names {
+            if !suffixes.contains(&name) {
+                suffixes.push(name);
+            }
+        }
+    }
+    suffixes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        announcement, channel_followed, channel_resub_shared, channel_update, user_notify,
+        ChannelEventKind, UserEventKind,
+    };
+
+    #[test]
+    fn single_event_names() {
+        assert_eq!("channel:1:update", channel_update(1));
+        assert_eq!("channel:1:followed", channel_followed(1));
+        assert_eq!("channel:1:resubShared", channel_resub_shared(1));
+        assert_eq!("user:2:notify", user_notify(2));
+        assert_eq!("announcement:announce", announcement());
+    }
+
+    #[test]
+    fn channel_event_names() {
+        assert_eq!(
+            vec!["channel:1:update", "channel:1:followed"],
+            ChannelEventKind::event_names(
+                1,
+                &[
+                    ChannelEventKind::Update,
+                    ChannelEventKind::Followed,
+                    ChannelEventKind::Update
+                ]
+            )
+        );
+        assert!(ChannelEventKind::event_names(1, &[]).is_empty());
+    }
+
+    #[test]
+    fn channel_event_names_all() {
+        let names =
+            ChannelEventKind::event_names(1, &[ChannelEventKind::Skill, ChannelEventKind::All]);
+        assert_eq!(10, names.len());
+        assert_eq!("channel:1:skill", names[0]);
+        assert!(names.contains(&"channel:1:update".to_owned()));
+        assert!(names.contains(&"channel:1:resubShared".to_owned()));
+        assert!(names.contains(&"channel:1:patronageUpdate".to_owned()));
+    }
+
+    #[test]
+    fn user_event_names() {
+        assert_eq!(
+            vec!["user:2:achievement"],
+            UserEventKind::event_names(2, &[UserEventKind::Achievement])
+        );
+        assert_eq!(
+            vec![
+                "user:2:update",
+                "user:2:followed",
+                "user:2:subscribed",
+                "user:2:resubscribed",
+                "user:2:achievement",
+                "user:2:notify"
+            ],
+            UserEventKind::event_names(2, &[UserEventKind::All])
+        );
+    }
+}
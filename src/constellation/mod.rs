@@ -4,17 +4,52 @@
 //!
 //! [ConstellationClient]: struct.ConstellationClient.html
 
+/// Error codes and types
+pub mod errors;
+/// Names of the events that can be subscribed to
+pub mod events;
 /// Static models for the JSON data
 pub mod models;
+mod replay;
+mod stats;
+mod subscriptions;
 
-use crate::internal::{connect as socket_connect, ClientSocketWrapper};
-use atomic_counter::AtomicCounter;
+pub use crate::internal::{BroadcastReceiver, ConnectOptions, ConnectionState};
+pub use stats::ConstellationStats;
+pub use subscriptions::SubscriptionResult;
+
+use crate::internal::{
+    connect as socket_connect, ClientSocketWrapper, MessageObserver, ReconnectHook,
+};
+use atomic_counter::{AtomicCounter, ConsistentCounter};
 use failure::{format_err, Error};
-use log::debug;
+use log::{debug, error, info, warn};
 use serde_json::{json, Value};
-use std::{collections::HashMap, convert::TryFrom, sync::mpsc::Receiver, thread::JoinHandle};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    sync::{
+        mpsc::{channel, Receiver, RecvError, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use url::Url;
+use ws::{CloseCode, Sender as SocketSender};
 
+use errors::{AuthenticationRequired, SubscriptionLimitExceeded};
+use events::{ChannelEventKind, UserEventKind};
 use models::{Event, Method, Reply};
+use replay::ReplayBuffer;
+use stats::StatsCounters;
+use subscriptions::Subscriptions;
+
+/// Default Constellation endpoint.
+pub const ENDPOINT: &str = "wss://constellation.mixer.com";
+/// Most events sent in each call by `resubscribe`, `subscribe_channel_all`,
+/// and `unsubscribe_all`
+const SUBSCRIPTION_BATCH_SIZE: usize = 50;
 
 /// Possible messages from the socket.
 pub enum StreamMessage {
@@ -25,35 +60,655 @@ pub enum StreamMessage {
 }
 
 /// Wrapper for connecting and interacting with Constellation.
+///
+/// Dropping the client closes the connection, which ends the internal thread.
 pub struct ConstellationClient {
     client: ClientSocketWrapper,
-    /// Internal thread join handle
-    pub join_handle: JoinHandle<()>,
+    /// Internal thread join handle; `take` it to join the thread after the
+    /// client is dropped, or use `join`
+    pub join_handle: Option<JoinHandle<()>>,
+    subscriptions: Arc<Mutex<Subscriptions>>,
+    subscription_limit: Option<usize>,
+    stats: Arc<StatsCounters>,
+    replay: Arc<Mutex<Option<ReplayBuffer>>>,
+    authenticated: Arc<Mutex<Option<bool>>>,
 }
 
-impl ConstellationClient {
-    /// Connect to Constellation.
+impl ConstellationClient {
+    /// Connect to Constellation.
+    ///
+    /// The client ID is sent when connecting, so changing it requires
+    /// connecting again.
+    ///
+    /// Constellation sends a `hello` event once the connection is ready; wait for
+    /// it (see `is_ready` and `is_welcome`) before calling methods, as ones sent
+    /// earlier may not be answered.
+    ///
+    /// When Constellation closes the connection with code 1012 because it's being
+    /// deployed, the client reconnects after a short delay and subscribes to the
+    /// same events again (see `resubscribe`). This can be turned off with the
+    /// `reconnect_on_deploy` connection option.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect("aaa").unwrap();
+    /// ```
+    pub fn connect(client_id: &str) -> Result<(Self, Receiver<String>), Error> {
+        ConstellationClient::connect_with_options(client_id, &ConnectOptions::default())
+    }
+
+    /// Connect to Constellation with an OAuth access token, which is needed
+    /// to subscribe to `user:{id}:*` events.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    /// * `access_token` - OAuth access token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect_authenticated("aaa", "bbb").unwrap();
+    /// ```
+    pub fn connect_authenticated(
+        client_id: &str,
+        access_token: &str,
+    ) -> Result<(Self, Receiver<String>), Error> {
+        let options = ConnectOptions::builder()
+            .access_token(Some(access_token.to_owned()))
+            .build();
+        ConstellationClient::connect_with_options(client_id, &options)
+    }
+
+    /// Connect to Constellation with additional options.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    /// * `options` - connection options
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::constellation::{ConnectOptions, ConstellationClient};
+    /// use std::time::Duration;
+    /// let options = ConnectOptions::builder()
+    ///     .keepalive(Duration::from_secs(30))
+    ///     .build();
+    /// let (client, receiver) = ConstellationClient::connect_with_options("aaa", &options).unwrap();
+    /// ```
+    pub fn connect_with_options(
+        client_id: &str,
+        options: &ConnectOptions,
+    ) -> Result<(Self, Receiver<String>), Error> {
+        ConstellationClient::connect_to_with_options(ENDPOINT, client_id, options)
+    }
+
+    /// Connect to Constellation, returning a receiver that can be cloned so
+    /// that several threads each get every message.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect_broadcast("aaa").unwrap();
+    /// let metrics = receiver.clone();
+    /// ```
+    pub fn connect_broadcast(client_id: &str) -> Result<(Self, BroadcastReceiver), Error> {
+        let (client, receiver) = ConstellationClient::connect(client_id)?;
+        Ok((client, BroadcastReceiver::new(receiver)))
+    }
+
+    /// Connect to Constellation, with events and replies parsed and sent to
+    /// separate receivers.
+    ///
+    /// Messages that can't be parsed are logged and counted in `stats` as
+    /// `invalid_received`, rather than being sent to either receiver.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ConstellationClient;
+    /// let (client, events, replies) = ConstellationClient::connect_split("aaa").unwrap();
+    /// for event in events {
+    ///     println!("{}: {:?}", event.event, event.data);
+    /// }
+    /// ```
+    pub fn connect_split(
+        client_id: &str,
+    ) -> Result<(Self, Receiver<Event>, Receiver<Reply>), Error> {
+        ConstellationClient::connect_split_with_options(client_id, &ConnectOptions::default())
+    }
+
+    /// Connect to Constellation with additional options, with events and
+    /// replies parsed and sent to separate receivers.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your client ID
+    /// * `options` - connection options
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::constellation::{ConnectOptions, ConstellationClient};
+    /// let options = ConnectOptions::builder()
+    ///     .access_token(Some("bbb".to_owned()))
+    ///     .build();
+    /// let (client, events, replies) =
+    ///     ConstellationClient::connect_split_with_options("aaa", &options).unwrap();
+    /// ```
+    pub fn connect_split_with_options(
+        client_id: &str,
+        options: &ConnectOptions,
+    ) -> Result<(Self, Receiver<Event>, Receiver<Reply>), Error> {
+        ConstellationClient::connect_to_split_with_options(ENDPOINT, client_id, options)
+    }
+
+    /// Connect to a Constellation-compatible endpoint, with events and replies
+    /// parsed and sent to separate receivers.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - `ws://` or `wss://` URL to connect to
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ConstellationClient;
+    /// let (client, events, replies) =
+    ///     ConstellationClient::connect_to_split("ws://localhost:8080", "aaa").unwrap();
+    /// ```
+    pub fn connect_to_split(
+        endpoint: &str,
+        client_id: &str,
+    ) -> Result<(Self, Receiver<Event>, Receiver<Reply>), Error> {
+        ConstellationClient::connect_to_split_with_options(
+            endpoint,
+            client_id,
+            &ConnectOptions::default(),
+        )
+    }
+
+    /// Connect to a Constellation-compatible endpoint with additional options,
+    /// with events and replies parsed and sent to separate receivers.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - `ws://` or `wss://` URL to connect to
+    /// * `client_id` - your client ID
+    /// * `options` - connection options
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::constellation::{ConnectOptions, ConstellationClient};
+    /// let (client, events, replies) = ConstellationClient::connect_to_split_with_options(
+    ///     "ws://localhost:8080",
+    ///     "aaa",
+    ///     &ConnectOptions::default(),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn connect_to_split_with_options(
+        endpoint: &str,
+        client_id: &str,
+        options: &ConnectOptions,
+    ) -> Result<(Self, Receiver<Event>, Receiver<Reply>), Error> {
+        let (client, receiver) =
+            ConstellationClient::connect_to_with_options(endpoint, client_id, options)?;
+        let (event_send, event_recv) = channel();
+        let (reply_send, reply_recv) = channel();
+        // ends when the connection's thread drops its sender
+        thread::spawn(move || {
+            for message in receiver {
+                match ConstellationClient::parse(&message) {
+                    Ok(StreamMessage::Event(event)) => {
+                        let _ = event_send.send(event);
+                    }
+                    Ok(StreamMessage::Reply(reply)) => {
+                        let _ = reply_send.send(reply);
+                    }
+                    Err(e) => warn!("Could not parse message '{}': {}", message, e),
+                }
+            }
+        });
+        Ok((client, event_recv, reply_recv))
+    }
+
+    /// Connect to a Constellation-compatible endpoint other than the default.
+    ///
+    /// This is useful for pointing the client at a local mock server in tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - `ws://` or `wss://` URL to connect to
+    /// * `client_id` - your client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect_to("ws://localhost:8080", "aaa").unwrap();
+    /// ```
+    pub fn connect_to(endpoint: &str, client_id: &str) -> Result<(Self, Receiver<String>), Error> {
+        ConstellationClient::connect_to_with_options(
+            endpoint,
+            client_id,
+            &ConnectOptions::default(),
+        )
+    }
+
+    /// Connect to a Constellation-compatible endpoint with additional options.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - `ws://` or `wss://` URL to connect to
+    /// * `client_id` - your client ID
+    /// * `options` - connection options
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mixer_wrappers::constellation::{ConnectOptions, ConstellationClient};
+    /// let (client, receiver) = ConstellationClient::connect_to_with_options(
+    ///     "ws://localhost:8080",
+    ///     "aaa",
+    ///     &ConnectOptions::default(),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn connect_to_with_options(
+        endpoint: &str,
+        client_id: &str,
+        options: &ConnectOptions,
+    ) -> Result<(Self, Receiver<String>), Error> {
+        let url = Url::parse(endpoint)?;
+        if url.scheme() != "ws" && url.scheme() != "wss" {
+            return Err(format_err!(
+                "Endpoint must be a ws:// or wss:// URL, got '{}'",
+                endpoint
+            ));
+        }
+        let subscriptions = Arc::new(Mutex::new(Subscriptions::default()));
+        let stats = Arc::new(StatsCounters::default());
+        let replay: Arc<Mutex<Option<ReplayBuffer>>> = Arc::new(Mutex::new(None));
+        let observer_subscriptions = Arc::clone(&subscriptions);
+        let observer_stats = Arc::clone(&stats);
+        let observer_replay = Arc::clone(&replay);
+        let authenticated: Arc<Mutex<Option<bool>>> = Arc::new(Mutex::new(None));
+        let observer_authenticated = Arc::clone(&authenticated);
+        let observer: MessageObserver = Arc::new(move |message: &str| {
+            let json: Value = serde_json::from_str(message).unwrap_or_default();
+            observer_stats.record(&json, message.len());
+            observer_subscriptions.lock().unwrap().on_message(&json);
+            if let Some(replay) = observer_replay.lock().unwrap().as_mut() {
+                replay.on_message(&json);
+            }
+            if is_hello(&json) {
+                *observer_authenticated.lock().unwrap() =
+                    Some(json["data"]["authenticated"].as_bool().unwrap_or(false));
+            }
+        });
+        let reconnect_subscriptions = Arc::clone(&subscriptions);
+        let reconnect_stats = Arc::clone(&stats);
+        let reconnect_authenticated = Arc::clone(&authenticated);
+        let on_reconnect: ReconnectHook = Arc::new(move |socket_out, method_counter| {
+            reconnect_stats.record_reconnect();
+            // the new connection gets its own hello event
+            *reconnect_authenticated.lock().unwrap() = None;
+            resubscribe(&reconnect_subscriptions, socket_out, method_counter);
+        });
+        let (client, join_handle, receiver) = socket_connect(
+            endpoint,
+            client_id,
+            options,
+            Some(observer),
+            Some(on_reconnect),
+        )?;
+        Ok((
+            ConstellationClient {
+                client,
+                join_handle: Some(join_handle),
+                subscriptions,
+                subscription_limit: None,
+                stats,
+                replay,
+                authenticated,
+            },
+            receiver,
+        ))
+    }
+
+    /// Block until the connection to Constellation ends.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect("").unwrap();
+    /// client.join().expect("Could not join thread");
+    /// ```
+    pub fn join(mut self) -> thread::Result<()> {
+        match self.join_handle.take() {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of methods sent to Constellation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let count = client.methods_sent();
+    /// ```
+    pub fn methods_sent(&self) -> usize {
+        self.client.methods_sent()
+    }
+
+    /// Number of messages received from Constellation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let count = client.messages_received();
+    /// ```
+    pub fn messages_received(&self) -> u64 {
+        self.client.messages_received()
+    }
+
+    /// Number of bytes received from Constellation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let bytes = client.bytes_received();
+    /// ```
+    pub fn bytes_received(&self) -> u64 {
+        self.client.bytes_received()
+    }
+
+    /// How long the current connection to Constellation has been open.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let uptime = client.uptime();
+    /// ```
+    pub fn uptime(&self) -> Duration {
+        self.client.uptime()
+    }
+
+    /// Id the next method will have.
+    ///
+    /// Ids keep counting up across reconnects after a deploy. When replacing
+    /// the client with a new connection, pass this as the `method_id_start`
+    /// option so that replies to the old client's methods can't be mistaken
+    /// for replies to the new one's.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{constellation::ConnectOptions, ConstellationClient};
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let options = ConnectOptions::builder()
+    ///     .method_id_start(client.peek_method_id())
+    ///     .build();
+    /// drop(client);
+    /// let (client, receiver) = ConstellationClient::connect_with_options("", &options).unwrap();
+    /// ```
+    pub fn peek_method_id(&self) -> usize {
+        self.client.peek_method_id()
+    }
+
+    /// Whether Constellation has stopped answering keepalive pings.
+    ///
+    /// Always `false` unless the `keepalive` connection option is set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// if client.is_stale() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn is_stale(&self) -> bool {
+        self.client.is_stale()
+    }
+
+    /// Current state of the connection to Constellation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// let state = client.state();
+    /// ```
+    pub fn state(&mut self) -> ConnectionState {
+        self.client.state()
+    }
+
+    /// Code and reason of the close frame, if the connection to Constellation
+    /// has been closed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// if let Some((code, reason)) = client.last_close() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn last_close(&self) -> Option<(u16, String)> {
+        self.client.last_close()
+    }
+
+    /// Whether Constellation has sent its `hello` event, after which the
+    /// connection is ready for method calls.
+    ///
+    /// Goes back to `false` when the client reconnects, until the new
+    /// connection's `hello` event arrives.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # use std::{thread, time::Duration};
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// while !client.is_ready() {
+    ///     thread::sleep(Duration::from_millis(50));
+    /// }
+    /// client.subscribe(&["channel:123:update"]).unwrap();
+    /// ```
+    pub fn is_ready(&self) -> bool {
+        self.authenticated.lock().unwrap().is_some()
+    }
+
+    /// Whether Constellation reported the connection as authenticated
+    /// in its `hello` event.
+    ///
+    /// `false` until the `hello` event has been received.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect_authenticated("", "").unwrap();
+    /// if client.is_authenticated() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn is_authenticated(&self) -> bool {
+        *self.authenticated.lock().unwrap() == Some(true)
+    }
+
+    /// Snapshot of the counters for the messages received from Constellation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let stats = client.stats();
+    /// println!("{} events received", stats.events_received);
+    /// ```
+    pub fn stats(&self) -> ConstellationStats {
+        self.stats.snapshot()
+    }
+
+    /// Keep the most recent events so that they can be fetched with `replay`,
+    /// e.g. by a consumer that was restarted and missed them.
+    ///
+    /// Only the newest `capacity` events are kept; calling this again
+    /// changes the capacity without losing the newest events.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - maximum number of events to keep
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.enable_replay(100);
+    /// ```
+    pub fn enable_replay(&mut self, capacity: usize) {
+        let mut replay = self.replay.lock().unwrap();
+        match replay.as_mut() {
+            Some(buffer) => buffer.resize(capacity),
+            None => *replay = Some(ReplayBuffer::new(capacity)),
+        }
+    }
+
+    /// The events kept since `enable_replay` was called, oldest first.
+    ///
+    /// Always empty if `enable_replay` hasn't been called.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// # client.enable_replay(100);
+    /// for event in client.replay() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn replay(&self) -> Vec<Event> {
+        match self.replay.lock().unwrap().as_ref() {
+            Some(buffer) => buffer.events(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Take the result of a subscribe or unsubscribe, once Constellation has replied to it.
+    ///
+    /// Subscribing to an event that's already subscribed to (error 4108), or unsubscribing
+    /// from one that isn't (error 4109), is treated as success and reported separately
+    /// from the events that failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `method_id` - id returned by `subscribe` or `unsubscribe`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// let id = client.subscribe(&["channel:123:update"]).unwrap();
+    /// // ... once the reply has been received
+    /// if let Some(result) = client.take_subscription_result(id) {
+    ///     if !result.failed.is_empty() {
+    ///         // ...
+    ///     }
+    /// }
+    /// ```
+    pub fn take_subscription_result(&self, method_id: usize) -> Option<SubscriptionResult> {
+        self.subscriptions.lock().unwrap().take_result(method_id)
+    }
+
+    /// Take the errors from compressed payloads that couldn't be decompressed
+    /// since the last call.
+    ///
+    /// Always empty unless the `gzip` connection option is set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// for error in client.take_decode_errors() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn take_decode_errors(&mut self) -> Vec<String> {
+        self.client.take_decode_errors()
+    }
+
+    /// Number of subscriptions that Constellation has confirmed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (client, _) = ConstellationClient::connect("").unwrap();
+    /// let count = client.subscription_count();
+    /// ```
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.lock().unwrap().count()
+    }
+
+    /// Set the maximum number of subscriptions, checked locally before sending
+    /// `livesubscribe` calls.
+    ///
+    /// Subscribes that would go over the limit return a `SubscriptionLimitExceeded`
+    /// error without being sent, rather than being rejected by Constellation with
+    /// error 4110.
     ///
     /// # Arguments
     ///
-    /// * `client_id` - your client ID
+    /// * `limit` - maximum number of subscriptions
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use mixer_wrappers::ConstellationClient;
-    /// let (client, receiver) = ConstellationClient::connect("aaa").unwrap();
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.set_subscription_limit(500);
     /// ```
-    pub fn connect(client_id: &str) -> Result<(Self, Receiver<String>), Error> {
-        let (client, join_handle, receiver) =
-            socket_connect("wss://constellation.mixer.com", client_id)?;
-        Ok((
-            ConstellationClient {
-                client,
-                join_handle,
-            },
-            receiver,
-        ))
+    pub fn set_subscription_limit(&mut self, limit: usize) {
+        self.subscription_limit = Some(limit);
     }
 
     /// Call a method, sending data to the socket.
@@ -81,53 +736,232 @@ impl ConstellationClient {
         method: &str,
         params: &HashMap<String, Value>,
     ) -> Result<(), Error> {
+        self.send_method(method, params)?;
+        Ok(())
+    }
+
+    /// Send a method to the socket, returning its id.
+    fn send_method(
+        &mut self,
+        method: &str,
+        params: &HashMap<String, Value>,
+    ) -> Result<usize, Error> {
         if !self.client.check_connection() {
             return Err(format_err!("Not connected to socket"));
         }
+        let id = self.client.next_method_id();
         let to_send = Method {
             method_type: "method".to_owned(),
             method: method.to_owned(),
             params: params.to_owned(),
-            id: self.client.method_counter.inc(),
+            id,
         };
         debug!("Sending method call to socket: {:?}", to_send);
-        self.client
-            .socket_out
-            .send(serde_json::to_string(&to_send)?)?;
-        Ok(())
+        self.client.send(serde_json::to_string(&to_send)?)?;
+        Ok(id)
+    }
+
+    /// Send a subscription change and track it until its reply arrives.
+    fn send_subscription(
+        &mut self,
+        method: &str,
+        subscribe: bool,
+        events: &[&str],
+    ) -> Result<usize, Error> {
+        let mut map = HashMap::new();
+        map.insert("events".to_owned(), json!(events));
+        // hold the lock until the method is tracked so the reply can't be handled first
+        let subscriptions = Arc::clone(&self.subscriptions);
+        let mut subscriptions = subscriptions.lock().unwrap();
+        if subscribe {
+            if let Some(limit) = self.subscription_limit {
+                let requested = subscriptions.count_with(events);
+                if requested > limit {
+                    return Err(SubscriptionLimitExceeded { limit, requested }.into());
+                }
+            }
+        }
+        let id = self.send_method(method, &map)?;
+        subscriptions.track(id, subscribe, events);
+        Ok(id)
     }
 
     /// Subscribe to events.
     ///
     /// The documentation on this method is found [here], as well as a [listing of events].
     ///
+    /// Returns the method's id, which can be passed to `take_subscription_result`
+    /// once Constellation has replied.
+    ///
     /// # Arguments
     ///
-    /// * `events` - slice of event names to subscribe to
+    /// * `events` - slice of event names to subscribe to, e.g. from the
+    ///   functions in the `events` module
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// # use mixer_wrappers::ConstellationClient;
+    /// # use mixer_wrappers::{constellation::events, ConstellationClient};
     /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
     /// client.subscribe(&["aaa", "bbb"]).unwrap();
+    /// client.subscribe(&[events::channel_update(123), events::channel_followed(123)]).unwrap();
     /// ```
     ///
     /// [here]: https://dev.mixer.com/reference/constellation/methods/livesubscribe
     /// [listing of events]: https://dev.mixer.com/reference/constellation/events
-    pub fn subscribe(&mut self, events: &[&str]) -> Result<(), Error> {
-        let mut map = HashMap::new();
-        map.insert("events".to_owned(), json!(events));
-        self.call_method("livesubscribe", &map)
+    pub fn subscribe<S: AsRef<str>>(&mut self, events: &[S]) -> Result<usize, Error> {
+        self.subscribe_with_force(events, false)
+    }
+
+    /// Subscribe to events, optionally skipping the local authentication check.
+    ///
+    /// Unless `force` is set, subscribing to `user:{id}:*` events after Constellation
+    /// has reported the connection as unauthenticated returns an `AuthenticationRequired`
+    /// error without being sent, rather than being rejected by Constellation with
+    /// error 4107. Subscribes sent before the `hello` event arrives aren't checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - slice of event names to subscribe to
+    /// * `force` - send the subscribe even if the events need authentication
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe_with_force(&["user:123:update"], true).unwrap();
+    /// ```
+    pub fn subscribe_with_force<S: AsRef<str>>(
+        &mut self,
+        events: &[S],
+        force: bool,
+    ) -> Result<usize, Error> {
+        let events: Vec<&str> = events.iter().map(AsRef::as_ref).collect();
+        if !force && *self.authenticated.lock().unwrap() == Some(false) {
+            let needs_auth: Vec<String> = events
+                .iter()
+                .filter(|e| e.starts_with("user:"))
+                .map(|&e| e.to_owned())
+                .collect();
+            if !needs_auth.is_empty() {
+                return Err(AuthenticationRequired { events: needs_auth }.into());
+            }
+        }
+        self.send_subscription("livesubscribe", true, &events)
+    }
+
+    /// Subscribe to events for a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `kinds` - kinds of channel events; `ChannelEventKind::All` subscribes to all of them
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{constellation::events::ChannelEventKind, ConstellationClient};
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe_channel(123, &[ChannelEventKind::Update, ChannelEventKind::Followed]).unwrap();
+    /// ```
+    pub fn subscribe_channel(
+        &mut self,
+        channel_id: usize,
+        kinds: &[ChannelEventKind],
+    ) -> Result<usize, Error> {
+        let names = ChannelEventKind::event_names(channel_id, kinds);
+        self.subscribe(&names)
+    }
+
+    /// Subscribe to every kind of `channel:{id}:*` event, e.g. for a dashboard.
+    ///
+    /// The events are the ones from `ChannelEventKind::All`, sent in batches of
+    /// up to 50 per `livesubscribe` call. Returns the ids of the methods.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// let ids = client.subscribe_channel_all(123).unwrap();
+    /// ```
+    pub fn subscribe_channel_all(&mut self, channel_id: usize) -> Result<Vec<usize>, Error> {
+        let names = ChannelEventKind::event_names(channel_id, &[ChannelEventKind::All]);
+        self.subscribe_batched(&names)
+    }
+
+    /// Subscribe to events for a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `kinds` - kinds of user events; `UserEventKind::All` subscribes to all of them
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{constellation::events::UserEventKind, ConstellationClient};
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe_user(123, &[UserEventKind::All]).unwrap();
+    /// ```
+    pub fn subscribe_user(
+        &mut self,
+        user_id: usize,
+        kinds: &[UserEventKind],
+    ) -> Result<usize, Error> {
+        let names = UserEventKind::event_names(user_id, kinds);
+        self.subscribe(&names)
+    }
+
+    /// Subscribe to a channel's patronage updates, for tracking milestone progress.
+    ///
+    /// The events can be parsed into `ConstellationEvent::PatronageUpdate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe_patronage(123).unwrap();
+    /// ```
+    pub fn subscribe_patronage(&mut self, channel_id: usize) -> Result<usize, Error> {
+        self.subscribe_channel(channel_id, &[ChannelEventKind::PatronageUpdate])
+    }
+
+    /// Subscribe to system-wide announcements, e.g. maintenance notices.
+    ///
+    /// The events can be parsed into `ConstellationEvent::Announcement`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe_announcements().unwrap();
+    /// ```
+    pub fn subscribe_announcements(&mut self) -> Result<usize, Error> {
+        self.subscribe(&[events::announcement()])
     }
 
     /// Unsubscribe from events.
     ///
     /// The documentation on this method is found [here], as well as a [listing of events].
     ///
+    /// Returns the method's id, which can be passed to `take_subscription_result`
+    /// once Constellation has replied.
+    ///
     /// # Arguments
     ///
-    /// * `events` - slice of event names to subscribe to
+    /// * `events` - slice of event names to unsubscribe from
     ///
     /// # Examples
     ///
@@ -139,10 +973,119 @@ impl ConstellationClient {
     ///
     /// [here]: https://dev.mixer.com/reference/constellation/methods/liveunsubscribe
     /// [listing of events]: https://dev.mixer.com/reference/constellation/events
-    pub fn unsubscribe(&mut self, events: &[&str]) -> Result<(), Error> {
-        let mut map = HashMap::new();
-        map.insert("events".to_owned(), json!(events));
-        self.call_method("liveunsubscribe", &map)
+    pub fn unsubscribe<S: AsRef<str>>(&mut self, events: &[S]) -> Result<usize, Error> {
+        let events: Vec<&str> = events.iter().map(AsRef::as_ref).collect();
+        self.send_subscription("liveunsubscribe", false, &events)
+    }
+
+    /// Subscribe again to every event that's subscribed to, or will be once
+    /// pending subscribes are confirmed.
+    ///
+    /// After a reconnect for a deploy the client does this by itself; call it
+    /// when that failed (it's logged as an error), or to make sure that
+    /// Constellation's subscriptions match the client's. Events that are still
+    /// subscribed to are reported in `already_subscribed` by
+    /// `take_subscription_result`, and are kept.
+    ///
+    /// The events are sent in batches of up to 50 per `livesubscribe` call.
+    /// Returns the ids of the methods; there are none if nothing is subscribed to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe(&["aaa", "bbb"]).unwrap();
+    /// let ids = client.resubscribe().unwrap();
+    /// ```
+    pub fn resubscribe(&mut self) -> Result<Vec<usize>, Error> {
+        let events = self.subscriptions.lock().unwrap().events();
+        self.subscribe_batched(&events)
+    }
+
+    /// Subscribe to events in batches, returning the ids of the methods.
+    fn subscribe_batched(&mut self, events: &[String]) -> Result<Vec<usize>, Error> {
+        events
+            .chunks(SUBSCRIPTION_BATCH_SIZE)
+            .map(|batch| self.subscribe(batch))
+            .collect()
+    }
+
+    /// Unsubscribe from every event that's subscribed to, or will be once
+    /// pending subscribes are confirmed, e.g. before a clean shutdown.
+    ///
+    /// The events are sent in batches of up to 50 per `liveunsubscribe` call.
+    /// Returns the ids of the methods, which can be passed to
+    /// `take_subscription_result`; there are none if nothing is subscribed to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # let (mut client, _) = ConstellationClient::connect("").unwrap();
+    /// client.subscribe(&["aaa", "bbb"]).unwrap();
+    /// let ids = client.unsubscribe_all().unwrap();
+    /// ```
+    pub fn unsubscribe_all(&mut self) -> Result<Vec<usize>, Error> {
+        let events = self.subscriptions.lock().unwrap().events();
+        events
+            .chunks(SUBSCRIPTION_BATCH_SIZE)
+            .map(|batch| self.unsubscribe(batch))
+            .collect()
+    }
+
+    /// Take the next message from the receiver without blocking, and parse it.
+    ///
+    /// Returns `Ok(None)` if there's no message waiting, and `Err` once the
+    /// connection has ended and every message has been taken.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - receiver returned when connecting
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{constellation::StreamMessage, ConstellationClient};
+    /// let (client, receiver) = ConstellationClient::connect("").unwrap();
+    /// loop {
+    ///     match ConstellationClient::try_next(&receiver) {
+    ///         Ok(Some(Ok(StreamMessage::Event(event)))) => println!("{}", event.event),
+    ///         Ok(Some(Ok(StreamMessage::Reply(_)))) => {}
+    ///         Ok(Some(Err(e))) => eprintln!("{}", e),
+    ///         Ok(None) => {}
+    ///         Err(_) => break,
+    ///     }
+    /// }
+    /// ```
+    pub fn try_next(
+        receiver: &Receiver<String>,
+    ) -> Result<Option<Result<StreamMessage, Error>>, RecvError> {
+        match receiver.try_recv() {
+            Ok(message) => Ok(Some(ConstellationClient::parse(&message))),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(RecvError),
+        }
+    }
+
+    /// Whether a message from the receiver is Constellation's `hello` event,
+    /// which is sent once the connection is ready for method calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - String message from the receiver
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::ConstellationClient;
+    /// let message = r#"{"type":"event","event":"hello","data":{"authenticated":false}}"#;
+    /// assert!(ConstellationClient::is_welcome(message));
+    /// ```
+    pub fn is_welcome(message: &str) -> bool {
+        serde_json::from_str(message)
+            .map(|json: Value| is_hello(&json))
+            .unwrap_or(false)
     }
 
     /// Helper method to parse the JSON messages into structs.
@@ -179,5 +1122,527 @@ impl ConstellationClient {
     }
 }
 
+/// Whether a parsed message is the `hello` event.
+fn is_hello(json: &Value) -> bool {
+    json["type"] == "event" && json["event"] == "hello"
+}
+
+/// Subscribe a new connection to the events that the closed one was subscribed to.
+fn resubscribe(
+    subscriptions: &Mutex<Subscriptions>,
+    socket_out: &SocketSender,
+    method_counter: &ConsistentCounter,
+) {
+    let mut subscriptions = subscriptions.lock().unwrap();
+    let events = subscriptions.reset();
+    if events.is_empty() {
+        return;
+    }
+    info!("Resubscribing to {} events", events.len());
+    let mut params = HashMap::new();
+    params.insert("events".to_owned(), json!(events));
+    let method = Method {
+        method_type: "method".to_owned(),
+        method: "livesubscribe".to_owned(),
+        params,
+        id: method_counter.inc(),
+    };
+    let sent = serde_json::to_string(&method)
+        .map_err(Error::from)
+        .and_then(|text| socket_out.send(text).map_err(Error::from));
+    if let Err(e) = sent {
+        error!("Could not resubscribe: {}", e);
+    }
+    // tracked even if sending failed, so that `resubscribe` can send the events again
+    let events: Vec<&str> = events.iter().map(String::as_str).collect();
+    subscriptions.track(method.id, true, &events);
+}
+
+impl Drop for ConstellationClient {
+    fn drop(&mut self) {
+        // the socket may already be closed
+        if let Err(e) = self.client.close(CloseCode::Normal) {
+            debug!("Could not close the Constellation socket: {}", e);
+        }
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::{
+        errors::{AuthenticationRequired, SubscriptionLimitExceeded},
+        events::{ChannelEventKind, UserEventKind},
+        ConnectOptions, ConnectionState, ConstellationClient, StreamMessage,
+    };
+    use crate::internal::test_server::{ServerEvent, TestServer};
+    use serde_json::{json, Value};
+    use std::{sync::mpsc::channel, thread, time::Duration};
+
+    #[test]
+    fn connect_to() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+
+        client.subscribe(&["channel:1:update"]).unwrap();
+        match server.next_event(Duration::from_secs(1)) {
+            Some(ServerEvent::Message(m)) => {
+                assert!(m.contains(r#""method":"livesubscribe""#));
+                assert!(m.contains("channel:1:update"));
+            }
+            e => panic!("Unexpected event {:?}", e),
+        }
+
+        server.send(r#"{"type":"event","event":"hello","data":{"authenticated":false}}"#);
+        let msg = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(msg.contains("hello"));
+    }
+
+    #[test]
+    fn drop_closes() {
+        let server = TestServer::start();
+        let (client, _) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        drop(client);
+        assert_eq!(
+            Some(ServerEvent::Close(1000)),
+            server.next_event(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn join_handle_after_drop() {
+        let server = TestServer::start();
+        let (mut client, _) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        let handle = client.join_handle.take().unwrap();
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn drop_after_close() {
+        let server = TestServer::start();
+        let (client, _) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        drop(server);
+        thread::sleep(Duration::from_millis(200));
+        drop(client);
+    }
+
+    /// Reply to the next method the server receives, returning the method.
+    fn reply_to_next(server: &TestServer) -> Value {
+        let method: Value = match server.next_event(Duration::from_secs(1)) {
+            Some(ServerEvent::Message(m)) => serde_json::from_str(&m).unwrap(),
+            e => panic!("Unexpected event {:?}", e),
+        };
+        server.send(
+            &json!({"type": "reply", "id": method["id"], "result": null, "error": null})
+                .to_string(),
+        );
+        method
+    }
+
+    #[test]
+    fn subscription_count() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        client.subscribe(&["a", "b"]).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(2, client.subscription_count());
+
+        client.unsubscribe(&["a"]).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(1, client.subscription_count());
+    }
+
+    #[test]
+    fn already_subscribed() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        let id = client.subscribe(&["a"]).unwrap();
+        server.next_event(Duration::from_secs(1));
+        server.send(
+            &json!({
+                "type": "reply",
+                "id": id,
+                "result": null,
+                "error": {"id": 4108, "message": "Already subscribed"}
+            })
+            .to_string(),
+        );
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        let result = client.take_subscription_result(id).unwrap();
+        assert_eq!(vec!["a"], result.already_subscribed);
+        assert!(result.failed.is_empty());
+        assert_eq!(1, client.subscription_count());
+    }
+
+    #[test]
+    fn subscription_limit() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        client.set_subscription_limit(2);
+
+        client.subscribe(&["a"]).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+
+        // "a" is already subscribed, so this only adds one
+        client.subscribe(&["a", "b"]).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(2, client.subscription_count());
+
+        let sent = client.methods_sent();
+        let err = client.subscribe(&["b", "c"]).unwrap_err();
+        let err = err.downcast::<SubscriptionLimitExceeded>().unwrap();
+        assert_eq!(
+            SubscriptionLimitExceeded {
+                limit: 2,
+                requested: 3
+            },
+            err
+        );
+        assert_eq!(sent, client.methods_sent());
+        assert_eq!(None, server.next_event(Duration::from_millis(200)));
+    }
+
+    /// Connect to the server and send the `hello` event, waiting until it's received.
+    fn connect_with_hello(server: &TestServer, authenticated: bool) -> ConstellationClient {
+        let (client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        server.send(
+            &json!({"type": "event", "event": "hello", "data": {"authenticated": authenticated}})
+                .to_string(),
+        );
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        client
+    }
+
+    #[test]
+    fn ready_after_hello() {
+        let server = TestServer::start();
+        let (client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        assert!(!client.is_ready());
+
+        server.send(r#"{"type":"event","event":"hello","data":{}}"#);
+        let message = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(ConstellationClient::is_welcome(&message));
+        assert!(client.is_ready());
+        assert!(!client.is_authenticated());
+    }
+
+    #[test]
+    fn try_next() {
+        let (sender, receiver) = channel();
+        assert!(ConstellationClient::try_next(&receiver).unwrap().is_none());
+
+        sender
+            .send(r#"{"type":"event","event":"hello","data":{}}"#.to_owned())
+            .unwrap();
+        sender.send("not json".to_owned()).unwrap();
+        match ConstellationClient::try_next(&receiver) {
+            Ok(Some(Ok(StreamMessage::Event(event)))) => assert_eq!("hello", event.event),
+            _ => panic!("Expected the hello event"),
+        }
+        assert!(ConstellationClient::try_next(&receiver)
+            .unwrap()
+            .unwrap()
+            .is_err());
+        assert!(ConstellationClient::try_next(&receiver).unwrap().is_none());
+
+        sender.send("not json".to_owned()).unwrap();
+        drop(sender);
+        assert!(ConstellationClient::try_next(&receiver).unwrap().is_some());
+        assert!(ConstellationClient::try_next(&receiver).is_err());
+    }
+
+    #[test]
+    fn is_welcome() {
+        assert!(ConstellationClient::is_welcome(
+            r#"{"type":"event","event":"hello","data":{"authenticated":true}}"#
+        ));
+        assert!(!ConstellationClient::is_welcome(
+            r#"{"type":"event","event":"channel:1:update","data":{}}"#
+        ));
+        assert!(!ConstellationClient::is_welcome(
+            r#"{"type":"reply","id":0,"result":null,"error":null}"#
+        ));
+        assert!(!ConstellationClient::is_welcome("not json"));
+    }
+
+    #[test]
+    fn user_events_need_auth() {
+        let server = TestServer::start();
+        let mut client = connect_with_hello(&server, false);
+        assert!(!client.is_authenticated());
+
+        let sent = client.methods_sent();
+        let err = client
+            .subscribe_user(1, &[UserEventKind::Update])
+            .unwrap_err();
+        let err = err.downcast::<AuthenticationRequired>().unwrap();
+        assert_eq!(vec!["user:1:update"], err.events);
+        assert!(format!("{}", err).contains("connect_authenticated"));
+        assert_eq!(sent, client.methods_sent());
+        assert_eq!(None, server.next_event(Duration::from_millis(200)));
+
+        client.subscribe(&["channel:1:update"]).unwrap();
+        assert!(server.next_event(Duration::from_secs(1)).is_some());
+    }
+
+    #[test]
+    fn user_events_authenticated() {
+        let server = TestServer::start();
+        let mut client = connect_with_hello(&server, true);
+        assert!(client.is_authenticated());
+
+        client.subscribe_user(1, &[UserEventKind::Update]).unwrap();
+        assert!(reply_to_next(&server).to_string().contains("user:1:update"));
+    }
+
+    #[test]
+    fn user_events_force() {
+        let server = TestServer::start();
+        let mut client = connect_with_hello(&server, false);
+
+        client
+            .subscribe_with_force(&["user:1:update"], true)
+            .unwrap();
+        assert!(reply_to_next(&server).to_string().contains("user:1:update"));
+    }
+
+    #[test]
+    fn resubscribe_on_deploy() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        client.subscribe(&["a", "b"]).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+
+        server.close(1012, "Restarting");
+        // ws doesn't accept 1012 as a close code, so it answers with a protocol error
+        assert!(matches!(
+            server.next_event(Duration::from_secs(1)),
+            Some(ServerEvent::Close(_))
+        ));
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(3))
+        );
+        let method = reply_to_next(&server);
+        assert_eq!("livesubscribe", method["method"]);
+        assert_eq!(json!(["a", "b"]), method["params"]["events"]);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(2, client.subscription_count());
+        assert_eq!(1, client.stats().reconnects);
+        assert_eq!(ConnectionState::Connected, client.state());
+    }
+
+    #[test]
+    fn resubscribe() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        assert!(client.resubscribe().unwrap().is_empty());
+
+        client.subscribe(&["b", "a"]).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        client.subscribe(&["c"]).unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        let ids = client.resubscribe().unwrap();
+        assert_eq!(1, ids.len());
+        let method = reply_to_next(&server);
+        assert_eq!("livesubscribe", method["method"]);
+        assert_eq!(json!(ids[0]), method["id"]);
+        assert_eq!(json!(["a", "b", "c"]), method["params"]["events"]);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(3, client.subscription_count());
+    }
+
+    #[test]
+    fn subscribe_channel_all() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        let ids = client.subscribe_channel_all(7).unwrap();
+        assert_eq!(1, ids.len());
+        let method = reply_to_next(&server);
+        assert_eq!("livesubscribe", method["method"]);
+        assert_eq!(json!(ids[0]), method["id"]);
+        assert_eq!(
+            json!(ChannelEventKind::event_names(7, &[ChannelEventKind::All])),
+            method["params"]["events"]
+        );
+        assert!(method["params"]["events"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("channel:7:resubShared")));
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(10, client.subscription_count());
+    }
+
+    #[test]
+    fn unsubscribe_all() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        assert!(client.unsubscribe_all().unwrap().is_empty());
+
+        let events: Vec<String> = (0..60)
+            .map(|i| format!("channel:{:02}:update", i))
+            .collect();
+        client.subscribe(&events).unwrap();
+        reply_to_next(&server);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(60, client.subscription_count());
+
+        let ids = client.unsubscribe_all().unwrap();
+        assert_eq!(2, ids.len());
+        let first = reply_to_next(&server);
+        assert_eq!("liveunsubscribe", first["method"]);
+        assert_eq!(json!(ids[0]), first["id"]);
+        assert_eq!(json!(events[..50]), first["params"]["events"]);
+        let second = reply_to_next(&server);
+        assert_eq!(json!(ids[1]), second["id"]);
+        assert_eq!(json!(events[50..]), second["params"]["events"]);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(0, client.subscription_count());
+        assert!(client.take_subscription_result(ids[1]).is_some());
+    }
+
+    #[test]
+    fn subscribe_channel() {
+        let server = TestServer::start();
+        let (mut client, _) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+        client
+            .subscribe_channel(5, &[ChannelEventKind::Update, ChannelEventKind::Hosted])
+            .unwrap();
+        let method = reply_to_next(&server);
+        assert_eq!("livesubscribe", method["method"]);
+        assert_eq!(
+            json!(["channel:5:update", "channel:5:hosted"]),
+            method["params"]["events"]
+        );
+    }
+
+    #[test]
+    fn replay() {
+        let server = TestServer::start();
+        let (mut client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        server.send(r#"{"type":"event","event":"before","data":null}"#);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(client.replay().is_empty());
+
+        client.enable_replay(2);
+        for n in 0..3 {
+            server.send(
+                &json!({"type": "event", "event": format!("e{}", n), "data": null}).to_string(),
+            );
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        let names: Vec<String> = client.replay().into_iter().map(|e| e.event).collect();
+        assert_eq!(vec!["e1", "e2"], names);
+    }
+
+    #[test]
+    fn stats() {
+        let server = TestServer::start();
+        let (client, receiver) = ConstellationClient::connect_to(&server.url, "").unwrap();
+        assert_eq!(0, client.stats().events_received);
+        assert_eq!(None, client.stats().last_event_millis);
+
+        let event = r#"{"type":"event","event":"live","data":{}}"#;
+        let reply = r#"{"type":"reply","id":0,"result":null,"error":null}"#;
+        let no_name = r#"{"type":"event","data":{}}"#;
+        for message in &[event, event, reply, "not json", no_name] {
+            server.send(message);
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        let stats = client.stats();
+        assert_eq!(2, stats.events_received);
+        assert_eq!(1, stats.replies_received);
+        assert_eq!(2, stats.invalid_received);
+        assert_eq!(
+            (event.len() * 2 + reply.len() + "not json".len() + no_name.len()) as u64,
+            stats.bytes_received
+        );
+        assert_eq!(0, stats.reconnects);
+        assert!(stats.last_event_millis.is_some());
+        assert!(serde_json::to_string(&stats)
+            .unwrap()
+            .contains(r#""events_received":2"#));
+    }
+
+    #[test]
+    fn connect_split() {
+        let server = TestServer::start();
+        let (client, events, replies) =
+            ConstellationClient::connect_to_split(&server.url, "").unwrap();
+        server.next_event(Duration::from_secs(1));
+
+        server.send(r#"{"type":"event","event":"live","data":{"a":1}}"#);
+        server.send("not json");
+        server.send(r#"{"type":"event","data":{}}"#);
+        server.send(r#"{"type":"reply","id":3,"result":null,"error":null}"#);
+
+        let event = events.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!("live", event.event);
+        assert_eq!(Some(json!({"a": 1})), event.data);
+        let reply = replies.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(3, reply.id);
+        assert!(events.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(replies.try_recv().is_err());
+        let stats = client.stats();
+        assert_eq!(2, stats.invalid_received);
+        assert_eq!(1, stats.events_received);
+    }
+
+    #[test]
+    fn connect_split_with_options() {
+        let server = TestServer::start();
+        let options = ConnectOptions::builder()
+            .keepalive(Duration::from_millis(50))
+            .build();
+        let (_client, events, _replies) =
+            ConstellationClient::connect_to_split_with_options(&server.url, "", &options).unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        assert_eq!(
+            Some(ServerEvent::Ping),
+            server.next_event(Duration::from_secs(1))
+        );
+        server.send(r#"{"type":"event","event":"live","data":null}"#);
+        let event = events.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!("live", event.event);
+    }
+
+    #[test]
+    fn connect_to_bad_scheme() {
+        let res = ConstellationClient::connect_to("https://constellation.mixer.com", "");
+        assert!(res.is_err());
+        let res = ConstellationClient::connect_to("not a url", "");
+        assert!(res.is_err());
+    }
+}
@@ -2,13 +2,15 @@ use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{collections::HashMap, convert::TryFrom};
 
+use super::errors::ConstellationError;
+
 /// An Event coming in from the socket.
 ///
 /// These are sent from Constellation when connecting,
 /// receiving a live event, etc.
 ///
 /// See https://dev.mixer.com/reference/constellation/events
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Event {
     /// Always 'event'
     #[serde(rename = "type")]
@@ -19,6 +21,10 @@ pub struct Event {
     /// per the docs, completely unstructured; it depends
     /// on which kind of event was received.
     pub data: Option<Value>,
+    /// Fields that aren't modeled above, kept so that fields Mixer adds
+    /// are still available
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 impl TryFrom<Value> for Event {
@@ -34,6 +40,109 @@ impl TryFrom<Value> for Event {
     }
 }
 
+/// Payload of the `hello` event, which Constellation sends once the
+/// connection is ready for method calls.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct HelloPayload {
+    /// Whether the connection was made with a valid OAuth token, so that
+    /// `user:{id}:*` events can be subscribed to
+    #[serde(default)]
+    pub authenticated: bool,
+}
+
+/// Severity of an announcement.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementLevel {
+    /// Informational notice
+    Info,
+    /// Warning, e.g. upcoming maintenance
+    Warning,
+    /// Something is broken
+    Error,
+    /// Level not known to this library
+    #[serde(other)]
+    Unknown,
+}
+
+/// Payload of an `announcement:announce` event, which is a system-wide
+/// notice such as upcoming maintenance.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct AnnouncementPayload {
+    /// Announcement's heading
+    pub heading: String,
+    /// Announcement's text
+    pub body: String,
+    /// Announcement's severity
+    pub level: AnnouncementLevel,
+}
+
+/// Payload of a `channel:{id}:patronageUpdate` event, sent when a channel in
+/// the patronage program earns embers.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PatronageUpdatePayload {
+    /// Channel's id
+    pub channel: usize,
+    /// Id of the current patronage period
+    pub patronage_period_id: String,
+    /// Id of the current milestone
+    pub current_milestone_id: usize,
+    /// Id of the current milestone group
+    pub current_milestone_group_id: usize,
+    /// Embers earned in the current period
+    pub patronage_earned: u64,
+}
+
+/// An event with its data parsed into the matching payload type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstellationEvent {
+    /// `hello`, sent when the connection is ready
+    Hello(HelloPayload),
+    /// `announcement:announce`
+    Announcement(AnnouncementPayload),
+    /// `channel:{id}:patronageUpdate`
+    PatronageUpdate(PatronageUpdatePayload),
+    /// Any event that doesn't have a typed payload
+    Other {
+        /// Which event
+        event: String,
+        /// Unstructured data associated with the event
+        data: Option<Value>,
+    },
+}
+
+impl TryFrom<&Event> for ConstellationEvent {
+    type Error = &'static str;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        fn payload<T: serde::de::DeserializeOwned>(event: &Event) -> Result<T, &'static str> {
+            match &event.data {
+                Some(data) => {
+                    serde_json::from_value(data.clone()).map_err(|_| "Could not load payload")
+                }
+                None => Err("Event has no data"),
+            }
+        }
+        match event.event.as_str() {
+            "hello" => Ok(ConstellationEvent::Hello(
+                event
+                    .data
+                    .as_ref()
+                    .map_or(Ok(HelloPayload::default()), |_| payload(event))?,
+            )),
+            "announcement:announce" => Ok(ConstellationEvent::Announcement(payload(event)?)),
+            name if name.starts_with("channel:") && name.ends_with(":patronageUpdate") => {
+                Ok(ConstellationEvent::PatronageUpdate(payload(event)?))
+            }
+            _ => Ok(ConstellationEvent::Other {
+                event: event.event.clone(),
+                data: event.data.clone(),
+            }),
+        }
+    }
+}
+
 /// A Method to send to the socket.
 ///
 /// This is how clients send data _to_ the socket.
@@ -78,6 +187,50 @@ pub struct Reply {
     pub result: Option<HashMap<String, Value>>,
     /// Method error
     pub error: Option<MixerError>,
+    /// Fields that aren't modeled above, kept so that fields Mixer adds
+    /// are still available
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Reply {
+    /// Get the error that Constellation replied with, with the description
+    /// of its code, if the method failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::constellation::models::Reply;
+    /// let reply: Reply = serde_json::from_str(
+    ///     r#"{"type":"reply","id":1,"result":null,"error":{"id":4106,"message":"Unknown event"}}"#,
+    /// ).unwrap();
+    /// let err = reply.constellation_error().unwrap();
+    /// assert_eq!(4106, err.code);
+    /// assert!(err.description.is_some());
+    /// ```
+    pub fn constellation_error(&self) -> Option<ConstellationError> {
+        self.error.as_ref().map(ConstellationError::from)
+    }
+
+    /// Convert the reply into the method's result, or the error that
+    /// Constellation replied with.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::constellation::models::Reply;
+    /// let reply: Reply = serde_json::from_str(
+    ///     r#"{"type":"reply","id":1,"result":null,"error":{"id":4106,"message":"Unknown event"}}"#,
+    /// ).unwrap();
+    /// let err = reply.into_result().unwrap_err();
+    /// assert_eq!(4106, err.code);
+    /// ```
+    pub fn into_result(self) -> Result<Option<HashMap<String, Value>>, ConstellationError> {
+        match self.error {
+            Some(e) => Err(ConstellationError::from(e)),
+            None => Ok(self.result),
+        }
+    }
 }
 
 impl TryFrom<Value> for Reply {
@@ -95,14 +248,17 @@ impl TryFrom<Value> for Reply {
 
 #[cfg(test)]
 mod tests {
-    use super::{Event, MixerError, Reply};
+    use super::{
+        AnnouncementLevel, AnnouncementPayload, ConstellationEvent, Event, HelloPayload,
+        MixerError, PatronageUpdatePayload, Reply,
+    };
     use serde_json::{json, Value};
     use std::{collections::HashMap, convert::TryFrom};
 
     #[test]
     fn event_try_from_json() {
         let text = r#"{"type":"event","event":"foobar","data": null}"#;
-        let json: Value = serde_json::from_str(&text).unwrap();
+        let json: Value = serde_json::from_str(text).unwrap();
         let event = Event::try_from(json).unwrap();
 
         assert_eq!(event.event, "foobar");
@@ -119,7 +275,7 @@ mod tests {
     #[test]
     fn reply_try_from_json() {
         let text = r#"{"type":"reply","id":40,"result":null,"error":null}"#;
-        let json: Value = serde_json::from_str(&text).unwrap();
+        let json: Value = serde_json::from_str(text).unwrap();
         let reply = Reply::try_from(json).unwrap();
 
         assert_eq!(reply.id, 40);
@@ -136,7 +292,7 @@ mod tests {
     #[test]
     fn event_from_json() {
         let text = r#"{"type":"event","event":"hello","data":{}}"#;
-        let event: Event = serde_json::from_str(&text).unwrap();
+        let event: Event = serde_json::from_str(text).unwrap();
 
         assert_eq!("event", event.event_type);
         assert_eq!("hello", event.event);
@@ -148,7 +304,7 @@ mod tests {
     #[test]
     fn reply_from_json() {
         let text = r#"{"type":"reply","id":100,"result":{"foo":123},"error":null}"#;
-        let reply: Reply = serde_json::from_str(&text).unwrap();
+        let reply: Reply = serde_json::from_str(text).unwrap();
 
         assert_eq!("reply", reply.reply_type);
         assert_eq!(100, reply.id);
@@ -160,6 +316,151 @@ mod tests {
         assert_eq!(text, serde_json::to_string(&reply).unwrap());
     }
 
+    #[test]
+    fn reply_into_result() {
+        let text = r#"{"type":"reply","id":1,"result":{"foo":123},"error":null}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        let result = reply.into_result().unwrap().unwrap();
+        assert_eq!(json!(123), result["foo"]);
+
+        let text =
+            r#"{"type":"reply","id":1,"result":null,"error":{"id":4107,"message":"No access"}}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        let err = reply.into_result().unwrap_err();
+        assert_eq!(4107, err.code);
+        assert_eq!("No access", err.message);
+        assert!(err.description.is_some());
+    }
+
+    #[test]
+    fn reply_constellation_error() {
+        let text = r#"{"type":"reply","id":1,"result":{},"error":null}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        assert_eq!(None, reply.constellation_error());
+
+        let text =
+            r#"{"type":"reply","id":1,"result":null,"error":{"id":4107,"message":"No access"}}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        let err = reply.constellation_error().unwrap();
+        assert_eq!(4107, err.code);
+        assert_eq!("No access", err.message);
+        assert!(err.description.is_some());
+    }
+
+    #[test]
+    fn hello_event() {
+        let event: Event = serde_json::from_str(
+            r#"{"type":"event","event":"hello","data":{"authenticated":true}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ConstellationEvent::Hello(HelloPayload {
+                authenticated: true
+            }),
+            ConstellationEvent::try_from(&event).unwrap()
+        );
+
+        let event: Event =
+            serde_json::from_str(r#"{"type":"event","event":"hello","data":{}}"#).unwrap();
+        assert_eq!(
+            ConstellationEvent::Hello(HelloPayload {
+                authenticated: false
+            }),
+            ConstellationEvent::try_from(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn announcement_event() {
+        let text = r#"{
+            "type": "event",
+            "event": "announcement:announce",
+            "data": {
+                "heading": "Scheduled maintenance",
+                "body": "Chat will be unavailable for 10 minutes.",
+                "level": "warning"
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        let typed = ConstellationEvent::try_from(&event).unwrap();
+        assert_eq!(
+            ConstellationEvent::Announcement(AnnouncementPayload {
+                heading: "Scheduled maintenance".to_owned(),
+                body: "Chat will be unavailable for 10 minutes.".to_owned(),
+                level: AnnouncementLevel::Warning,
+            }),
+            typed
+        );
+    }
+
+    #[test]
+    fn announcement_event_unknown_level() {
+        let event: Event = serde_json::from_value(json!({
+            "type": "event",
+            "event": "announcement:announce",
+            "data": {"heading": "a", "body": "b", "level": "critical"}
+        }))
+        .unwrap();
+        match ConstellationEvent::try_from(&event).unwrap() {
+            ConstellationEvent::Announcement(a) => assert_eq!(AnnouncementLevel::Unknown, a.level),
+            e => panic!("Unexpected event {:?}", e),
+        }
+    }
+
+    #[test]
+    fn announcement_event_bad_payload() {
+        let event: Event = serde_json::from_value(json!({
+            "type": "event",
+            "event": "announcement:announce",
+            "data": {"heading": "a"}
+        }))
+        .unwrap();
+        assert!(ConstellationEvent::try_from(&event).is_err());
+    }
+
+    #[test]
+    fn patronage_update_event() {
+        let text = r#"{
+            "type": "event",
+            "event": "channel:160788:patronageUpdate",
+            "data": {
+                "channel": 160788,
+                "patronagePeriodId": "5c7d3b6c4f0a2b001f8d2b9e",
+                "currentMilestoneId": 3,
+                "currentMilestoneGroupId": 1,
+                "patronageEarned": 12500
+            }
+        }"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            ConstellationEvent::PatronageUpdate(PatronageUpdatePayload {
+                channel: 160788,
+                patronage_period_id: "5c7d3b6c4f0a2b001f8d2b9e".to_owned(),
+                current_milestone_id: 3,
+                current_milestone_group_id: 1,
+                patronage_earned: 12500,
+            }),
+            ConstellationEvent::try_from(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn other_event() {
+        let event: Event = serde_json::from_value(json!({
+            "type": "event",
+            "event": "channel:1:update",
+            "data": {"online": true}
+        }))
+        .unwrap();
+        assert_eq!(
+            ConstellationEvent::Other {
+                event: "channel:1:update".to_owned(),
+                data: Some(json!({"online": true})),
+            },
+            ConstellationEvent::try_from(&event).unwrap()
+        );
+    }
+
     #[test]
     fn test_mixer_error() {
         let err = MixerError {
@@ -168,4 +469,20 @@ mod tests {
         };
         let _ = format!("{:?}", err);
     }
+
+    #[test]
+    fn extra_fields() {
+        let text = r#"{"type":"event","event":"foobar","data":null,"newField":{"a":1}}"#;
+        let event: Event = serde_json::from_str(text).unwrap();
+        assert_eq!(Some(&json!({"a": 1})), event.extra.get("newField"));
+        let json: Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json!({"a": 1}), json["newField"]);
+        assert!(json.get("extra").is_none());
+
+        let text = r#"{"type":"reply","id":1,"result":null,"error":null,"extraReply":[1]}"#;
+        let reply: Reply = serde_json::from_str(text).unwrap();
+        assert_eq!(1, reply.extra.len());
+        let json: Value = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json!([1]), json["extraReply"]);
+    }
 }
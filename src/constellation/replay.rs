@@ -0,0 +1,98 @@
+//! Bounded buffer of the most recent events.
+
+use super::models::Event;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Ring buffer holding the last `capacity` events, dropping the oldest
+/// once full.
+pub(crate) struct ReplayBuffer {
+    capacity: usize,
+    events: VecDeque<Event>,
+}
+
+impl ReplayBuffer {
+    /// Create an empty buffer, which grows as events come in.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - maximum number of events to keep
+    pub fn new(capacity: usize) -> Self {
+        ReplayBuffer {
+            capacity,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Change the capacity, dropping the oldest events if there are too many.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - maximum number of events to keep
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+    }
+
+    /// Store the message if it's an event.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - parsed message
+    pub fn on_message(&mut self, json: &Value) {
+        if self.capacity == 0 || json["type"] != "event" {
+            return;
+        }
+        let event: Event = match serde_json::from_value(json.clone()) {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Copy of the stored events, oldest first.
+    pub fn events(&self) -> Vec<Event> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplayBuffer;
+    use serde_json::json;
+
+    fn event(n: usize) -> serde_json::Value {
+        json!({"type": "event", "event": format!("e{}", n), "data": null})
+    }
+
+    #[test]
+    fn keeps_newest() {
+        let mut buffer = ReplayBuffer::new(3);
+        for n in 0..5 {
+            buffer.on_message(&event(n));
+        }
+        buffer.on_message(&json!({"type": "reply", "id": 0, "result": null, "error": null}));
+        let names: Vec<String> = buffer.events().into_iter().map(|e| e.event).collect();
+        assert_eq!(vec!["e2", "e3", "e4"], names);
+    }
+
+    #[test]
+    fn resize() {
+        let mut buffer = ReplayBuffer::new(3);
+        for n in 0..3 {
+            buffer.on_message(&event(n));
+        }
+        buffer.resize(1);
+        assert_eq!("e2", buffer.events()[0].event);
+        assert_eq!(1, buffer.events().len());
+
+        buffer.resize(0);
+        buffer.on_message(&event(3));
+        assert!(buffer.events().is_empty());
+    }
+}
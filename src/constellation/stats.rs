@@ -0,0 +1,140 @@
+//! Counters for the messages received from Constellation.
+
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Snapshot of the Constellation client's counters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConstellationStats {
+    /// Number of event messages received
+    pub events_received: u64,
+    /// Number of method replies received
+    pub replies_received: u64,
+    /// Number of messages received that weren't a valid event or reply,
+    /// e.g. invalid JSON or an event without its name
+    pub invalid_received: u64,
+    /// Number of bytes of message text received
+    pub bytes_received: u64,
+    /// Number of times the client has reconnected
+    pub reconnects: u64,
+    /// Time of the last event, in milliseconds since the Unix epoch
+    pub last_event_millis: Option<u64>,
+}
+
+/// Counters updated from the socket thread.
+#[derive(Default)]
+pub(crate) struct StatsCounters {
+    events: AtomicU64,
+    replies: AtomicU64,
+    invalid: AtomicU64,
+    bytes: AtomicU64,
+    reconnects: AtomicU64,
+    last_event_millis: AtomicU64,
+}
+
+impl StatsCounters {
+    /// Count a message from the socket.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - parsed message
+    /// * `len` - length of the message text
+    pub fn record(&self, json: &Value, len: usize) {
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+        match json["type"].as_str() {
+            Some("event") if is_event(json) => {
+                self.events.fetch_add(1, Ordering::Relaxed);
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default();
+                self.last_event_millis.store(now, Ordering::Relaxed);
+            }
+            Some("reply") if is_reply(json) => {
+                self.replies.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {
+                self.invalid.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Count a reconnection.
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Take a snapshot of the counters.
+    pub fn snapshot(&self) -> ConstellationStats {
+        let last_event_millis = self.last_event_millis.load(Ordering::Relaxed);
+        ConstellationStats {
+            events_received: self.events.load(Ordering::Relaxed),
+            replies_received: self.replies.load(Ordering::Relaxed),
+            invalid_received: self.invalid.load(Ordering::Relaxed),
+            bytes_received: self.bytes.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            last_event_millis: if last_event_millis == 0 {
+                None
+            } else {
+                Some(last_event_millis)
+            },
+        }
+    }
+}
+
+/// Whether an event message has the fields that `Event` needs, checked on
+/// the already-parsed message rather than deserializing it again.
+fn is_event(json: &Value) -> bool {
+    json["event"].is_string()
+}
+
+/// Whether a reply message has the fields that `Reply` needs, checked on
+/// the already-parsed message rather than deserializing it again.
+fn is_reply(json: &Value) -> bool {
+    let error = &json["error"];
+    json["id"].is_u64()
+        && (json["result"].is_null() || json["result"].is_object())
+        && (error.is_null()
+            || (error["id"]
+                .as_u64()
+                .is_some_and(|id| id <= u64::from(u16::MAX))
+                && error["message"].is_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_event, is_reply};
+    use crate::constellation::models::{Event, Reply};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn checks_match_models() {
+        let messages = [
+            json!({"type": "event", "event": "live", "data": {}}),
+            json!({"type": "event", "event": "live"}),
+            json!({"type": "event", "data": {}}),
+            json!({"type": "event", "event": 5}),
+            json!({"type": "reply", "id": 1, "result": null, "error": null}),
+            json!({"type": "reply", "id": 1, "result": {"a": 1}}),
+            json!({"type": "reply", "id": 1, "error": {"id": 4106, "message": "Unknown"}}),
+            json!({"type": "reply", "id": -1}),
+            json!({"type": "reply", "id": 1, "result": [1]}),
+            json!({"type": "reply", "id": 1, "error": {"id": 70000, "message": "Big"}}),
+            json!({"type": "reply", "id": 1, "error": "failed"}),
+        ];
+        for message in &messages {
+            let as_event = serde_json::from_value::<Event>(message.clone()).is_ok();
+            let as_reply = serde_json::from_value::<Reply>(message.clone()).is_ok();
+            match message["type"].as_str() {
+                Some("event") => assert_eq!(as_event, is_event(message), "{}", message),
+                _ => assert_eq!(as_reply, is_reply(message), "{}", message),
+            }
+        }
+        assert!(!is_event(&Value::Null));
+        assert!(!is_reply(&Value::Null));
+    }
+}
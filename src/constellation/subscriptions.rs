@@ -0,0 +1,299 @@
+//! Tracking of the events that the client is subscribed to.
+
+use super::errors::ConstellationError;
+use log::warn;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Number of results kept for `take_result` before the oldest are dropped.
+const MAX_RESULTS: usize = 256;
+
+/// Outcome of a `livesubscribe` or `liveunsubscribe` call, once Constellation
+/// has replied to it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscriptionResult {
+    /// Id of the method
+    pub method_id: usize,
+    /// Events that were subscribed to or unsubscribed from
+    pub succeeded: Vec<String>,
+    /// Events that were already subscribed to (error 4108 on subscribe),
+    /// which is treated as success
+    pub already_subscribed: Vec<String>,
+    /// Events that weren't subscribed to (error 4109 on unsubscribe),
+    /// which is treated as success
+    pub not_subscribed: Vec<String>,
+    /// Events that couldn't be subscribed to or unsubscribed from
+    pub failed: Vec<String>,
+    /// Error that Constellation replied with, for the failed events
+    pub error: Option<ConstellationError>,
+}
+
+/// Subscription change waiting on its reply.
+struct Pending {
+    subscribe: bool,
+    events: Vec<String>,
+}
+
+/// Confirmed and pending subscriptions, updated from the replies to
+/// `livesubscribe` and `liveunsubscribe` calls.
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    active: HashSet<String>,
+    pending: HashMap<usize, Pending>,
+    results: HashMap<usize, SubscriptionResult>,
+}
+
+impl Subscriptions {
+    /// Number of confirmed subscriptions.
+    pub fn count(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Number of subscriptions there would be if all pending subscribes
+    /// and the passed events were confirmed.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - events about to be subscribed to
+    pub fn count_with(&self, events: &[&str]) -> usize {
+        let mut all: HashSet<&str> = self.active.iter().map(String::as_str).collect();
+        for pending in self.pending.values().filter(|p| p.subscribe) {
+            all.extend(pending.events.iter().map(String::as_str));
+        }
+        all.extend(events);
+        all.len()
+    }
+
+    /// Track a sent method until its reply arrives.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - id of the sent method
+    /// * `subscribe` - `true` for a subscribe, `false` for an unsubscribe
+    /// * `events` - events in the method
+    pub fn track(&mut self, id: usize, subscribe: bool, events: &[&str]) {
+        self.pending.insert(
+            id,
+            Pending {
+                subscribe,
+                events: events.iter().map(|&e| e.to_owned()).collect(),
+            },
+        );
+    }
+
+    /// Update the subscriptions from a message from the socket.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - parsed message
+    pub fn on_message(&mut self, json: &Value) {
+        if json["type"] != "reply" {
+            return;
+        }
+        let pending = match json["id"]
+            .as_u64()
+            .and_then(|id| self.pending.remove(&(id as usize)))
+        {
+            Some(p) => p,
+            None => return,
+        };
+        let mut result = SubscriptionResult {
+            method_id: json["id"].as_u64().unwrap_or_default() as usize,
+            ..SubscriptionResult::default()
+        };
+        let error = match json.get("error") {
+            Some(e) if !e.is_null() => {
+                let code = e["id"].as_u64().unwrap_or_default() as u16;
+                let message = e["message"].as_str().unwrap_or_default();
+                Some(ConstellationError::new(code, message))
+            }
+            _ => None,
+        };
+        match error {
+            None => result.succeeded = pending.events.clone(),
+            // already in the requested state, so the change is idempotent
+            Some(ref e) if pending.subscribe && e.code == 4108 => {
+                warn!("Already subscribed to {:?}", pending.events);
+                result.already_subscribed = pending.events.clone();
+            }
+            Some(ref e) if !pending.subscribe && e.code == 4109 => {
+                warn!("Not subscribed to {:?}", pending.events);
+                result.not_subscribed = pending.events.clone();
+            }
+            Some(e) => {
+                result.failed = pending.events;
+                result.error = Some(e);
+                self.store(result);
+                return;
+            }
+        }
+        for event in pending.events {
+            if pending.subscribe {
+                self.active.insert(event);
+            } else {
+                self.active.remove(&event);
+            }
+        }
+        self.store(result);
+    }
+
+    /// Keep a result until it's taken, dropping the oldest if there are too many.
+    fn store(&mut self, result: SubscriptionResult) {
+        self.results.insert(result.method_id, result);
+        if self.results.len() > MAX_RESULTS {
+            if let Some(&oldest) = self.results.keys().min() {
+                self.results.remove(&oldest);
+            }
+        }
+    }
+
+    /// Events that will be subscribed to once all pending changes are confirmed,
+    /// sorted by name.
+    ///
+    /// Changes still waiting on their replies are applied in the order they were sent.
+    pub fn events(&self) -> Vec<String> {
+        let mut events: HashSet<&str> = self.active.iter().map(String::as_str).collect();
+        let mut pending: Vec<(&usize, &Pending)> = self.pending.iter().collect();
+        pending.sort_by_key(|&(id, _)| *id);
+        for (_, change) in pending {
+            for event in &change.events {
+                if change.subscribe {
+                    events.insert(event);
+                } else {
+                    events.remove(event.as_str());
+                }
+            }
+        }
+        let mut events: Vec<String> = events.into_iter().map(str::to_owned).collect();
+        events.sort();
+        events
+    }
+
+    /// Forget the subscriptions of a closed connection, returning the events to
+    /// subscribe to again on the new one.
+    ///
+    /// Changes still waiting on their replies are applied, since the replies won't arrive.
+    pub fn reset(&mut self) -> Vec<String> {
+        let events = self.events();
+        self.active.clear();
+        self.pending.clear();
+        events
+    }
+
+    /// Take the result of a subscription change, if its reply has arrived.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - id of the sent method
+    pub fn take_result(&mut self, id: usize) -> Option<SubscriptionResult> {
+        self.results.remove(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SubscriptionResult, Subscriptions};
+    use serde_json::json;
+
+    #[test]
+    fn confirmed_on_reply() {
+        let mut subs = Subscriptions::default();
+        subs.track(0, true, &["a", "b"]);
+        assert_eq!(0, subs.count());
+        assert_eq!(3, subs.count_with(&["b", "c"]));
+
+        subs.on_message(&json!({"type": "reply", "id": 0, "result": null, "error": null}));
+        assert_eq!(2, subs.count());
+
+        subs.track(1, false, &["a"]);
+        subs.on_message(&json!({"type": "reply", "id": 1, "result": null, "error": null}));
+        assert_eq!(1, subs.count());
+        assert_eq!(1, subs.count_with(&["b"]));
+    }
+
+    #[test]
+    fn error_reply_ignored() {
+        let mut subs = Subscriptions::default();
+        subs.track(0, true, &["a"]);
+        subs.on_message(&json!({
+            "type": "reply",
+            "id": 0,
+            "result": null,
+            "error": {"id": 4106, "message": "Unknown event"}
+        }));
+        assert_eq!(0, subs.count());
+        assert_eq!(1, subs.count_with(&["b"]));
+    }
+
+    #[test]
+    fn already_subscribed() {
+        let mut subs = Subscriptions::default();
+        subs.track(0, true, &["a"]);
+        subs.on_message(&json!({
+            "type": "reply",
+            "id": 0,
+            "result": null,
+            "error": {"id": 4108, "message": "Already subscribed"}
+        }));
+        assert_eq!(1, subs.count());
+        let result = subs.take_result(0).unwrap();
+        assert_eq!(vec!["a"], result.already_subscribed);
+        assert!(result.failed.is_empty());
+        assert_eq!(None, result.error);
+        assert_eq!(None, subs.take_result(0));
+    }
+
+    #[test]
+    fn not_subscribed() {
+        let mut subs = Subscriptions::default();
+        subs.track(0, true, &["a"]);
+        subs.on_message(&json!({"type": "reply", "id": 0, "result": null, "error": null}));
+        subs.track(1, false, &["a"]);
+        subs.on_message(&json!({
+            "type": "reply",
+            "id": 1,
+            "result": null,
+            "error": {"id": 4109, "message": "Not subscribed"}
+        }));
+        assert_eq!(0, subs.count());
+        assert_eq!(
+            SubscriptionResult {
+                method_id: 1,
+                not_subscribed: vec!["a".to_owned()],
+                ..SubscriptionResult::default()
+            },
+            subs.take_result(1).unwrap()
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let mut subs = Subscriptions::default();
+        subs.track(0, true, &["a", "b"]);
+        subs.on_message(&json!({"type": "reply", "id": 0, "result": null, "error": null}));
+        subs.track(1, true, &["c"]);
+        subs.track(2, false, &["a", "c"]);
+        subs.track(3, true, &["d"]);
+
+        assert_eq!(vec!["b", "d"], subs.events());
+        assert_eq!(2, subs.count());
+        assert_eq!(vec!["b", "d"], subs.reset());
+        assert_eq!(0, subs.count());
+        assert_eq!(0, subs.count_with(&[]));
+    }
+
+    #[test]
+    fn failed() {
+        let mut subs = Subscriptions::default();
+        subs.track(0, false, &["a"]);
+        subs.on_message(&json!({
+            "type": "reply",
+            "id": 0,
+            "result": null,
+            "error": {"id": 4108, "message": "Already subscribed"}
+        }));
+        let result = subs.take_result(0).unwrap();
+        assert_eq!(vec!["a"], result.failed);
+        assert_eq!(4108, result.error.unwrap().code);
+    }
+}
@@ -0,0 +1,191 @@
+//! Receiver that can be cloned, with every clone getting every message.
+
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, RecvError, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Receivers to send each message to.
+#[derive(Default)]
+struct Hub {
+    senders: Vec<Sender<String>>,
+    /// Set once the source has closed, so that new receivers start disconnected
+    closed: bool,
+}
+
+/// Receiver for the messages from a socket that can be cloned to share the
+/// messages between threads.
+///
+/// Each receiver gets its own copy of every message; a clone gets the messages
+/// that arrive after it was created.
+pub struct BroadcastReceiver {
+    receiver: Receiver<String>,
+    hub: Arc<Mutex<Hub>>,
+}
+
+impl BroadcastReceiver {
+    /// Start forwarding the messages from the source receiver to every clone
+    /// of the returned receiver.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - receiver from the socket connection
+    pub(crate) fn new(source: Receiver<String>) -> Self {
+        let hub = Arc::new(Mutex::new(Hub::default()));
+        let receiver = BroadcastReceiver::subscribe(&hub);
+        let thread_hub = Arc::clone(&hub);
+        // keeps draining the source even with no receivers left, so the socket
+        // thread can always send to it
+        thread::spawn(move || {
+            for message in source {
+                thread_hub
+                    .lock()
+                    .unwrap()
+                    .senders
+                    .retain(|sender| sender.send(message.clone()).is_ok());
+            }
+            let mut hub = thread_hub.lock().unwrap();
+            hub.senders.clear();
+            hub.closed = true;
+        });
+        receiver
+    }
+
+    /// Create a receiver that gets the messages from now on.
+    fn subscribe(hub: &Arc<Mutex<Hub>>) -> Self {
+        let (sender, receiver) = channel();
+        let mut locked = hub.lock().unwrap();
+        if !locked.closed {
+            locked.senders.push(sender);
+        }
+        BroadcastReceiver {
+            receiver,
+            hub: Arc::clone(hub),
+        }
+    }
+
+    /// Block until the next message, like `Receiver::recv`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect_broadcast("aaa").unwrap();
+    /// let message = receiver.recv().unwrap();
+    /// ```
+    pub fn recv(&self) -> Result<String, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Get the next message if there is one, like `Receiver::try_recv`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect_broadcast("aaa").unwrap();
+    /// if let Ok(message) = receiver.try_recv() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn try_recv(&self) -> Result<String, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Wait up to the timeout for the next message, like `Receiver::recv_timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - how long to wait
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// # use std::time::Duration;
+    /// let (client, receiver) = ConstellationClient::connect_broadcast("aaa").unwrap();
+    /// let message = receiver.recv_timeout(Duration::from_secs(1));
+    /// ```
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// Iterate over the messages, blocking for each one, until the connection ends.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::ConstellationClient;
+    /// let (client, receiver) = ConstellationClient::connect_broadcast("aaa").unwrap();
+    /// for message in receiver.iter() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        self.receiver.iter()
+    }
+}
+
+impl Clone for BroadcastReceiver {
+    fn clone(&self) -> Self {
+        BroadcastReceiver::subscribe(&self.hub)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BroadcastReceiver;
+    use std::{
+        sync::mpsc::{channel, RecvTimeoutError},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn every_receiver_gets_every_message() {
+        let (sender, source) = channel();
+        let first = BroadcastReceiver::new(source);
+        let second = first.clone();
+        sender.send("a".to_owned()).unwrap();
+        sender.send("b".to_owned()).unwrap();
+        for receiver in &[&first, &second] {
+            assert_eq!("a", receiver.recv_timeout(Duration::from_secs(1)).unwrap());
+            assert_eq!("b", receiver.recv_timeout(Duration::from_secs(1)).unwrap());
+        }
+
+        let third = second.clone();
+        sender.send("c".to_owned()).unwrap();
+        assert_eq!("c", third.recv_timeout(Duration::from_secs(1)).unwrap());
+        assert_eq!("c", first.recv_timeout(Duration::from_secs(1)).unwrap());
+    }
+
+    #[test]
+    fn dropped_receiver() {
+        let (sender, source) = channel();
+        let first = BroadcastReceiver::new(source);
+        drop(first.clone());
+        sender.send("a".to_owned()).unwrap();
+        assert_eq!("a", first.recv_timeout(Duration::from_secs(1)).unwrap());
+
+        // the source keeps being drained with no receivers
+        drop(first);
+        thread::sleep(Duration::from_millis(100));
+        sender.send("b".to_owned()).unwrap();
+    }
+
+    #[test]
+    fn source_closed() {
+        let (sender, source) = channel::<String>();
+        let first = BroadcastReceiver::new(source);
+        drop(sender);
+        assert!(first.recv().is_err());
+        assert_eq!(
+            Err(RecvTimeoutError::Disconnected),
+            first.clone().recv_timeout(Duration::from_secs(1))
+        );
+    }
+}
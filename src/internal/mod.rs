@@ -1,33 +1,204 @@
-use atomic_counter::ConsistentCounter;
-use failure::Error;
+mod broadcast;
+pub(crate) mod redact;
+#[cfg(test)]
+pub(crate) mod test_server;
+
+pub use broadcast::BroadcastReceiver;
+
+use atomic_counter::{AtomicCounter, ConsistentCounter};
+use failure::{format_err, Error};
+use flate2::read::GzDecoder;
 use log::{debug, error, info, warn};
+use rand::{thread_rng, Rng};
 use std::{
-    sync::mpsc::{channel, Receiver, Sender as ChanSender},
+    io::Read,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender as ChanSender},
+        Arc, Mutex,
+    },
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
+use typed_builder::TypedBuilder;
 use url::Url;
 use ws::{
-    connect as socket_connect, CloseCode, Error as SocketError, Handler, Handshake,
-    Message as SocketMessage, Request, Result as WSResult, Sender as SocketSender,
+    connect as socket_connect, util::Token, CloseCode, Error as SocketError, Frame, Handler,
+    Handshake, Message as SocketMessage, OpCode, Request, Result as WSResult,
+    Sender as SocketSender,
 };
 
+/// Timeout token for sending keepalive pings.
+const PING: Token = Token(1);
+
+/// Close code the server sends when it's being deployed or restarted.
+const DEPLOY_CLOSE_CODE: u16 = 1012;
+
+/// Range of the randomized delay before reconnecting after a deploy, in milliseconds,
+/// so that clients don't all reconnect at the same moment.
+const DEPLOY_RECONNECT_DELAY_MILLIS: (u64, u64) = (500, 1500);
+
+/// Default longest time to wait for the connection and handshake, in seconds.
+const CONNECT_TIMEOUT: u64 = 15;
+
+/// Options for connecting to a socket endpoint.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::constellation::ConnectOptions;
+/// # use std::time::Duration;
+/// let options = ConnectOptions::builder()
+///     .keepalive(Duration::from_secs(30))
+///     .build();
+/// ```
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct ConnectOptions {
+    /// Interval between keepalive pings; no pings are sent if `None`
+    #[builder(default)]
+    pub keepalive: Option<Duration>,
+    /// Number of unanswered pings after which the connection is marked stale
+    #[builder(default = 3)]
+    pub max_missed_pongs: u32,
+    /// Ask the server to send gzip-compressed binary payloads, which are
+    /// decompressed before being sent to the receiver (Constellation only)
+    #[builder(default)]
+    pub gzip: bool,
+    /// OAuth access token sent as a bearer token when connecting, so that
+    /// events needing authentication can be subscribed to (Constellation only)
+    #[builder(default)]
+    pub access_token: Option<String>,
+    /// Reconnect after a short randomized delay when the server closes the
+    /// connection with code 1012, which it sends when it's being deployed or
+    /// restarted
+    #[builder(default = true)]
+    pub reconnect_on_deploy: bool,
+    /// Longest time to wait for the connection and handshake to finish before
+    /// `connect` returns an error
+    #[builder(default = Duration::from_secs(CONNECT_TIMEOUT))]
+    pub connect_timeout: Duration,
+    /// Id of the first method sent; pass the old client's `peek_method_id` when
+    /// connecting again, so that the ids of the two connections don't overlap
+    #[builder(default)]
+    pub method_id_start: usize,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions::builder().build()
+    }
+}
+
+/// Change in the status of the connection, sent from the socket handler.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConnectionEvent {
+    Open,
+    Closed { code: u16, reason: String },
+    Error(String),
+    DecodeError(String),
+}
+
+/// Current state of a socket connection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    /// Connected to the server
+    Connected,
+    /// The connection was closed
+    Closed {
+        /// Close frame code
+        code: u16,
+        /// Close frame reason
+        reason: String,
+    },
+    /// The connection encountered an error
+    Errored(String),
+}
+
+/// Callback run on the socket thread for each text message, before
+/// the message is sent to the receiver.
+pub(crate) type MessageObserver = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Callback run on the socket thread once the connection has been re-established,
+/// with the new socket and the counter for method ids.
+pub(crate) type ReconnectHook = Arc<dyn Fn(&SocketSender, &ConsistentCounter) + Send + Sync>;
+
+/// State shared between the socket handler and the client.
+#[derive(Default)]
+struct SharedState {
+    stale: AtomicBool,
+    last_close: Mutex<Option<(u16, String)>>,
+    closed_for_deploy: AtomicBool,
+    closing: AtomicBool,
+    socket_out: Mutex<Option<SocketSender>>,
+    method_counter: ConsistentCounter,
+    /// Id the method counter started from
+    method_id_start: usize,
+    observer: Option<MessageObserver>,
+    on_reconnect: Option<ReconnectHook>,
+    messages_received: AtomicU64,
+    bytes_received: AtomicU64,
+    /// When the current connection was opened
+    opened_at: Mutex<Option<Instant>>,
+}
+
 struct RawSocketWrapper {
     client_id: String,
-    connection_sender: ChanSender<bool>,
+    socket_out: SocketSender,
+    handshake_sender: ChanSender<Result<(), String>>,
+    connection_sender: ChanSender<ConnectionEvent>,
     message_sender: ChanSender<String>,
+    opened: bool,
+    keepalive: Option<Duration>,
+    max_missed_pongs: u32,
+    missed_pongs: u32,
+    gzip: bool,
+    access_token: Option<String>,
+    reconnecting: bool,
+    shared: Arc<SharedState>,
 }
 
 impl RawSocketWrapper {
     /// Create a new low-level client.
     fn new(
         client_id: &str,
-        connection_sender: ChanSender<bool>,
+        socket_out: SocketSender,
+        handshake_sender: ChanSender<Result<(), String>>,
+        connection_sender: ChanSender<ConnectionEvent>,
         message_sender: ChanSender<String>,
+        options: &ConnectOptions,
+        shared: Arc<SharedState>,
     ) -> Self {
         RawSocketWrapper {
             client_id: client_id.to_owned(),
+            socket_out,
+            handshake_sender,
             connection_sender,
             message_sender,
+            opened: false,
+            keepalive: options.keepalive,
+            max_missed_pongs: options.max_missed_pongs,
+            missed_pongs: 0,
+            gzip: options.gzip,
+            access_token: options.access_token.clone(),
+            reconnecting: false,
+            shared,
+        }
+    }
+
+    /// Send a text message to the observer and receiver.
+    fn forward(&self, text: &str) {
+        if let Some(observer) = &self.shared.observer {
+            observer(text);
+        }
+        self.message_sender.send(text.to_owned()).unwrap();
+    }
+
+    /// Schedule the next keepalive ping, if enabled.
+    fn schedule_ping(&self) {
+        if let Some(interval) = self.keepalive {
+            if let Err(e) = self.socket_out.timeout(interval.as_millis() as u64, PING) {
+                error!("Could not schedule keepalive ping: {}", e);
+            }
         }
     }
 }
@@ -40,61 +211,333 @@ impl Handler for RawSocketWrapper {
         req.headers_mut()
             .push(("client-id".into(), self.client_id.clone().into()));
         req.headers_mut().push(("x-is-bot".into(), "true".into()));
+        if self.gzip {
+            req.headers_mut()
+                .push(("x-accept-compression".into(), "gzip".into()));
+        }
+        if let Some(token) = &self.access_token {
+            req.headers_mut()
+                .push(("authorization".into(), format!("Bearer {}", token).into()));
+        }
         Ok(req)
     }
 
     /// Handler for when the connection is opened.
     fn on_open(&mut self, _handshake: Handshake) -> WSResult<()> {
         info!("Connected");
-        self.connection_sender.send(true).unwrap();
+        self.opened = true;
+        *self.shared.opened_at.lock().unwrap() = Some(Instant::now());
+        self.connection_sender.send(ConnectionEvent::Open).unwrap();
+        let _ = self.handshake_sender.send(Ok(()));
+        if self.reconnecting {
+            if let Some(on_reconnect) = &self.shared.on_reconnect {
+                on_reconnect(&self.socket_out, &self.shared.method_counter);
+            }
+        }
+        self.schedule_ping();
         Ok(())
     }
 
     /// Handler for when the connection receives a message.
     fn on_message(&mut self, msg: SocketMessage) -> WSResult<()> {
-        if !msg.is_empty() && msg.is_text() {
+        self.shared
+            .messages_received
+            .fetch_add(1, Ordering::Relaxed);
+        self.shared
+            .bytes_received
+            .fetch_add(msg.len() as u64, Ordering::Relaxed);
+        if msg.is_empty() {
+            return Ok(());
+        }
+        if msg.is_text() {
             debug!("Got message from socket: {:?}", msg);
-            self.message_sender
-                .send(msg.as_text().unwrap().to_owned())
-                .unwrap();
+            self.forward(msg.as_text().unwrap());
+        } else if self.gzip {
+            let mut text = String::new();
+            match GzDecoder::new(&msg.into_data()[..]).read_to_string(&mut text) {
+                Ok(_) => {
+                    debug!("Got compressed message from socket: {}", text);
+                    self.forward(&text);
+                }
+                Err(e) => {
+                    error!("Could not decompress message: {}", e);
+                    let _ = self
+                        .connection_sender
+                        .send(ConnectionEvent::DecodeError(e.to_string()));
+                }
+            }
         }
         Ok(())
     }
 
+    /// Handler for timeouts, used to send keepalive pings.
+    fn on_timeout(&mut self, event: Token) -> WSResult<()> {
+        if event != PING {
+            return Ok(());
+        }
+        if self.missed_pongs >= self.max_missed_pongs && !self.shared.stale.load(Ordering::SeqCst) {
+            warn!("No pong received for {} pings", self.missed_pongs);
+            self.shared.stale.store(true, Ordering::SeqCst);
+        }
+        debug!("Sending keepalive ping");
+        self.socket_out.ping(Vec::new())?;
+        self.missed_pongs += 1;
+        self.schedule_ping();
+        Ok(())
+    }
+
+    /// Handler for incoming frames, used to watch for keepalive pongs.
+    fn on_frame(&mut self, frame: Frame) -> WSResult<Option<Frame>> {
+        if frame.has_rsv1() || frame.has_rsv2() || frame.has_rsv3() {
+            return Err(SocketError::new(
+                ws::ErrorKind::Protocol,
+                "Encountered frame with reserved bits set.",
+            ));
+        }
+        if frame.opcode() == OpCode::Pong {
+            self.missed_pongs = 0;
+            self.shared.stale.store(false, Ordering::SeqCst);
+        }
+        Ok(Some(frame))
+    }
+
     /// Handler for when the connection is closed.
     fn on_close(&mut self, code: CloseCode, reason: &str) {
         warn!("Closed: {:?} | {}", code, reason);
-        self.connection_sender.send(false).unwrap();
+        if !self.opened {
+            let _ = self.handshake_sender.send(Err(format!(
+                "Connection closed during handshake: {:?} {}",
+                code, reason
+            )));
+        }
+        let code: u16 = code.into();
+        *self.shared.last_close.lock().unwrap() = Some((code, reason.to_owned()));
+        if code == DEPLOY_CLOSE_CODE {
+            self.shared.closed_for_deploy.store(true, Ordering::SeqCst);
+        }
+        // the client may have already been dropped
+        let _ = self.connection_sender.send(ConnectionEvent::Closed {
+            code,
+            reason: reason.to_owned(),
+        });
     }
 
     /// Handler for when the connection receives an error.
     fn on_error(&mut self, error: SocketError) {
         error!("An error occurred: {}", error);
+        if !self.opened {
+            let _ = self.handshake_sender.send(Err(error.to_string()));
+        }
+        let _ = self
+            .connection_sender
+            .send(ConnectionEvent::Error(error.to_string()));
     }
 }
 
 /// Client for communicating with Mixer's Constellation endpoint.
 pub struct ClientSocketWrapper {
-    /// Raw socket connection
-    pub socket_out: SocketSender,
-    connection_receiver: Receiver<bool>,
-    is_connected: bool,
-    /// Atomic counter for methods
-    pub method_counter: ConsistentCounter,
+    connection_receiver: Receiver<ConnectionEvent>,
+    state: ConnectionState,
+    decode_errors: Vec<String>,
+    shared: Arc<SharedState>,
 }
 
 impl ClientSocketWrapper {
     /// Create a new high-level client.
-    fn new(
-        socket_out: SocketSender,
-        connection_receiver: Receiver<bool>,
-    ) -> Self {
+    fn new(connection_receiver: Receiver<ConnectionEvent>, shared: Arc<SharedState>) -> Self {
         ClientSocketWrapper {
-            socket_out,
             connection_receiver,
-            is_connected: false,
-            method_counter: ConsistentCounter::new(0),
+            // only created once the handshake has finished
+            state: ConnectionState::Connected,
+            decode_errors: Vec::new(),
+            shared,
+        }
+    }
+
+    /// Send a text message through the current connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - message to send
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// client.send("{}".to_owned()).unwrap();
+    /// ```
+    pub fn send(&self, text: String) -> Result<(), Error> {
+        match self.shared.socket_out.lock().unwrap().as_ref() {
+            Some(socket_out) => Ok(socket_out.send(text)?),
+            None => Err(format_err!("Not connected to socket")),
+        }
+    }
+
+    /// Close the current connection with the code.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - close frame code
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// client.close(CloseCode::Normal).unwrap();
+    /// ```
+    pub fn close(&self, code: CloseCode) -> Result<(), Error> {
+        // don't reconnect, even if a deploy close is already on its way
+        self.shared.closing.store(true, Ordering::SeqCst);
+        match self.shared.socket_out.lock().unwrap().as_ref() {
+            Some(socket_out) => Ok(socket_out.close(code)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the id for the next method, counting it as sent.
+    ///
+    /// Ids count up from the `method_id_start` connection option and keep
+    /// counting across reconnects after a deploy, so a reply can't be matched
+    /// to the wrong method.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let id = client.next_method_id();
+    /// ```
+    pub fn next_method_id(&self) -> usize {
+        self.shared.method_counter.inc()
+    }
+
+    /// Get the id the next method will have, without counting it as sent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let id = client.peek_method_id();
+    /// ```
+    pub fn peek_method_id(&self) -> usize {
+        self.shared.method_counter.get()
+    }
+
+    /// Number of methods sent through this client.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let count = client.methods_sent();
+    /// ```
+    pub fn methods_sent(&self) -> usize {
+        self.shared.method_counter.get() - self.shared.method_id_start
+    }
+
+    /// Number of messages received from the server, including any that were
+    /// empty or couldn't be decompressed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let count = client.messages_received();
+    /// ```
+    pub fn messages_received(&self) -> u64 {
+        self.shared.messages_received.load(Ordering::Relaxed)
+    }
+
+    /// Number of bytes received from the server, as sent over the socket
+    /// (before decompressing).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let bytes = client.bytes_received();
+    /// ```
+    pub fn bytes_received(&self) -> u64 {
+        self.shared.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// How long the current connection has been open; reset when reconnecting.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let uptime = client.uptime();
+    /// ```
+    pub fn uptime(&self) -> Duration {
+        self.shared
+            .opened_at
+            .lock()
+            .unwrap()
+            .map(|opened| opened.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Whether the server has stopped answering keepalive pings.
+    ///
+    /// Always `false` if keepalive pings are not enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let is_stale = client.is_stale();
+    /// ```
+    pub fn is_stale(&self) -> bool {
+        self.shared.stale.load(Ordering::SeqCst)
+    }
+
+    /// Code and reason of the last close frame, if the connection has been closed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// if let Some((code, reason)) = client.last_close() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn last_close(&self) -> Option<(u16, String)> {
+        self.shared.last_close.lock().unwrap().clone()
+    }
+
+    /// Checks for new connection events from the underlying client,
+    /// returning the current state of the connection.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let state = client.state();
+    /// ```
+    pub fn state(&mut self) -> ConnectionState {
+        while let Ok(event) = self.connection_receiver.try_recv() {
+            debug!("Got new connection event: {:?}", event);
+            self.state = match (event, &self.state) {
+                (ConnectionEvent::Open, _) => ConnectionState::Connected,
+                (ConnectionEvent::Closed { code, reason }, _) => {
+                    ConnectionState::Closed { code, reason }
+                }
+                // ws can report a protocol error after the close frame (e.g. for 1012),
+                // which shouldn't hide the close code
+                (ConnectionEvent::Error(_), ConnectionState::Closed { .. }) => continue,
+                (ConnectionEvent::Error(e), _) => ConnectionState::Errored(e),
+                // a bad payload doesn't affect the connection itself
+                (ConnectionEvent::DecodeError(e), _) => {
+                    self.decode_errors.push(e);
+                    continue;
+                }
+            };
         }
+        self.state.clone()
+    }
+
+    /// Take the errors from compressed messages that couldn't be decompressed
+    /// since the last call.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// for error in client.take_decode_errors() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn take_decode_errors(&mut self) -> Vec<String> {
+        self.state();
+        std::mem::take(&mut self.decode_errors)
     }
 
     /// Checks to see if new connection status has come from the underlying client.
@@ -105,14 +548,7 @@ impl ClientSocketWrapper {
     /// let is_connected = client.check_connection();
     /// ```
     pub fn check_connection(&mut self) -> bool {
-        match self.connection_receiver.try_recv() {
-            Ok(v) => {
-                debug!("Got new connection status: {}", v);
-                self.is_connected = v;
-                self.is_connected
-            }
-            Err(_) => self.is_connected,
-        }
+        self.state() == ConnectionState::Connected
     }
 }
 
@@ -123,6 +559,10 @@ impl ClientSocketWrapper {
 /// utilizes threads so that it does not block; the program can continue
 /// running after calling this method.
 ///
+/// This method does wait for the websocket handshake to finish, returning an
+/// error if the connection could not be established (unreachable endpoint,
+/// rejected client ID, etc.).
+///
 /// Of the tuple that's returned, the first struct is the client that is
 /// used to send messages to the server. The second is the internal thread
 /// join handle. The third is the MPSC receiver that is sent the replies
@@ -132,47 +572,463 @@ impl ClientSocketWrapper {
 ///
 /// * `endpoint` - server socket endpoint
 /// * `client_id` - client ID
+/// * `options` - connection options
+/// * `observer` - optional callback run on each text message
+/// * `on_reconnect` - optional callback run after reconnecting for a deploy
 ///
 /// # Examples
 ///
 /// ## Simple method call
 ///
 /// ```rust,ignore
-/// # use mixer_wrappers::internal::connect;
-/// let (client, join_handle, receiver) = connect("wss://somewhere.com:443", "aaaaaaaaaa").unwrap();
+/// # use mixer_wrappers::internal::{connect, ConnectOptions};
+/// let (client, join_handle, receiver) =
+///     connect("wss://somewhere.com:443", "aaaaaaaaaa", &ConnectOptions::default(), None, None)
+///         .unwrap();
 /// ```
 pub fn connect(
     endpoint: &str,
     client_id: &str,
+    options: &ConnectOptions,
+    observer: Option<MessageObserver>,
+    on_reconnect: Option<ReconnectHook>,
 ) -> Result<(ClientSocketWrapper, JoinHandle<()>, Receiver<String>), Error> {
     debug!("Setting up connection");
     // create channels
     let (ws_send, ws_recv) = channel::<SocketSender>();
-    let (conn_send, conn_recv) = channel::<bool>();
+    let (hs_send, hs_recv) = channel::<Result<(), String>>();
+    let (conn_send, conn_recv) = channel::<ConnectionEvent>();
     let (msg_send, msg_rev) = channel::<String>();
 
+    let shared = Arc::new(SharedState {
+        method_counter: ConsistentCounter::new(options.method_id_start),
+        method_id_start: options.method_id_start,
+        observer,
+        on_reconnect,
+        ..SharedState::default()
+    });
+
+    let deadline = Instant::now() + options.connect_timeout;
+    let timed_out = |socket_out: Option<&SocketSender>| {
+        // stop the socket thread from reconnecting if the connection does finish
+        shared.closing.store(true, Ordering::SeqCst);
+        if let Some(socket_out) = socket_out {
+            let _ = socket_out.shutdown();
+        }
+        format_err!("Timed out connecting to {}", endpoint)
+    };
+
     // launch the socket connection in a new thread
-    let endpoint = endpoint.to_owned();
+    let thread_endpoint = endpoint.to_owned();
     let client_id = client_id.to_owned();
+    let options = options.clone();
+    let handler_shared = Arc::clone(&shared);
     let client_handler = thread::spawn(move || {
-        debug!("Starting connection");
-        socket_connect(endpoint, |socket_out| {
-            let client = RawSocketWrapper::new(&client_id, conn_send.clone(), msg_send.clone());
-            // send the socket output struct through the corresponding channel
-            ws_send
-                .send(socket_out)
-                .expect("Could not send socket output to channel");
-            client
-        })
-        .expect("Could not start socket connection");
+        let mut reconnecting = false;
+        loop {
+            debug!("Starting connection");
+            handler_shared
+                .closed_for_deploy
+                .store(false, Ordering::SeqCst);
+            let result = socket_connect(thread_endpoint.as_str(), |socket_out| {
+                let mut client = RawSocketWrapper::new(
+                    &client_id,
+                    socket_out.clone(),
+                    hs_send.clone(),
+                    conn_send.clone(),
+                    msg_send.clone(),
+                    &options,
+                    Arc::clone(&handler_shared),
+                );
+                client.reconnecting = reconnecting;
+                *handler_shared.socket_out.lock().unwrap() = Some(socket_out.clone());
+                // send the socket output struct through the corresponding channel
+                let _ = ws_send.send(socket_out);
+                client
+            });
+            // the handshake channel closes with this thread, which unblocks `connect`
+            if let Err(e) = result {
+                error!("Could not start socket connection: {}", e);
+                break;
+            }
+            if !options.reconnect_on_deploy
+                || !handler_shared.closed_for_deploy.load(Ordering::SeqCst)
+                || handler_shared.closing.load(Ordering::SeqCst)
+            {
+                break;
+            }
+            let delay = thread_rng().gen_range(
+                DEPLOY_RECONNECT_DELAY_MILLIS.0,
+                DEPLOY_RECONNECT_DELAY_MILLIS.1,
+            );
+            info!("Server is being deployed, reconnecting in {}ms", delay);
+            thread::sleep(Duration::from_millis(delay));
+            if handler_shared.closing.load(Ordering::SeqCst) {
+                break;
+            }
+            reconnecting = true;
+        }
     });
     // receive the socket output struct
-    let socket_out = ws_recv.recv()?;
+    let socket_out = match ws_recv.recv_timeout(remaining(deadline)) {
+        Ok(socket_out) => socket_out,
+        Err(RecvTimeoutError::Timeout) => return Err(timed_out(None)),
+        Err(RecvTimeoutError::Disconnected) => {
+            return Err(format_err!(
+                "Could not start socket connection to {}",
+                endpoint
+            ))
+        }
+    };
+    // wait for the handshake to finish
+    match hs_recv.recv_timeout(remaining(deadline)) {
+        Ok(Ok(())) => (),
+        Ok(Err(e)) => {
+            let _ = socket_out.shutdown();
+            return Err(format_err!("Could not connect to {}: {}", endpoint, e));
+        }
+        Err(RecvTimeoutError::Timeout) => return Err(timed_out(Some(&socket_out))),
+        Err(RecvTimeoutError::Disconnected) => {
+            return Err(format_err!(
+                "Connection to {} ended before the handshake finished",
+                endpoint
+            ))
+        }
+    }
 
     // create the final client
-    let client = ClientSocketWrapper::new(socket_out, conn_recv);
+    let client = ClientSocketWrapper::new(conn_recv, shared);
 
     // return the final client
     debug!("Connection setup finished");
     Ok((client, client_handler, msg_rev))
 }
+
+/// Check that a socket endpoint is reachable by connecting to it and closing
+/// the connection as soon as it opens.
+///
+/// # Arguments
+///
+/// * `endpoint` - server socket endpoint
+/// * `client_id` - client ID
+/// * `timeout` - longest time to wait for the connection to open
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::chat::probe;
+/// # use std::time::Duration;
+/// let servers = vec!["wss://chat1.mixer.com:443", "wss://chat2.mixer.com:443"];
+/// let healthy = servers
+///     .into_iter()
+///     .find(|s| probe(s, "aaaaaaaaaa", Duration::from_secs(5)).is_ok());
+/// ```
+pub fn probe(endpoint: &str, client_id: &str, timeout: Duration) -> Result<(), Error> {
+    let options = ConnectOptions::builder()
+        .reconnect_on_deploy(false)
+        .connect_timeout(timeout)
+        .build();
+    let (client, _, _) = connect(endpoint, client_id, &options, None, None)?;
+    client.close(CloseCode::Normal)
+}
+
+/// Time left until the deadline, or zero if it has passed.
+fn remaining(deadline: Instant) -> Duration {
+    deadline.saturating_duration_since(Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        connect, probe,
+        test_server::{ServerEvent, TestServer},
+        ConnectOptions, ConnectionState, ReconnectHook,
+    };
+    use flate2::{write::GzEncoder, Compression};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn keepalive_pings() {
+        let server = TestServer::start();
+        let options = ConnectOptions::builder()
+            .keepalive(Duration::from_millis(50))
+            .build();
+        let (client, _, _) = connect(&server.url, "", &options, None, None).unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                Some(ServerEvent::Ping),
+                server.next_event(Duration::from_secs(1))
+            );
+        }
+        assert!(!client.is_stale());
+    }
+
+    #[test]
+    fn keepalive_disabled() {
+        let server = TestServer::start();
+        let (_client, _, _) =
+            connect(&server.url, "", &ConnectOptions::default(), None, None).unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        assert_eq!(None, server.next_event(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn keepalive_stale() {
+        let server = TestServer::start_with(false);
+        let options = ConnectOptions::builder()
+            .keepalive(Duration::from_millis(20))
+            .max_missed_pongs(2_u32)
+            .build();
+        let (client, _, _) = connect(&server.url, "", &options, None, None).unwrap();
+        assert!(!client.is_stale());
+        thread::sleep(Duration::from_secs(1));
+        assert!(client.is_stale());
+    }
+
+    #[test]
+    fn connect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let res = connect(&url, "", &ConnectOptions::default(), None, None);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn connect_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf);
+            stream
+                .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+        let res = connect(
+            &url,
+            "bad_client_id",
+            &ConnectOptions::default(),
+            None,
+            None,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn connect_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // accepts the connection but never answers the handshake
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            thread::sleep(Duration::from_secs(5));
+            drop(stream);
+        });
+        let options = ConnectOptions::builder()
+            .connect_timeout(Duration::from_millis(200))
+            .build();
+        let start = Instant::now();
+        match connect(&url, "", &options, None, None) {
+            Err(e) => assert!(e.to_string().contains("Timed out")),
+            Ok(_) => panic!("Connected without a handshake"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn probe_endpoint() {
+        let server = TestServer::start();
+        probe(&server.url, "", Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+        assert_eq!(
+            Some(ServerEvent::Close(1000)),
+            server.next_event(Duration::from_secs(1))
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(probe(&url, "", Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn method_id_start() {
+        let server = TestServer::start();
+        let options = ConnectOptions::builder().method_id_start(10usize).build();
+        let (client, _, _) = connect(&server.url, "", &options, None, None).unwrap();
+        assert_eq!(10, client.peek_method_id());
+        assert_eq!(10, client.next_method_id());
+        assert_eq!(11, client.peek_method_id());
+        assert_eq!(1, client.methods_sent());
+    }
+
+    #[test]
+    fn traffic_counters() {
+        let server = TestServer::start();
+        let (client, _, receiver) =
+            connect(&server.url, "", &ConnectOptions::default(), None, None).unwrap();
+        assert_eq!(0, client.messages_received());
+        assert_eq!(0, client.bytes_received());
+
+        server.send("hello");
+        server.send("world!");
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(2, client.messages_received());
+        assert_eq!(11, client.bytes_received());
+
+        let uptime = client.uptime();
+        thread::sleep(Duration::from_millis(20));
+        assert!(client.uptime() >= uptime + Duration::from_millis(20));
+    }
+
+    #[test]
+    fn connect_invalid_url() {
+        let res = connect("not a url", "", &ConnectOptions::default(), None, None);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn gzip_message() {
+        let server = TestServer::start();
+        let options = ConnectOptions::builder().gzip(true).build();
+        let (mut client, _, receiver) = connect(&server.url, "", &options, None, None).unwrap();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(br#"{"type":"event"}"#).unwrap();
+        server.send_binary(encoder.finish().unwrap());
+        assert_eq!(
+            r#"{"type":"event"}"#,
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap()
+        );
+        assert!(client.take_decode_errors().is_empty());
+
+        server.send_binary(b"not gzip".to_vec());
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(1, client.take_decode_errors().len());
+        assert!(client.take_decode_errors().is_empty());
+        assert_eq!(ConnectionState::Connected, client.state());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn binary_ignored_without_gzip() {
+        let server = TestServer::start();
+        let (mut client, _, receiver) =
+            connect(&server.url, "", &ConnectOptions::default(), None, None).unwrap();
+        server.send_binary(b"not gzip".to_vec());
+        thread::sleep(Duration::from_millis(200));
+        assert!(receiver.try_recv().is_err());
+        assert!(client.take_decode_errors().is_empty());
+    }
+
+    #[test]
+    fn close_code() {
+        let server = TestServer::start();
+        let options = ConnectOptions::builder().reconnect_on_deploy(false).build();
+        let (mut client, _, _) = connect(&server.url, "", &options, None, None).unwrap();
+        assert_eq!(None, client.last_close());
+        assert_eq!(ConnectionState::Connected, client.state());
+
+        server.close(1012, "Restarting");
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(Some((1012, "Restarting".to_owned())), client.last_close());
+        assert_eq!(
+            ConnectionState::Closed {
+                code: 1012,
+                reason: "Restarting".to_owned()
+            },
+            client.state()
+        );
+        assert!(!client.check_connection());
+    }
+
+    #[test]
+    fn reconnect_on_deploy() {
+        let server = TestServer::start();
+        let reconnects = Arc::new(AtomicUsize::new(0));
+        let hook_reconnects = Arc::clone(&reconnects);
+        let on_reconnect: ReconnectHook = Arc::new(move |_, _| {
+            hook_reconnects.fetch_add(1, Ordering::SeqCst);
+        });
+        let (mut client, _, _) = connect(
+            &server.url,
+            "",
+            &ConnectOptions::default(),
+            None,
+            Some(on_reconnect),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+
+        server.close(1012, "Restarting");
+        // ws doesn't accept 1012 as a close code, so it answers with a protocol error
+        assert!(matches!(
+            server.next_event(Duration::from_secs(1)),
+            Some(ServerEvent::Close(_))
+        ));
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(3))
+        );
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(1, reconnects.load(Ordering::SeqCst));
+        assert_eq!(ConnectionState::Connected, client.state());
+        assert_eq!(Some((1012, "Restarting".to_owned())), client.last_close());
+
+        client.send("hello".to_owned()).unwrap();
+        assert_eq!(
+            Some(ServerEvent::Message("hello".to_owned())),
+            server.next_event(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn no_reconnect_on_other_close() {
+        let server = TestServer::start();
+        let (mut client, _, _) =
+            connect(&server.url, "", &ConnectOptions::default(), None, None).unwrap();
+        assert_eq!(
+            Some(ServerEvent::Open),
+            server.next_event(Duration::from_secs(1))
+        );
+
+        server.close(4011, "Session expired");
+        assert_eq!(
+            Some(ServerEvent::Close(4011)),
+            server.next_event(Duration::from_secs(1))
+        );
+        assert_eq!(None, server.next_event(Duration::from_secs(2)));
+        assert_eq!(
+            ConnectionState::Closed {
+                code: 4011,
+                reason: "Session expired".to_owned()
+            },
+            client.state()
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Formatting of requests and responses for logging, with secrets masked.
+
+use reqwest::{
+    header::{self, HeaderMap},
+    Request, Url,
+};
+use serde_json::Value;
+
+/// Text that secrets are replaced with.
+const MASK: &str = "***";
+
+/// Whether a query param or JSON field with this name holds a secret.
+///
+/// A shortcode `handle` counts, as it's enough to get the authorization code
+/// once the user has granted access.
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["secret", "token", "password", "handle"]
+        .iter()
+        .any(|word| name.contains(word))
+}
+
+/// Format a request's method, URL and headers, with secrets masked.
+///
+/// # Arguments
+///
+/// * `req` - request about to be sent
+pub(crate) fn redact(req: &Request) -> String {
+    format!(
+        "{} {} headers: {}",
+        req.method(),
+        redact_url(req.url()),
+        redact_headers(req.headers())
+    )
+}
+
+/// Format a URL with the values of secret query params masked.
+///
+/// # Arguments
+///
+/// * `url` - URL to format
+pub(crate) fn redact_url(url: &Url) -> String {
+    if url.query().is_none() {
+        return url.to_string();
+    }
+    let mut url = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if is_secret_name(&name) {
+                MASK.to_owned()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url.to_string()
+}
+
+/// Format headers, keeping only the scheme of `Authorization` values (e.g.
+/// `Bearer ***` or `Secret ***`) and masking other credentials.
+///
+/// # Arguments
+///
+/// * `headers` - headers to format
+pub(crate) fn redact_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if name == header::AUTHORIZATION {
+                let scheme = value
+                    .to_str()
+                    .ok()
+                    .and_then(|v| v.split_whitespace().next())
+                    .unwrap_or_default();
+                format!("{} {}", scheme, MASK).trim_start().to_owned()
+            } else if name == header::COOKIE
+                || name == header::SET_COOKIE
+                || is_secret_name(name.as_str())
+            {
+                MASK.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format a response body, masking secret fields if it's a JSON object.
+///
+/// # Arguments
+///
+/// * `body` - response body
+pub(crate) fn redact_json(body: &str) -> String {
+    fn mask(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (name, value) in map.iter_mut() {
+                    if is_secret_name(name) && !value.is_null() {
+                        *value = Value::String(MASK.to_owned());
+                    } else {
+                        mask(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(mask),
+            _ => {}
+        }
+    }
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut json) => {
+            mask(&mut json);
+            json.to_string()
+        }
+        Err(_) => body.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{redact, redact_headers, redact_json, redact_url};
+    use reqwest::{
+        header::{self, HeaderMap, HeaderValue},
+        Client, Url,
+    };
+
+    #[test]
+    fn request() {
+        let req = Client::new()
+            .get("https://mixer.com/api/v1/users/current")
+            .query(&[("client_secret", "hunter2"), ("limit", "2")])
+            .header("client-id", "abc")
+            .header(header::AUTHORIZATION, "Bearer my_token")
+            .build()
+            .unwrap();
+        let text = redact(&req);
+        assert_eq!(
+            "GET https://mixer.com/api/v1/users/current?client_secret=***&limit=2 \
+             headers: client-id: abc, authorization: Bearer ***",
+            text
+        );
+        assert!(!text.contains("hunter2"));
+        assert!(!text.contains("my_token"));
+    }
+
+    #[test]
+    fn url_without_query() {
+        let url = Url::parse("https://mixer.com/api/v1/hooks").unwrap();
+        assert_eq!("https://mixer.com/api/v1/hooks", redact_url(&url));
+    }
+
+    #[test]
+    fn headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Secret abc"),
+        );
+        headers.insert("x-csrf-token", HeaderValue::from_static("def"));
+        headers.insert(header::SET_COOKIE, HeaderValue::from_static("session=ghi"));
+        headers.insert("x-request-id", HeaderValue::from_static("jkl"));
+        assert_eq!(
+            "authorization: Secret ***, x-csrf-token: ***, set-cookie: ***, x-request-id: jkl",
+            redact_headers(&headers)
+        );
+    }
+
+    #[test]
+    fn json_body() {
+        let body = r#"{"access_token":"abc","refresh_token":"def","expires_in":3600,"nested":[{"client_secret":"ghi"}]}"#;
+        assert_eq!(
+            r#"{"access_token":"***","expires_in":3600,"nested":[{"client_secret":"***"}],"refresh_token":"***"}"#,
+            redact_json(body)
+        );
+        assert_eq!("not json", redact_json("not json"));
+    }
+
+    #[test]
+    fn shortcode_response() {
+        let body = r#"{"code":"ABC123","expires_in":120,"handle":"5cc3bd7f2c6ee1b5"}"#;
+        let text = redact_json(body);
+        assert!(!text.contains("5cc3bd7f2c6ee1b5"));
+        assert_eq!(r#"{"code":"ABC123","expires_in":120,"handle":"***"}"#, text);
+    }
+}
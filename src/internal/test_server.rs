@@ -0,0 +1,119 @@
+//! Local websocket server for testing the socket clients.
+
+use std::{
+    sync::mpsc::{channel, Receiver, Sender as ChanSender},
+    thread,
+    time::Duration,
+};
+use ws::{
+    Builder, CloseCode, Frame, Handler, Handshake, Message, OpCode, Result as WSResult,
+    Sender as SocketSender,
+};
+
+/// Something that happened on the server.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ServerEvent {
+    Open,
+    Message(String),
+    Ping,
+    Close(u16),
+}
+
+struct ServerHandler {
+    events: ChanSender<ServerEvent>,
+    answer_pings: bool,
+}
+
+impl Handler for ServerHandler {
+    fn on_open(&mut self, _handshake: Handshake) -> WSResult<()> {
+        let _ = self.events.send(ServerEvent::Open);
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: Message) -> WSResult<()> {
+        if let Ok(text) = msg.as_text() {
+            let _ = self.events.send(ServerEvent::Message(text.to_owned()));
+        }
+        Ok(())
+    }
+
+    fn on_frame(&mut self, frame: Frame) -> WSResult<Option<Frame>> {
+        if frame.opcode() == OpCode::Ping {
+            let _ = self.events.send(ServerEvent::Ping);
+            if !self.answer_pings {
+                return Ok(None);
+            }
+        }
+        Ok(Some(frame))
+    }
+
+    fn on_close(&mut self, code: CloseCode, _reason: &str) {
+        let _ = self.events.send(ServerEvent::Close(code.into()));
+    }
+}
+
+/// Websocket server listening on a random local port.
+pub(crate) struct TestServer {
+    /// URL to connect to
+    pub url: String,
+    events: Receiver<ServerEvent>,
+    broadcaster: SocketSender,
+}
+
+impl TestServer {
+    /// Start a server that answers pings.
+    pub fn start() -> Self {
+        TestServer::start_with(true)
+    }
+
+    /// Start a server, choosing whether or not it answers pings.
+    pub fn start_with(answer_pings: bool) -> Self {
+        let (event_send, event_recv) = channel();
+        let server = Builder::new()
+            .build(move |_| ServerHandler {
+                events: event_send.clone(),
+                answer_pings,
+            })
+            .unwrap()
+            .bind("127.0.0.1:0")
+            .unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+        let broadcaster = server.broadcaster();
+        thread::spawn(move || {
+            let _ = server.run();
+        });
+        TestServer {
+            url,
+            events: event_recv,
+            broadcaster,
+        }
+    }
+
+    /// Wait for the next event from the server.
+    pub fn next_event(&self, timeout: Duration) -> Option<ServerEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// Send a text message to all connected clients.
+    pub fn send(&self, text: &str) {
+        self.broadcaster.send(text).unwrap();
+    }
+
+    /// Send a binary message to all connected clients.
+    pub fn send_binary(&self, data: Vec<u8>) {
+        self.broadcaster.send(data).unwrap();
+    }
+
+    /// Close all connected clients with the code and reason.
+    pub fn close(&self, code: u16, reason: &str) {
+        self.broadcaster
+            .close_with_reason(CloseCode::from(code), reason.to_owned())
+            .unwrap();
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.broadcaster.shutdown();
+    }
+}
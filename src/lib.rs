@@ -8,6 +8,8 @@
 //! [Core REST API]: https://dev.mixer.com/rest/index.html
 
 #![warn(missing_docs)]
+// failure's derive macros emit their impls inside anonymous consts
+#![allow(non_local_definitions)]
 
 pub mod chat;
 pub mod constellation;
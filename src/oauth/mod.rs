@@ -1,16 +1,33 @@
 //! Wrappers around OAuth calls for authenticating the user
 //! interacting with your application.
 //!
-//! This module does not contain any structs; instead, import methods individually as needed.
+//! Most of this module is functions, imported individually as needed.
 //!
 //! `get_authorize_url` is used to start your application's user on Mixer's standard OAuth flow, where
 //! Mixer has the user authenticate and confirm using the application and then redirects them to the
 //! configured redirect URL, where a web server needs to be running to take the code from the user
 //! and exchange it for the OAuth token.
 //!
-//! `get_token_from_code` is used for exchanging the code for the token in the normal flow.
+//! `get_authorize_url_checked` does the same after checking the redirect URL with
+//! `check_redirect_url`, returning an error for a malformed one.
 //!
-//! `get_access_token_from_refresh` is used to get another access token from the refresh token.
+//! `parse_redirect` pulls the code and state out of the URL the user was redirected to, and
+//! `verify_state` checks the state against the one returned by `get_authorize_url_with_state`.
+//!
+//! `get_token_from_code` is used for exchanging the code for the token in the normal flow;
+//! `get_mixer_token_from_code` does the same but returns a `MixerToken` with the refresh token
+//! and granted scopes.
+//!
+//! `AuthCodeFlow` packages that flow into one reusable object, keeping the state values
+//! itself and checking them before exchanging codes.
+//!
+//! `get_authorize_url_pkce` and `get_token_from_code_pkce` are the same flow for public clients
+//! (desktop or mobile applications) that can't keep a client secret, using a PKCE verifier and
+//! challenge pair from `generate_pkce_pair` in place of the secret.
+//!
+//! `get_access_token_from_refresh` is used to get another access token from the refresh token;
+//! `get_mixer_token_from_refresh` returns it as a `MixerToken`. `RefreshingTokenProvider` does
+//! this automatically for a `REST` wrapper, before the token expires or when it's rejected.
 //!
 //! `get_shortcode` is used for generating a 6-digit code for the application's user to enter on
 //! Mixer's "shortcode" OAuth flow, which is useful when the application does not contain a web server
@@ -18,13 +35,32 @@
 //! on Mixer's site.
 //!
 //! `check_shortcode` is used to poll the Mixer API for the status of a user entering (or not entering)
-//! a shortcode.
+//! a shortcode; `wait_for_shortcode` polls it until the user has finished.
+//!
+//! `set_base_url` points all of the above at another server, e.g. a local mock in tests.
 
-use log::debug;
+use crate::{
+    internal::redact::{redact, redact_json},
+    rest::TokenProvider,
+};
+use failure::format_err;
+use lazy_static::lazy_static;
+use log::{debug, warn};
 use oauth2::{Config, Token, TokenError};
+use rand::{thread_rng, Rng, RngCore};
 use reqwest::Client;
-use serde_derive::Deserialize;
+use serde::{Deserialize as _, Deserializer, Serializer};
+use serde_derive::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+use typed_builder::TypedBuilder;
+use url::Url;
 
 /// Struct around the response from fetching an auth shortcode.
 #[derive(Debug, Deserialize)]
@@ -50,48 +86,133 @@ pub enum ShortcodeStatus {
     UserGrantedAccess(String),
 }
 
+/// OAuth token, with all of the fields Mixer sends.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MixerToken {
+    /// Token for making API calls on the user's behalf
+    pub access_token: String,
+    /// Token for getting a new access token once it expires
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Seconds until the access token expires
+    pub expires_in: u64,
+    /// Type of the token, e.g. "Bearer"
+    pub token_type: String,
+    /// Scopes granted to the token
+    #[serde(
+        default,
+        deserialize_with = "deserialize_scope",
+        serialize_with = "serialize_scope"
+    )]
+    pub scope: Vec<String>,
+}
+
+/// Split the space-separated scope string from the token response.
+fn deserialize_scope<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let scope = Option::<String>::deserialize(deserializer)?;
+    Ok(scope
+        .map(|s| s.split_whitespace().map(|s| s.to_owned()).collect())
+        .unwrap_or_default())
+}
+
+/// Join the scopes with spaces, as they're sent in the token response, so
+/// that a saved token can be loaded again.
+fn serialize_scope<S>(scope: &[String], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&scope.join(" "))
+}
+
+/// Query parameters from the redirect back to the application.
+#[derive(Debug, PartialEq)]
+pub struct RedirectParams {
+    /// Code to exchange for a token
+    pub code: String,
+    /// State value, to compare against the one sent in the authorize URL
+    pub state: String,
+}
+
+/// PKCE code verifier and its matching S256 challenge.
+#[derive(Debug, Clone)]
+pub struct PkcePair {
+    /// Secret verifier, kept by the application and sent when exchanging the code
+    pub verifier: String,
+    /// Challenge derived from the verifier, sent in the authorize URL
+    pub challenge: String,
+}
+
+/// Default base URL of Mixer's site, which the OAuth endpoints are under.
+pub const BASE_URL: &str = "https://mixer.com";
+
+lazy_static! {
+    static ref CURRENT_BASE_URL: RwLock<String> = RwLock::new(BASE_URL.to_owned());
+}
+
+/// Set the base URL that the OAuth endpoints are under, e.g. to point the
+/// functions in this module at a mock server; defaults to `BASE_URL`.
+///
+/// This applies to every later call in the process.
+///
+/// # Arguments
+///
+/// * `base_url` - URL that endpoints are appended to
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::{get_authorize_url, set_base_url, BASE_URL};
+/// set_base_url("http://localhost:8080/");
+/// let url = get_authorize_url("aaa", "bbb", &["s_1"], "ccc", false);
+/// assert!(url.starts_with("http://localhost:8080/oauth/authorize?"));
+/// # set_base_url(BASE_URL);
+/// ```
+pub fn set_base_url(base_url: &str) {
+    *CURRENT_BASE_URL.write().unwrap() = base_url.trim_end_matches('/').to_owned();
+}
+
+/// Get the URL of an endpoint under the base URL.
+///
+/// # Arguments
+///
+/// * `path` - path of the endpoint, without a leading `/`
+fn endpoint_url(path: &str) -> String {
+    format!("{}/{}", CURRENT_BASE_URL.read().unwrap(), path)
+}
+
 /// Get the endpoint for authorizing a user.
 ///
 /// https://dev.mixer.com/reference/oauth/quickdetails
 fn get_endpoint_auth_url() -> String {
-    #[cfg(not(test))]
-    return "https://mixer.com/oauth/authorize".to_owned();
-    #[cfg(test)]
-    return mockito::server_url();
+    endpoint_url("oauth/authorize")
 }
 
 /// Get the endpoint for exchanging the code for a token.
 ///
 /// https://dev.mixer.com/reference/oauth/quickdetails
 fn get_endpoint_token_url() -> String {
-    #[cfg(not(test))]
-    return "https://mixer.com/api/v1/oauth/token".to_owned();
-    #[cfg(test)]
-    return mockito::server_url();
+    endpoint_url("api/v1/oauth/token")
 }
 
 /// Get the endpoint for creating a shortcode.
 ///
 /// https://dev.mixer.com/reference/oauth/shortcodeauth#shortcode-flow-specification
 fn get_shortcode_url_start() -> String {
-    #[cfg(not(test))]
-    return "https://mixer.com/api/v1/oauth/shortcode ".to_owned();
-    #[cfg(test)]
-    return mockito::server_url();
+    endpoint_url("api/v1/oauth/shortcode")
 }
 
-/// Get the endpoint for creating a shortcode.
+/// Get the endpoint for checking on a shortcode.
 ///
 /// https://dev.mixer.com/reference/oauth/shortcodeauth#shortcode-flow-specification
 ///
 /// # Arguments
 ///
-/// * `_handle` - handle from the initial shortcode response
-fn get_shortcode_url_check(_handle: &str) -> String {
-    #[cfg(not(test))]
-    return format!("https://mixer.com/api/v1/oauth/shortcode/check/{}", _handle);
-    #[cfg(test)]
-    return mockito::server_url();
+/// * `handle` - handle from the initial shortcode response
+fn get_shortcode_url_check(handle: &str) -> String {
+    endpoint_url(&format!("api/v1/oauth/shortcode/check/{}", handle))
 }
 
 /// Create an OAuth2 Config struct instance.
@@ -109,16 +230,92 @@ fn init(client_id: &str, client_secret: &str, scopes: &[&str], redirect_url: &st
         get_endpoint_auth_url(),
         get_endpoint_token_url(),
     );
-    for scope in scopes {
-        config = config.add_scope((*scope).to_owned());
+    for scope in dedup_scopes(scopes) {
+        config = config.add_scope(scope.to_owned());
     }
     config = config.set_redirect_url(redirect_url);
-    config = config.set_state(format!("{}", rand::random::<u64>()));
+    config = config.set_state(generate_state());
     config
 }
 
+/// Create an OAuth2 Config struct instance, returning an error if the redirect
+/// URL isn't a valid absolute URL.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `client_secret` - your OAuth application secret
+/// * `scopes` - your desired OAuth scopes
+/// * `redirect_url` - your application's redirect URL
+fn init_checked(
+    client_id: &str,
+    client_secret: &str,
+    scopes: &[&str],
+    redirect_url: &str,
+) -> Result<Config, failure::Error> {
+    check_redirect_url(redirect_url)?;
+    Ok(init(client_id, client_secret, scopes, redirect_url))
+}
+
+/// Check that a redirect URL is a valid absolute URL, as Mixer requires.
+///
+/// # Arguments
+///
+/// * `redirect_url` - your application's redirect URL
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::check_redirect_url;
+/// assert!(check_redirect_url("http://localhost:8080/callback").is_ok());
+/// assert!(check_redirect_url("localhost/callback").is_err());
+/// ```
+pub fn check_redirect_url(redirect_url: &str) -> Result<(), failure::Error> {
+    Url::parse(redirect_url)
+        .map(|_| ())
+        .map_err(|e| format_err!("Invalid redirect URL '{}': {}", redirect_url, e))
+}
+
+/// Remove duplicate scopes, keeping the first of each and
+/// logging a warning for the others.
+///
+/// # Arguments
+///
+/// * `scopes` - your desired OAuth scopes
+fn dedup_scopes<'a>(scopes: &[&'a str]) -> Vec<&'a str> {
+    let mut deduped: Vec<&str> = Vec::with_capacity(scopes.len());
+    for &scope in scopes {
+        if deduped.contains(&scope) {
+            warn!("Duplicate OAuth scope '{}' ignored", scope);
+        } else {
+            deduped.push(scope);
+        }
+    }
+    deduped
+}
+
+/// Dedup the scopes for a flow that requires at least one.
+///
+/// # Arguments
+///
+/// * `scopes` - your desired OAuth scopes
+fn check_scopes<'a>(scopes: &[&'a str]) -> Result<Vec<&'a str>, failure::Error> {
+    if scopes.is_empty() {
+        return Err(format_err!("At least one OAuth scope is required"));
+    }
+    Ok(dedup_scopes(scopes))
+}
+
+/// Generate a random state value for the authorize URL.
+fn generate_state() -> String {
+    format!("{}", rand::random::<u64>())
+}
+
 /// Get the authorize URL for your application.
 ///
+/// The URL contains a random state value that isn't returned, so the redirect
+/// can't be checked against it; prefer `get_authorize_url_with_state`.
+///
 /// # Arguments
 ///
 /// * `client_id` - your OAuth application id
@@ -142,13 +339,162 @@ pub fn get_authorize_url(
     redirect_url: &str,
     force: bool,
 ) -> String {
-    let config = init(client_id, client_secret, scopes, redirect_url);
-    let mut url = config.authorize_url();
+    get_authorize_url_with_state(client_id, client_secret, scopes, redirect_url, force).0
+}
+
+/// Get the authorize URL for your application, along with the state value put in it.
+///
+/// The caller must store the state (e.g. in the user's session) and, when the user is
+/// redirected back, compare it to the state in the redirect URL with `verify_state`
+/// before exchanging the code. A mismatch means the redirect didn't come from this
+/// authorization request, and the code must not be used; this protects against CSRF.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `client_secret` - your OAuth application secret
+/// * `scopes` - your desired OAuth scopes
+/// * `redirect_url` - your application's redirect URL
+/// * `force` - set to `true` to force re-authentication
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::oauth::{get_authorize_url_with_state, parse_redirect, verify_state};
+/// let (url, state) = get_authorize_url_with_state("aaa", "bbb", &["s_1", "s_2", "s_3"], "ccc", false);
+/// // send the user to `url`, then once they're redirected back:
+/// # let redirected_to = "";
+/// let params = parse_redirect(redirected_to).unwrap();
+/// if !verify_state(&state, &params.state) {
+///     panic!("State mismatch");
+/// }
+/// ```
+pub fn get_authorize_url_with_state(
+    client_id: &str,
+    client_secret: &str,
+    scopes: &[&str],
+    redirect_url: &str,
+    force: bool,
+) -> (String, String) {
+    authorize_url_with_state(init(client_id, client_secret, scopes, redirect_url), force)
+}
+
+/// Get the authorize URL for your application, along with the state value put in it,
+/// after checking that the redirect URL is valid.
+///
+/// This is the same as `get_authorize_url_with_state`, but returns an error for a
+/// malformed redirect URL rather than a URL that Mixer will reject.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `client_secret` - your OAuth application secret
+/// * `scopes` - your desired OAuth scopes
+/// * `redirect_url` - your application's redirect URL
+/// * `force` - set to `true` to force re-authentication
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::get_authorize_url_checked;
+/// let (url, state) = get_authorize_url_checked(
+///     "aaa",
+///     "bbb",
+///     &["s_1"],
+///     "http://localhost:8080/callback",
+///     false,
+/// )
+/// .unwrap();
+/// assert!(get_authorize_url_checked("aaa", "bbb", &["s_1"], "not a url", false).is_err());
+/// ```
+pub fn get_authorize_url_checked(
+    client_id: &str,
+    client_secret: &str,
+    scopes: &[&str],
+    redirect_url: &str,
+    force: bool,
+) -> Result<(String, String), failure::Error> {
+    let config = init_checked(client_id, client_secret, scopes, redirect_url)?;
+    Ok(authorize_url_with_state(config, force))
+}
+
+/// Build the authorize URL from the config with a new state value.
+///
+/// # Arguments
+///
+/// * `config` - OAuth2 config
+/// * `force` - set to `true` to force re-authentication
+fn authorize_url_with_state(config: Config, force: bool) -> (String, String) {
+    let state = generate_state();
+    let mut url = config.set_state(state.clone()).authorize_url();
     if force {
         url.query_pairs_mut()
             .append_pair("approval_prompt", "force");
     }
-    url.into_string()
+    (url.into_string(), state)
+}
+
+/// Parse the code and state from the URL that the user was redirected to.
+///
+/// # Arguments
+///
+/// * `url` - full redirect URL, including the query string
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::parse_redirect;
+/// let params = parse_redirect("http://localhost/auth?code=abc&state=123").unwrap();
+/// assert_eq!("abc", params.code);
+/// assert_eq!("123", params.state);
+/// ```
+pub fn parse_redirect(url: &str) -> Result<RedirectParams, failure::Error> {
+    let url = Url::parse(url)?;
+    let mut code = None;
+    let mut state = None;
+    let mut error = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => state = Some(value.into_owned()),
+            "error" => error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    if let Some(e) = error {
+        return Err(format_err!("Authorization failed: {}", e));
+    }
+    match (code, state) {
+        (Some(code), Some(state)) => Ok(RedirectParams { code, state }),
+        (None, _) => Err(format_err!("Redirect URL has no code")),
+        (_, None) => Err(format_err!("Redirect URL has no state")),
+    }
+}
+
+/// Check that the state from the redirect matches the one sent in the authorize URL.
+///
+/// # Arguments
+///
+/// * `expected` - state from `get_authorize_url_with_state`
+/// * `received` - state from `parse_redirect`
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::verify_state;
+/// assert!(verify_state("123", "123"));
+/// assert!(!verify_state("123", "456"));
+/// ```
+pub fn verify_state(expected: &str, received: &str) -> bool {
+    if expected.is_empty() || expected.len() != received.len() {
+        return false;
+    }
+    // compare every byte so the time taken doesn't leak the matching prefix
+    expected
+        .bytes()
+        .zip(received.bytes())
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        == 0
 }
 
 /// Exchange the code from a user's browser for an OAuth token.
@@ -178,6 +524,286 @@ pub fn get_token_from_code(
     config.exchange_code(code)
 }
 
+/// Generate a random PKCE verifier and its S256 challenge.
+///
+/// The verifier must be stored by the application until the code is exchanged
+/// for a token; the challenge is sent in the authorize URL.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::generate_pkce_pair;
+/// let pair = generate_pkce_pair();
+/// assert_eq!(43, pair.verifier.len());
+/// ```
+pub fn generate_pkce_pair() -> PkcePair {
+    let mut bytes = [0u8; 32];
+    thread_rng().fill_bytes(&mut bytes);
+    let verifier = base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD);
+    let challenge = pkce_challenge(&verifier);
+    PkcePair {
+        verifier,
+        challenge,
+    }
+}
+
+/// Compute the S256 challenge for a PKCE verifier.
+///
+/// # Arguments
+///
+/// * `verifier` - code verifier
+fn pkce_challenge(verifier: &str) -> String {
+    let hash = Sha256::digest(verifier.as_bytes());
+    base64::encode_config(&hash, base64::URL_SAFE_NO_PAD)
+}
+
+/// Get the authorize URL for your application, using PKCE instead of a client secret.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `scopes` - your desired OAuth scopes
+/// * `redirect_url` - your application's redirect URL
+/// * `code_challenge` - the challenge from `generate_pkce_pair`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::oauth::{generate_pkce_pair, get_authorize_url_pkce};
+/// let pair = generate_pkce_pair();
+/// let url = get_authorize_url_pkce("aaa", &["s_1", "s_2", "s_3"], "ccc", &pair.challenge);
+/// ```
+pub fn get_authorize_url_pkce(
+    client_id: &str,
+    scopes: &[&str],
+    redirect_url: &str,
+    code_challenge: &str,
+) -> String {
+    let config = init(client_id, "", scopes, redirect_url);
+    let mut url = config.authorize_url();
+    url.query_pairs_mut()
+        .append_pair("code_challenge", code_challenge)
+        .append_pair("code_challenge_method", "S256");
+    url.into_string()
+}
+
+/// Exchange the code from a user's browser for an OAuth token, using the
+/// PKCE verifier instead of a client secret.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `scopes` - your desired OAuth scopes
+/// * `redirect_url` - your application's redirect URL
+/// * `code` - the code from the user
+/// * `code_verifier` - the verifier from `generate_pkce_pair`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::oauth::{generate_pkce_pair, get_token_from_code_pkce};
+/// # let pair = generate_pkce_pair();
+/// let token = get_token_from_code_pkce("aaa", &["s_1", "s_2", "s_3"], "ccc", "code_here", &pair.verifier).unwrap();
+/// ```
+pub fn get_token_from_code_pkce(
+    client_id: &str,
+    scopes: &[&str],
+    redirect_url: &str,
+    code: &str,
+    code_verifier: &str,
+) -> Result<Token, failure::Error> {
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("client_id", client_id),
+        ("code", code),
+        ("code_verifier", code_verifier),
+        ("redirect_uri", redirect_url),
+        ("scope", &scopes.join(" ")),
+    ];
+    let text = request_token(&params)?;
+    let token: Token = serde_json::from_str(&text)?;
+    Ok(token)
+}
+
+/// Exchange the code from a user's browser for a `MixerToken`.
+///
+/// This is the same as `get_token_from_code`, but returns all of the fields
+/// that Mixer sends, including the refresh token and granted scopes.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `client_secret` - your OAuth application secret
+/// * `scopes` - your desired OAuth scopes
+/// * `redirect_url` - your application's redirect URL
+/// * `code` - the code from the user
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::oauth::get_mixer_token_from_code;
+/// let token = get_mixer_token_from_code("aaa", "bbb", &["s_1", "s_2", "s_3"], "ccc", "code_here").unwrap();
+/// ```
+pub fn get_mixer_token_from_code(
+    client_id: &str,
+    client_secret: &str,
+    scopes: &[&str],
+    redirect_url: &str,
+    code: &str,
+) -> Result<MixerToken, failure::Error> {
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("code", code),
+        ("redirect_uri", redirect_url),
+        ("scope", &scopes.join(" ")),
+    ];
+    let text = request_token(&params)?;
+    let token: MixerToken = serde_json::from_str(&text)?;
+    Ok(token)
+}
+
+/// Number of outstanding states kept by `AuthCodeFlow` before the oldest are dropped.
+const MAX_PENDING_STATES: usize = 256;
+
+/// Mixer's standard OAuth flow for applications with a web server, keeping
+/// track of the state values sent in authorize URLs.
+///
+/// Each state is accepted once, when the code it was sent with is exchanged.
+pub struct AuthCodeFlow {
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    redirect_url: String,
+    states: Mutex<VecDeque<String>>,
+}
+
+impl AuthCodeFlow {
+    /// Create a new flow for your application.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your OAuth application id
+    /// * `client_secret` - your OAuth application secret
+    /// * `scopes` - your desired OAuth scopes
+    /// * `redirect_url` - your application's redirect URL
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::oauth::AuthCodeFlow;
+    /// let flow = AuthCodeFlow::new("aaa", "bbb", &["s_1", "s_2", "s_3"], "ccc");
+    /// ```
+    pub fn new(client_id: &str, client_secret: &str, scopes: &[&str], redirect_url: &str) -> Self {
+        AuthCodeFlow {
+            client_id: client_id.to_owned(),
+            client_secret: client_secret.to_owned(),
+            scopes: scopes.iter().map(|&s| s.to_owned()).collect(),
+            redirect_url: redirect_url.to_owned(),
+            states: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Get an authorize URL to send a user to, keeping its state for `exchange`.
+    ///
+    /// # Arguments
+    ///
+    /// * `force` - set to `true` to force re-authentication
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::oauth::AuthCodeFlow;
+    /// # let flow = AuthCodeFlow::new("aaa", "bbb", &["s_1", "s_2", "s_3"], "ccc");
+    /// let url = flow.authorize_url(false);
+    /// ```
+    pub fn authorize_url(&self, force: bool) -> String {
+        let (url, state) = get_authorize_url_with_state(
+            &self.client_id,
+            &self.client_secret,
+            &self.scopes(),
+            &self.redirect_url,
+            force,
+        );
+        let mut states = self.states.lock().unwrap();
+        states.push_back(state);
+        if states.len() > MAX_PENDING_STATES {
+            states.pop_front();
+        }
+        url
+    }
+
+    /// Exchange the code from the redirect for a token, after checking that
+    /// the state is one from `authorize_url`.
+    ///
+    /// The code isn't sent if the state is unknown or was already used.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - the code from the redirect
+    /// * `state` - the state from the redirect
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::oauth::{parse_redirect, AuthCodeFlow};
+    /// # let flow = AuthCodeFlow::new("aaa", "bbb", &["s_1", "s_2", "s_3"], "ccc");
+    /// # let redirected_to = "";
+    /// let params = parse_redirect(redirected_to).unwrap();
+    /// let token = flow.exchange(&params.code, &params.state).unwrap();
+    /// ```
+    pub fn exchange(&self, code: &str, state: &str) -> Result<MixerToken, failure::Error> {
+        {
+            let mut states = self.states.lock().unwrap();
+            match states.iter().position(|s| verify_state(s, state)) {
+                Some(index) => {
+                    states.remove(index);
+                }
+                None => return Err(format_err!("Unknown or already used state")),
+            }
+        }
+        get_mixer_token_from_code(
+            &self.client_id,
+            &self.client_secret,
+            &self.scopes(),
+            &self.redirect_url,
+            code,
+        )
+    }
+
+    fn scopes(&self) -> Vec<&str> {
+        self.scopes.iter().map(String::as_str).collect()
+    }
+}
+
+/// Make a request to the token endpoint, returning the response body.
+///
+/// # Arguments
+///
+/// * `params` - form parameters
+fn request_token(params: &[(&str, &str)]) -> Result<String, failure::Error> {
+    let client = Client::new();
+    let req = client
+        .post(&get_endpoint_token_url())
+        .form(params)
+        .build()?;
+    debug!("Requesting token: {}", redact(&req));
+    let mut resp = client.execute(req)?;
+    let text = resp.text()?;
+    debug!("Token response: {}", redact_json(&text));
+    if !resp.status().is_success() {
+        return match serde_json::from_str::<TokenError>(&text) {
+            Ok(e) => Err(format_err!("{}", e)),
+            Err(_) => Err(format_err!(
+                "Got status {} from the token endpoint",
+                resp.status().as_u16()
+            )),
+        };
+    }
+    Ok(text)
+}
+
 /// Exchange a refresh token for another access token.
 ///
 /// This is required when the access token from a successful authentication expires -
@@ -210,6 +836,138 @@ pub fn get_access_token_from_refresh(
     config.exchange_refresh_token(refresh_token)
 }
 
+/// Exchange a refresh token for a `MixerToken`.
+///
+/// This is the same as `get_access_token_from_refresh`, but returns all of the
+/// fields that Mixer sends, including the new refresh token if there is one.
+///
+/// # Arguments
+///
+/// * `client_id` - your OAuth application id
+/// * `client_secret` - your OAuth application secret
+/// * `refresh_token` - the refresh token from the successful auth
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::oauth::get_mixer_token_from_refresh;
+/// let token = get_mixer_token_from_refresh("aaa", "bbb", "refresh_token_here").unwrap();
+/// ```
+pub fn get_mixer_token_from_refresh(
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<MixerToken, failure::Error> {
+    let params = [
+        ("grant_type", "refresh_token"),
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("refresh_token", refresh_token),
+    ];
+    let text = request_token(&params)?;
+    let token: MixerToken = serde_json::from_str(&text)?;
+    Ok(token)
+}
+
+/// Default time before a token expires that `RefreshingTokenProvider` refreshes it.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Token provider for `REST::set_token_provider` that refreshes a `MixerToken`
+/// shortly before it expires, or when Mixer rejects it.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::{oauth::{get_mixer_token_from_code, RefreshingTokenProvider}, REST};
+/// # use std::time::Duration;
+/// let token = get_mixer_token_from_code("aaa", "bbb", &["s_1"], "ccc", "code_here").unwrap();
+/// let provider = RefreshingTokenProvider::new("aaa", "bbb", token)
+///     .expiry_margin(Duration::from_secs(300));
+/// let mut api = REST::new("aaa");
+/// api.set_token_provider(Box::new(provider));
+/// ```
+pub struct RefreshingTokenProvider {
+    client_id: String,
+    client_secret: String,
+    margin: Duration,
+    /// Current token and when it expires
+    token: Mutex<(MixerToken, Instant)>,
+}
+
+impl RefreshingTokenProvider {
+    /// Create a provider for a token that was just received.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your OAuth application id
+    /// * `client_secret` - your OAuth application secret
+    /// * `token` - token from one of the OAuth flows, with a refresh token
+    pub fn new(client_id: &str, client_secret: &str, token: MixerToken) -> Self {
+        let expires_at = Instant::now() + Duration::from_secs(token.expires_in);
+        RefreshingTokenProvider {
+            client_id: client_id.to_owned(),
+            client_secret: client_secret.to_owned(),
+            margin: EXPIRY_MARGIN,
+            token: Mutex::new((token, expires_at)),
+        }
+    }
+
+    /// Set how long before the token expires that it's refreshed; defaults to a minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `margin` - time before expiry
+    pub fn expiry_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Current token, e.g. to save the latest refresh token.
+    pub fn token(&self) -> MixerToken {
+        self.token.lock().unwrap().0.clone()
+    }
+
+    /// Refresh the token, keeping the old refresh token if Mixer doesn't send a new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - current token and its expiry, which is replaced
+    fn refresh_locked(
+        &self,
+        current: &mut (MixerToken, Instant),
+    ) -> Result<String, failure::Error> {
+        let refresh_token = current
+            .0
+            .refresh_token
+            .clone()
+            .ok_or_else(|| format_err!("No refresh token to get a new access token with"))?;
+        debug!("Refreshing OAuth access token");
+        let mut token =
+            get_mixer_token_from_refresh(&self.client_id, &self.client_secret, &refresh_token)?;
+        if token.refresh_token.is_none() {
+            token.refresh_token = Some(refresh_token);
+        }
+        let access_token = token.access_token.clone();
+        let expires_at = Instant::now() + Duration::from_secs(token.expires_in);
+        *current = (token, expires_at);
+        Ok(access_token)
+    }
+}
+
+impl TokenProvider for RefreshingTokenProvider {
+    fn access_token(&self) -> Result<String, failure::Error> {
+        let mut current = self.token.lock().unwrap();
+        if Instant::now() + self.margin >= current.1 {
+            return self.refresh_locked(&mut current);
+        }
+        Ok(current.0.access_token.clone())
+    }
+
+    fn refresh(&self) -> Result<String, failure::Error> {
+        self.refresh_locked(&mut self.token.lock().unwrap())
+    }
+}
+
 /// Get an authentication shortcode.
 ///
 /// This is used for completing the OAuth flow for a user without supplying a redirect URL
@@ -221,6 +979,8 @@ pub fn get_access_token_from_refresh(
 /// or not the user has done so via making repeated API calls to another method, wrapped in
 /// this library by the `check_shortcode` function.
 ///
+/// Returns an error without making the request if `scopes` is empty.
+///
 /// # Arguments
 ///
 /// * `client_id` - your OAuth application id
@@ -240,15 +1000,21 @@ pub fn get_shortcode(
     client_secret: &str,
     scopes: &[&str],
 ) -> Result<ShortcodeResponse, failure::Error> {
+    let scopes = check_scopes(scopes)?;
     let client = Client::new();
     let json = json!({
         "client_id": client_id,
         "client_secret": client_secret,
         "scope": scopes.join(" "),
     });
-    let mut resp = client.post(&get_shortcode_url_start()).json(&json).send()?;
+    let req = client
+        .post(&get_shortcode_url_start())
+        .json(&json)
+        .build()?;
+    debug!("Requesting shortcode: {}", redact(&req));
+    let mut resp = client.execute(req)?;
     let text = resp.text()?;
-    debug!("Shortcode generation response: {}", text);
+    debug!("Shortcode generation response: {}", redact_json(&text));
     let data: ShortcodeResponse = serde_json::from_str(&text)?;
     Ok(data)
 }
@@ -267,7 +1033,7 @@ pub fn get_shortcode(
 /// # Arguments
 ///
 /// * `handle` - the handle received from starting the shortcode flow; this
-///              is not the code that's sent to the user
+///   is not the code that's sent to the user
 ///
 /// # Examples
 ///
@@ -305,19 +1071,104 @@ pub fn check_shortcode(handle: &str) -> ShortcodeStatus {
     }
 }
 
+/// How `wait_for_shortcode` polls for the status of a shortcode.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::oauth::ShortcodePolling;
+/// # use std::time::Duration;
+/// let polling = ShortcodePolling::builder()
+///     .interval(Duration::from_secs(5))
+///     .jitter(Duration::from_secs(1))
+///     .build();
+/// ```
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct ShortcodePolling {
+    /// Time between checks
+    #[builder(default = Duration::from_secs(3))]
+    pub interval: Duration,
+    /// Largest random amount added to or taken from each interval, so that
+    /// clients started together don't poll at the same moments; defaults to none
+    #[builder(default)]
+    pub jitter: Duration,
+}
+
+impl Default for ShortcodePolling {
+    fn default() -> Self {
+        ShortcodePolling::builder().build()
+    }
+}
+
+impl ShortcodePolling {
+    /// Time to wait before the next check.
+    fn next_delay(&self) -> Duration {
+        if self.jitter == Duration::from_secs(0) {
+            return self.interval;
+        }
+        let jitter = self.jitter.as_millis() as i64;
+        let offset = thread_rng().gen_range(-jitter, jitter + 1);
+        let millis = (self.interval.as_millis() as i64 + offset).max(0);
+        Duration::from_millis(millis as u64)
+    }
+}
+
+/// Check on the status of a shortcode until the user has granted or denied
+/// access, or the handle has expired.
+///
+/// This blocks the current thread, sleeping between checks.
+///
+/// # Arguments
+///
+/// * `handle` - the handle received from starting the shortcode flow
+/// * `polling` - interval and jitter between checks
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::oauth::{wait_for_shortcode, ShortcodePolling, ShortcodeStatus};
+/// match wait_for_shortcode("some_handle", &ShortcodePolling::default()) {
+///     ShortcodeStatus::UserGrantedAccess(code) => {
+///         // exchange the code for a token
+///     }
+///     _ => {
+///         // start again
+///     }
+/// }
+/// ```
+pub fn wait_for_shortcode(handle: &str, polling: &ShortcodePolling) -> ShortcodeStatus {
+    loop {
+        match check_shortcode(handle) {
+            ShortcodeStatus::WaitingOnUser => thread::sleep(polling.next_delay()),
+            status => return status,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        check_shortcode, get_access_token_from_refresh, get_authorize_url, get_shortcode,
-        get_token_from_code, ShortcodeStatus,
+        check_scopes, check_shortcode, dedup_scopes, generate_pkce_pair,
+        get_access_token_from_refresh, get_authorize_url, get_authorize_url_checked,
+        get_authorize_url_pkce, get_authorize_url_with_state, get_mixer_token_from_code,
+        get_mixer_token_from_refresh, get_shortcode, get_token_from_code, get_token_from_code_pkce,
+        parse_redirect, pkce_challenge, set_base_url, verify_state, wait_for_shortcode,
+        AuthCodeFlow, MixerToken, RefreshingTokenProvider, ShortcodePolling, ShortcodeStatus,
     };
-    use mockito::mock;
+    use crate::rest::TokenProvider;
+    use mockito::{mock, Matcher};
+    use std::time::Duration;
 
     const CLIENT_ID: &str = "a";
     const CLIENT_SECRET: &str = "b";
     const SCOPES: [&str; 2] = ["c", "d"];
     const REDIRECT_URL: &str = "e";
 
+    /// Point the module at the mock server.
+    fn mock_base_url() {
+        set_base_url(&mockito::server_url());
+    }
+
     #[test]
     fn test_get_authorize_url() {
         let url = get_authorize_url(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL, false);
@@ -329,20 +1180,155 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn test_get_authorize_url_duplicate_scopes() {
+        let url = get_authorize_url(
+            CLIENT_ID,
+            CLIENT_SECRET,
+            &["c", "d", "c"],
+            REDIRECT_URL,
+            false,
+        );
+        assert!(url.contains("scope=c+d&"));
+    }
+
+    #[test]
+    fn test_dedup_scopes() {
+        assert_eq!(vec!["a", "b"], dedup_scopes(&["a", "b", "a", "b"]));
+        assert_eq!(vec!["b", "a"], dedup_scopes(&["b", "a"]));
+        assert!(dedup_scopes(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_check_scopes() {
+        assert_eq!(vec!["a"], check_scopes(&["a", "a"]).unwrap());
+        assert!(check_scopes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_get_shortcode_no_scopes() {
+        mock_base_url();
+        let _m1 = mock("POST", "/api/v1/oauth/shortcode").expect(0).create();
+        assert!(get_shortcode(CLIENT_ID, CLIENT_SECRET, &[]).is_err());
+        _m1.assert();
+    }
+
     #[test]
     fn test_get_authorize_url_force() {
         let url = get_authorize_url(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL, true);
         assert!(url.contains("approval_prompt=force"));
     }
 
+    #[test]
+    fn test_get_authorize_url_with_state() {
+        let (url, state) =
+            get_authorize_url_with_state(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL, false);
+        assert!(url.contains(&format!("&state={}", state)));
+
+        let (_, other_state) =
+            get_authorize_url_with_state(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL, false);
+        assert_ne!(state, other_state);
+    }
+
+    #[test]
+    fn test_get_authorize_url_checked() {
+        let (url, state) = get_authorize_url_checked(
+            CLIENT_ID,
+            CLIENT_SECRET,
+            &SCOPES,
+            "http://localhost:8080/callback",
+            true,
+        )
+        .unwrap();
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A8080%2Fcallback"));
+        assert!(url.contains(&format!("state={}", state)));
+        assert!(url.ends_with("&approval_prompt=force"));
+
+        let err =
+            get_authorize_url_checked(CLIENT_ID, CLIENT_SECRET, &SCOPES, "::garbage::", false)
+                .unwrap_err()
+                .to_string();
+        assert!(err.starts_with("Invalid redirect URL '::garbage::'"));
+    }
+
+    #[test]
+    fn test_parse_redirect() {
+        let params = parse_redirect("http://localhost:8080/auth?code=abc&state=123").unwrap();
+        assert_eq!("abc", params.code);
+        assert_eq!("123", params.state);
+        assert!(parse_redirect("http://localhost:8080/auth?state=123").is_err());
+        assert!(parse_redirect("http://localhost:8080/auth?code=abc").is_err());
+        assert!(parse_redirect("http://localhost:8080/auth?error=access_denied&state=1").is_err());
+        assert!(parse_redirect("not a url").is_err());
+    }
+
+    #[test]
+    fn test_verify_state() {
+        assert!(verify_state("123", "123"));
+        assert!(!verify_state("123", "124"));
+        assert!(!verify_state("123", "1234"));
+        assert!(!verify_state("", ""));
+    }
+
+    #[test]
+    fn test_pkce_pair() {
+        let pair = generate_pkce_pair();
+        assert_eq!(43, pair.verifier.len());
+        assert_eq!(pkce_challenge(&pair.verifier), pair.challenge);
+        assert_ne!(pair.verifier, generate_pkce_pair().verifier);
+        assert_eq!(
+            "sBp1ZzE6U3kLcu4D1T5FlNP5QaApVwTquO9-E9mvc7I",
+            pkce_challenge("dBjftJeZ4CVP-mJ92K9qYgsNxnsqUIbkB9lPf1yrxNY")
+        );
+    }
+
+    #[test]
+    fn test_get_authorize_url_pkce() {
+        let url = get_authorize_url_pkce(CLIENT_ID, &SCOPES, REDIRECT_URL, "challenge");
+        assert!(url.contains("code_challenge=challenge&code_challenge_method=S256"));
+        assert!(!url.contains("client_secret"));
+    }
+
+    #[test]
+    fn test_get_token_from_code_pkce() {
+        mock_base_url();
+        let body = r#"{
+            "access_token": "123abc",
+            "expires_in": 3600,
+            "token_type": "test"
+        }"#;
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .match_body(Matcher::Regex("code_verifier=verifier".to_owned()))
+            .with_body(body)
+            .with_header("Content-Type", "application/json")
+            .create();
+        let token =
+            get_token_from_code_pkce(CLIENT_ID, &SCOPES, REDIRECT_URL, "the_code", "verifier")
+                .unwrap();
+        assert_eq!("123abc", token.access_token);
+    }
+
+    #[test]
+    fn test_get_token_from_code_pkce_error() {
+        mock_base_url();
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .with_status(400)
+            .with_body(r#"{"error": "invalid_grant"}"#)
+            .with_header("Content-Type", "application/json")
+            .create();
+        let res = get_token_from_code_pkce(CLIENT_ID, &SCOPES, REDIRECT_URL, "code", "verifier");
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_get_token_from_code() {
+        mock_base_url();
         let body = r#"{
             "access_token": "123abc",
             "expires_in": 3600,
             "token_type": "test"
         }"#;
-        let _m1 = mock("POST", "/")
+        let _m1 = mock("POST", "/api/v1/oauth/token")
             .with_body(body)
             .with_header("Content-Type", "application/json")
             .create();
@@ -351,14 +1337,92 @@ mod tests {
         assert_eq!("123abc", token.access_token);
     }
 
+    #[test]
+    fn test_get_mixer_token_from_code() {
+        mock_base_url();
+        let body = r#"{
+            "access_token": "123abc",
+            "refresh_token": "456def",
+            "expires_in": 21600,
+            "token_type": "Bearer",
+            "scope": "chat:connect chat:chat"
+        }"#;
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .match_body(Matcher::Regex("client_secret=b".to_owned()))
+            .with_body(body)
+            .with_header("Content-Type", "application/json")
+            .create();
+        let token =
+            get_mixer_token_from_code(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL, "123abc")
+                .unwrap();
+        assert_eq!("123abc", token.access_token);
+        assert_eq!(Some("456def".to_owned()), token.refresh_token);
+        assert_eq!(21600, token.expires_in);
+        assert_eq!("Bearer", token.token_type);
+        assert_eq!(vec!["chat:connect", "chat:chat"], token.scope);
+    }
+
+    #[test]
+    fn test_mixer_token_round_trip() {
+        let token = MixerToken {
+            access_token: "123abc".to_owned(),
+            refresh_token: Some("456def".to_owned()),
+            expires_in: 21600,
+            token_type: "Bearer".to_owned(),
+            scope: vec!["chat:connect".to_owned(), "chat:chat".to_owned()],
+        };
+        let text = serde_json::to_string(&token).unwrap();
+        assert!(text.contains(r#""scope":"chat:connect chat:chat""#));
+        let loaded: MixerToken = serde_json::from_str(&text).unwrap();
+        assert_eq!(token, loaded);
+    }
+
+    #[test]
+    fn test_auth_code_flow() {
+        mock_base_url();
+        let body = r#"{"access_token": "123abc", "expires_in": 3600, "token_type": "Bearer"}"#;
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .match_body(Matcher::Regex("code=xyz".to_owned()))
+            .with_body(body)
+            .with_header("Content-Type", "application/json")
+            .expect(1)
+            .create();
+        let flow = AuthCodeFlow::new(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL);
+        let url = flow.authorize_url(false);
+        let state = url.split("state=").nth(1).unwrap().to_owned();
+
+        assert!(flow.exchange("xyz", "not the state").is_err());
+        let token = flow.exchange("xyz", &state).unwrap();
+        assert_eq!("123abc", token.access_token);
+        // each state can only be used once
+        assert!(flow.exchange("xyz", &state).is_err());
+        _m1.assert();
+    }
+
+    #[test]
+    fn test_get_mixer_token_no_scope() {
+        mock_base_url();
+        let body = r#"{"access_token": "123abc", "expires_in": 3600, "token_type": "Bearer"}"#;
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .with_body(body)
+            .with_header("Content-Type", "application/json")
+            .create();
+        let token =
+            get_mixer_token_from_code(CLIENT_ID, CLIENT_SECRET, &SCOPES, REDIRECT_URL, "123abc")
+                .unwrap();
+        assert_eq!(None, token.refresh_token);
+        assert!(token.scope.is_empty());
+    }
+
     #[test]
     fn test_get_access_token_from_refresh() {
+        mock_base_url();
         let body = r#"{
             "access_token": "123abc",
             "expires_in": 3600,
             "token_type": "test"
         }"#;
-        let _m1 = mock("POST", "/")
+        let _m1 = mock("POST", "/api/v1/oauth/token")
             .with_body(body)
             .with_header("Content-Type", "application/json")
             .create();
@@ -373,14 +1437,74 @@ mod tests {
         assert_eq!("123abc", token.access_token);
     }
 
+    #[test]
+    fn test_get_mixer_token_from_refresh() {
+        mock_base_url();
+        let body = r#"{"access_token": "new", "expires_in": 3600, "token_type": "Bearer"}"#;
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .match_body(Matcher::Regex(
+                "grant_type=refresh_token&.*refresh_token=456def".to_owned(),
+            ))
+            .with_body(body)
+            .with_header("Content-Type", "application/json")
+            .create();
+        let token = get_mixer_token_from_refresh(CLIENT_ID, CLIENT_SECRET, "456def").unwrap();
+        assert_eq!("new", token.access_token);
+    }
+
+    fn mixer_token(access_token: &str, expires_in: u64) -> MixerToken {
+        MixerToken {
+            access_token: access_token.to_owned(),
+            refresh_token: Some("456def".to_owned()),
+            expires_in,
+            token_type: "Bearer".to_owned(),
+            scope: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_refreshing_token_provider() {
+        mock_base_url();
+        let body = r#"{"access_token": "new", "expires_in": 3600, "token_type": "Bearer"}"#;
+        let _m1 = mock("POST", "/api/v1/oauth/token")
+            .match_body(Matcher::Regex("refresh_token=456def".to_owned()))
+            .with_body(body)
+            .with_header("Content-Type", "application/json")
+            .expect(2)
+            .create();
+
+        let provider =
+            RefreshingTokenProvider::new(CLIENT_ID, CLIENT_SECRET, mixer_token("old", 3600));
+        assert_eq!("old", provider.access_token().unwrap());
+        assert_eq!("new", provider.refresh().unwrap());
+        assert_eq!("new", provider.access_token().unwrap());
+        // the refresh token is kept when Mixer doesn't send a new one
+        assert_eq!(Some("456def".to_owned()), provider.token().refresh_token);
+
+        // refreshed when the token is within the margin of expiring
+        let provider =
+            RefreshingTokenProvider::new(CLIENT_ID, CLIENT_SECRET, mixer_token("old", 30));
+        assert_eq!("new", provider.access_token().unwrap());
+        _m1.assert();
+    }
+
+    #[test]
+    fn test_refreshing_token_provider_no_refresh_token() {
+        let mut token = mixer_token("old", 0);
+        token.refresh_token = None;
+        let provider = RefreshingTokenProvider::new(CLIENT_ID, CLIENT_SECRET, token);
+        assert!(provider.access_token().is_err());
+    }
+
     #[test]
     fn test_get_shortcode() {
+        mock_base_url();
         let body = r#"{
             "code": "foo",
             "expires_in": 120,
             "handle": "bar"
         }"#;
-        let _m1 = mock("POST", "/")
+        let _m1 = mock("POST", "/api/v1/oauth/shortcode")
             .with_header("Content-Type", "application/json")
             .with_body(body)
             .create();
@@ -392,8 +1516,9 @@ mod tests {
 
     #[test]
     fn test_check_shortcode_200() {
+        mock_base_url();
         let body = r#"{"code": "foo"}"#;
-        let _m1 = mock("GET", "/")
+        let _m1 = mock("GET", "/api/v1/oauth/shortcode/check/bar")
             .with_header("Content-Type", "application/json")
             .with_body(body)
             .create();
@@ -403,22 +1528,68 @@ mod tests {
 
     #[test]
     fn test_check_shortcode_204() {
-        let _m1 = mock("GET", "/").with_status(204).create();
+        mock_base_url();
+        let _m1 = mock("GET", "/api/v1/oauth/shortcode/check/bar")
+            .with_status(204)
+            .create();
         let status = check_shortcode("bar");
         assert_eq!(status, ShortcodeStatus::WaitingOnUser);
     }
 
     #[test]
     fn test_check_shortcode_403() {
-        let _m1 = mock("GET", "/").with_status(403).create();
+        mock_base_url();
+        let _m1 = mock("GET", "/api/v1/oauth/shortcode/check/bar")
+            .with_status(403)
+            .create();
         let status = check_shortcode("bar");
         assert_eq!(status, ShortcodeStatus::UserDeniedAccess);
     }
 
     #[test]
     fn test_check_shortcode_404() {
-        let _m1 = mock("GET", "/").with_status(404).create();
+        mock_base_url();
+        let _m1 = mock("GET", "/api/v1/oauth/shortcode/check/bar")
+            .with_status(404)
+            .create();
         let status = check_shortcode("bar");
         assert_eq!(status, ShortcodeStatus::HandleInvalid);
     }
+
+    #[test]
+    fn test_wait_for_shortcode() {
+        mock_base_url();
+        let _m1 = mock("GET", "/api/v1/oauth/shortcode/check/baz")
+            .with_status(403)
+            .expect(1)
+            .create();
+        let status = wait_for_shortcode("baz", &ShortcodePolling::default());
+        assert_eq!(status, ShortcodeStatus::UserDeniedAccess);
+        _m1.assert();
+    }
+
+    #[test]
+    fn test_shortcode_polling_jitter() {
+        let polling = ShortcodePolling::default();
+        assert_eq!(Duration::from_secs(3), polling.next_delay());
+
+        let polling = ShortcodePolling::builder()
+            .interval(Duration::from_millis(1000))
+            .jitter(Duration::from_millis(200))
+            .build();
+        for _ in 0..100 {
+            let delay = polling.next_delay();
+            assert!(delay >= Duration::from_millis(800));
+            assert!(delay <= Duration::from_millis(1200));
+        }
+
+        // never negative
+        let polling = ShortcodePolling::builder()
+            .interval(Duration::from_millis(10))
+            .jitter(Duration::from_millis(100))
+            .build();
+        for _ in 0..100 {
+            assert!(polling.next_delay() <= Duration::from_millis(110));
+        }
+    }
 }
@@ -0,0 +1,111 @@
+//! Cache of response bodies by ETag, for sending conditional requests.
+
+use std::collections::{HashMap, VecDeque};
+
+/// Cached response to a `GET`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+/// Least-recently-used cache of responses, keyed by URL.
+pub(crate) struct EtagCache {
+    capacity: usize,
+    entries: HashMap<String, CachedResponse>,
+    /// URLs from least to most recently used
+    order: VecDeque<String>,
+}
+
+impl EtagCache {
+    /// Create an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - maximum number of responses kept
+    pub fn new(capacity: usize) -> Self {
+        EtagCache {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Get the cached response for a URL, marking it as recently used.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - full URL of the call
+    pub fn get(&mut self, url: &str) -> Option<CachedResponse> {
+        let cached = self.entries.get(url)?.clone();
+        self.touch(url);
+        Some(cached)
+    }
+
+    /// Cache a response, dropping the least recently used if the cache is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - full URL of the call
+    /// * `response` - response to cache
+    pub fn insert(&mut self, url: &str, response: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(url.to_owned(), response).is_some() {
+            self.touch(url);
+            return;
+        }
+        self.order.push_back(url.to_owned());
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Move a URL to the most recently used end.
+    fn touch(&mut self, url: &str) {
+        if let Some(i) = self.order.iter().position(|u| u == url) {
+            if let Some(url) = self.order.remove(i) {
+                self.order.push_back(url);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedResponse, EtagCache};
+
+    fn response(etag: &str) -> CachedResponse {
+        CachedResponse {
+            etag: etag.to_owned(),
+            body: format!("body {}", etag),
+        }
+    }
+
+    #[test]
+    fn least_recently_used_dropped() {
+        let mut cache = EtagCache::new(2);
+        cache.insert("a", response("1"));
+        cache.insert("b", response("2"));
+        assert_eq!(Some(response("1")), cache.get("a"));
+        cache.insert("c", response("3"));
+        assert_eq!(None, cache.get("b"));
+        assert_eq!(Some(response("1")), cache.get("a"));
+        assert_eq!(Some(response("3")), cache.get("c"));
+
+        cache.insert("a", response("4"));
+        cache.insert("d", response("5"));
+        assert_eq!(None, cache.get("c"));
+        assert_eq!(Some(response("4")), cache.get("a"));
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut cache = EtagCache::new(0);
+        cache.insert("a", response("1"));
+        assert_eq!(None, cache.get("a"));
+    }
+}
@@ -0,0 +1,710 @@
+//! Helper for channel-related REST API endpoints.
+
+use super::{
+    errors::{http_status, BadHttpResponseError, HostingForbidden},
+    iter_pages,
+    models::{Channel, ChannelUpdate, EmoticonPack, Follower},
+    parse_json, HttpMethod, PAGE_SIZE, REST,
+};
+use failure::{format_err, Error};
+use log::debug;
+use serde_json::json;
+
+/// Helper for channel-related REST API endpoints.
+pub struct ChannelsHelper<'a> {
+    /// Reference to constructing REST struct
+    pub rest: &'a REST,
+}
+
+impl<'a> ChannelsHelper<'a> {
+    /// Get a channel by its id or its name (token).
+    ///
+    /// # Arguments
+    ///
+    /// * `id_or_token` - channel's id, e.g. `"271"`, or name, e.g. `"someone"`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// let channel = helper.get("some_username").unwrap();
+    /// if channel.online == Some(true) {
+    ///     println!("{} viewers", channel.viewers_current.unwrap_or_default());
+    /// }
+    /// ```
+    pub fn get(&self, id_or_token: &str) -> Result<Channel, Error> {
+        debug!("Getting channel {}", id_or_token);
+        self.rest.query_json(
+            HttpMethod::Get,
+            &format!("channels/{}", id_or_token),
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Change a channel's title, game, or audience rating.
+    ///
+    /// Needs a token with the `channel:update:self` scope. If Mixer rejects a
+    /// value, the error is a `BadHttpResponseError` whose `api_error` lists the
+    /// problems with each field.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `access_token` - OAuth access token
+    /// * `update` - changes to make
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::{models::ChannelUpdate, REST};
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// let update = ChannelUpdate::new().name("Back after this").type_id(1234);
+    /// let channel = helper.update(271, "access_token", &update).unwrap();
+    /// ```
+    pub fn update(
+        &self,
+        channel_id: usize,
+        access_token: &str,
+        update: &ChannelUpdate,
+    ) -> Result<Channel, Error> {
+        debug!("Updating channel ID {}", channel_id);
+        let endpoint = format!("channels/{}", channel_id);
+        let text = self.rest.query_with_json(
+            HttpMethod::Patch,
+            &endpoint,
+            None,
+            update,
+            Some(access_token),
+        )?;
+        parse_json(&endpoint, &text)
+    }
+
+    /// Get the emote sprite sheets that can be used in a channel's chat.
+    ///
+    /// Each pack has the URL of its image and the position of each emote in it,
+    /// for drawing emotes in rendered chat messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `user_id` - optional user id, to also get the emotes that user has unlocked
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// for pack in helper.get_emoticons(271, None).unwrap() {
+    ///     for (name, position) in &pack.emoticons {
+    ///         println!("{} is at ({}, {}) in {}", name, position.x, position.y, pack.url);
+    ///     }
+    /// }
+    /// ```
+    pub fn get_emoticons(
+        &self,
+        channel_id: usize,
+        user_id: Option<usize>,
+    ) -> Result<Vec<EmoticonPack>, Error> {
+        debug!("Getting emoticons for channel ID {}", channel_id);
+        let user_id = user_id.map(|id| id.to_string());
+        let mut params = Vec::new();
+        if let Some(user_id) = &user_id {
+            params.push(("user", user_id.as_str()));
+        }
+        self.rest.query_json(
+            HttpMethod::Get,
+            &format!("channels/{}/emoticons", channel_id),
+            Some(&params),
+            None,
+            None,
+        )
+    }
+
+    /// Get a page of the users following a channel, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `page` - optional page number, starting from 0
+    /// * `limit` - optional number of results per page
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// for follower in helper.get_followers(271, None, Some(20), None).unwrap() {
+    ///     println!("{} followed at {}", follower.username, follower.followed.created_at);
+    /// }
+    /// ```
+    pub fn get_followers(
+        &self,
+        channel_id: usize,
+        page: Option<u32>,
+        limit: Option<u32>,
+        access_token: Option<&str>,
+    ) -> Result<Vec<Follower>, Error> {
+        debug!("Getting followers for channel ID {}", channel_id);
+        let page = page.map(|p| p.to_string());
+        let limit = limit.map(|l| l.to_string());
+        let mut params = Vec::new();
+        if let Some(page) = &page {
+            params.push(("page", page.as_str()));
+        }
+        if let Some(limit) = &limit {
+            params.push(("limit", limit.as_str()));
+        }
+        params.push(("order", "followed.createdAt:desc"));
+        self.rest.query_json(
+            HttpMethod::Get,
+            &format!("channels/{}/follow", channel_id),
+            Some(&params),
+            None,
+            access_token,
+        )
+    }
+
+    /// Iterate over every user following a channel, newest first, getting
+    /// each page of 50 as it's needed.
+    ///
+    /// If getting a page fails, the error is the last item.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// let usernames: Vec<String> = helper
+    ///     .iter_followers(271, None)
+    ///     .filter_map(Result::ok)
+    ///     .map(|follower| follower.username)
+    ///     .collect();
+    /// ```
+    pub fn iter_followers(
+        &self,
+        channel_id: usize,
+        access_token: Option<&str>,
+    ) -> impl Iterator<Item = Result<Follower, Error>> + 'a {
+        let helper = ChannelsHelper { rest: self.rest };
+        let access_token = access_token.map(str::to_owned);
+        iter_pages(move |page| {
+            helper.get_followers(
+                channel_id,
+                Some(page),
+                Some(PAGE_SIZE as u32),
+                access_token.as_deref(),
+            )
+        })
+    }
+
+    /// Get how many users follow a channel.
+    ///
+    /// This is read from the `x-total-count` header, so only a single
+    /// follower is requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let count = api.channels_helper().get_follower_count(271).unwrap();
+    /// println!("{} followers", count);
+    /// ```
+    pub fn get_follower_count(&self, channel_id: usize) -> Result<u64, Error> {
+        debug!("Getting follower count for channel ID {}", channel_id);
+        let endpoint = format!("channels/{}/follow", channel_id);
+        let resp = self
+            .rest
+            .query_full(
+                HttpMethod::Get,
+                &endpoint,
+                Some(&[("limit", "1")]),
+                None,
+                None,
+            )?
+            .ensure_success()?;
+        resp.headers
+            .get("x-total-count")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| {
+                format_err!("Response from '{}' has no 'x-total-count' header", endpoint)
+            })
+    }
+
+    /// Get the channels that are hosting a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// for channel in helper.get_hosters(271).unwrap() {
+    ///     println!("Hosted by {}", channel.token.unwrap_or_default());
+    /// }
+    /// ```
+    pub fn get_hosters(&self, channel_id: usize) -> Result<Vec<Channel>, Error> {
+        debug!("Getting hosters for channel ID {}", channel_id);
+        self.rest
+            .query_paginated(&format!("channels/{}/hosters", channel_id), &[], None)
+    }
+
+    /// Get the channel that a channel is hosting, or `None` if it isn't
+    /// hosting anyone.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// match helper.get_hostee(271).unwrap() {
+    ///     Some(hostee) => println!("Hosting {}", hostee.token.unwrap_or_default()),
+    ///     None => println!("Not hosting"),
+    /// }
+    /// ```
+    pub fn get_hostee(&self, channel_id: usize) -> Result<Option<Channel>, Error> {
+        debug!("Getting hostee for channel ID {}", channel_id);
+        match self.rest.query_json(
+            HttpMethod::Get,
+            &format!("channels/{}/hostee", channel_id),
+            None,
+            None,
+            None,
+        ) {
+            Ok(channel) => Ok(Some(channel)),
+            Err(ref e) if http_status(e) == Some(404) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Start hosting another channel, replacing any channel that's already
+    /// being hosted.
+    ///
+    /// If the token isn't allowed to change the channel's host, the error is
+    /// a `HostingForbidden` with Mixer's response.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - id of the channel that will host
+    /// * `target_id` - id of the channel to host
+    /// * `access_token` - OAuth access token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// let channel = helper.set_hostee(271, 314, "access_token").unwrap();
+    /// assert_eq!(Some(314), channel.hostee_id);
+    /// ```
+    pub fn set_hostee(
+        &self,
+        channel_id: usize,
+        target_id: usize,
+        access_token: &str,
+    ) -> Result<Channel, Error> {
+        debug!(
+            "Setting hostee for channel ID {} to {}",
+            channel_id, target_id
+        );
+        let endpoint = format!("channels/{}/hostee", channel_id);
+        let text = self
+            .rest
+            .query_with_json(
+                HttpMethod::Put,
+                &endpoint,
+                None,
+                &json!({ "id": target_id }),
+                Some(access_token),
+            )
+            .map_err(|e| hosting_error(channel_id, e))?;
+        parse_json(&endpoint, &text)
+    }
+
+    /// Stop hosting. Nothing happens if the channel isn't hosting anyone.
+    ///
+    /// If the token isn't allowed to change the channel's host, the error is
+    /// a `HostingForbidden` with Mixer's response.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `access_token` - OAuth access token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// api.channels_helper().clear_hostee(271, "access_token").unwrap();
+    /// ```
+    pub fn clear_hostee(&self, channel_id: usize, access_token: &str) -> Result<(), Error> {
+        debug!("Clearing hostee for channel ID {}", channel_id);
+        self.rest
+            .query_no_content(
+                HttpMethod::Delete,
+                &format!("channels/{}/hostee", channel_id),
+                None,
+                None,
+                Some(access_token),
+            )
+            .map_err(|e| hosting_error(channel_id, e))
+    }
+}
+
+/// Wrap a 403 from changing a channel's host in a `HostingForbidden`.
+///
+/// # Arguments
+///
+/// * `channel_id` - channel's id
+/// * `err` - error from the call
+fn hosting_error(channel_id: usize, err: Error) -> Error {
+    match err.downcast::<BadHttpResponseError>() {
+        Ok(response) if response.status == 403 => HostingForbidden {
+            channel_id,
+            response,
+        }
+        .into(),
+        Ok(response) => response.into(),
+        Err(err) => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest::{
+        errors::{http_status, BadHttpResponseError, HostingForbidden},
+        mock_rest,
+        models::{Audience, ChannelUpdate},
+    };
+    use mockito::{mock, Matcher};
+    use serde_json::json;
+
+    #[test]
+    fn test_get() {
+        let body = r#"{
+            "id": 271,
+            "userId": 314,
+            "token": "someone",
+            "online": false,
+            "partnered": false,
+            "name": "Chatting",
+            "audience": "family",
+            "viewersCurrent": 0,
+            "typeId": null,
+            "updatedAt": "2019-06-01T12:00:00.000Z"
+        }"#;
+        let m1 = mock("GET", "/channels/someone").with_body(body).create();
+        let m2 = mock("GET", "/channels/271").with_body(body).create();
+        let rest = mock_rest("");
+        let helper = rest.channels_helper();
+        let by_token = helper.get("someone").unwrap();
+        assert_eq!(271, by_token.id);
+        assert_eq!(Some("Chatting".to_owned()), by_token.name);
+        assert_eq!(None, by_token.type_id);
+        assert_eq!(Some(Audience::Family), by_token.audience);
+        assert_eq!(by_token, helper.get("271").unwrap());
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn test_get_missing() {
+        let _m1 = mock("GET", "/channels/nobody").with_status(404).create();
+        let rest = mock_rest("");
+        let err = rest.channels_helper().get("nobody").unwrap_err();
+        assert_eq!(Some(404), http_status(&err));
+    }
+
+    #[test]
+    fn test_get_emoticons() {
+        let body = r#"[
+            {
+                "url": "https://uploads.mixer.com/emoticons/pack1.png",
+                "emoticons": {":wave": {"x": 0, "y": 0, "width": 24, "height": 24}}
+            },
+            {
+                "url": "https://uploads.mixer.com/emoticons/pack2.png",
+                "emoticons": {":hype": {"x": 24, "y": 0, "width": 24, "height": 24}}
+            }
+        ]"#;
+        let m1 = mock("GET", "/channels/271/emoticons")
+            .with_body(body)
+            .create();
+        let m2 = mock("GET", "/channels/271/emoticons?user=314")
+            .with_body(
+                r#"[{"url": "https://uploads.mixer.com/emoticons/global.png", "emoticons": {}}]"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.channels_helper();
+        let packs = helper.get_emoticons(271, None).unwrap();
+        assert_eq!(2, packs.len());
+        assert_eq!(24, packs[1].emoticons[":hype"].x);
+        let packs = helper.get_emoticons(271, Some(314)).unwrap();
+        assert_eq!(
+            "https://uploads.mixer.com/emoticons/global.png",
+            packs[0].url
+        );
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn test_get_followers() {
+        let m1 = mock("GET", "/channels/271/follow?page=1&limit=2&order=followed.createdAt%3Adesc")
+            .match_header("authorization", "Bearer abc")
+            .with_body(
+                r#"[{"id": 3, "username": "c", "followed": {"createdAt": "2019-06-01T12:00:00.000Z"}}]"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let followers = rest
+            .channels_helper()
+            .get_followers(271, Some(1), Some(2), Some("abc"))
+            .unwrap();
+        assert_eq!(1, followers.len());
+        assert_eq!("c", followers[0].username);
+        assert_eq!("2019-06-01T12:00:00.000Z", followers[0].followed.created_at);
+        m1.assert();
+    }
+
+    #[test]
+    fn test_iter_followers() {
+        let follower = |id: usize| {
+            json!({
+                "id": id,
+                "username": format!("user{}", id),
+                "followed": {"createdAt": "2019-06-01T12:00:00.000Z"}
+            })
+        };
+        let first: Vec<_> = (0..50).map(follower).collect();
+        let m1 = mock(
+            "GET",
+            "/channels/272/follow?page=0&limit=50&order=followed.createdAt%3Adesc",
+        )
+        .match_header("authorization", "Bearer abc")
+        .with_header("x-total-count", "51")
+        .with_body(json!(first).to_string())
+        .create();
+        let m2 = mock(
+            "GET",
+            "/channels/272/follow?page=1&limit=50&order=followed.createdAt%3Adesc",
+        )
+        .match_header("authorization", "Bearer abc")
+        .with_header("x-total-count", "51")
+        .with_body(json!([follower(50)]).to_string())
+        .create();
+        let rest = mock_rest("");
+        let followers: Vec<_> = rest
+            .channels_helper()
+            .iter_followers(272, Some("abc"))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(51, followers.len());
+        assert_eq!("user50", followers[50].username);
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn test_iter_followers_error() {
+        let _m1 = mock(
+            "GET",
+            "/channels/273/follow?page=0&limit=50&order=followed.createdAt%3Adesc",
+        )
+        .with_status(500)
+        .create();
+        let rest = mock_rest("");
+        let results: Vec<_> = rest.channels_helper().iter_followers(273, None).collect();
+        assert_eq!(1, results.len());
+        assert_eq!(Some(500), http_status(results[0].as_ref().unwrap_err()));
+    }
+
+    #[test]
+    fn test_get_follower_count() {
+        let m1 = mock("GET", "/channels/274/follow?limit=1")
+            .with_header("x-total-count", "1234")
+            .with_body(r#"[{"id": 1, "username": "a", "followed": {"createdAt": ""}}]"#)
+            .create();
+        let _m2 = mock("GET", "/channels/275/follow?limit=1")
+            .with_body("[]")
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.channels_helper();
+        assert_eq!(1234, helper.get_follower_count(274).unwrap());
+        let err = helper.get_follower_count(275).unwrap_err();
+        assert!(err.to_string().contains("x-total-count"));
+        m1.assert();
+    }
+
+    #[test]
+    fn test_update() {
+        let m1 = mock("PATCH", "/channels/271")
+            .match_header("authorization", "Bearer abc")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"name": "New title"})))
+            .with_body(r#"{"id": 271, "token": "someone", "name": "New title", "typeId": 5}"#)
+            .create();
+        let rest = mock_rest("");
+        let channel = rest
+            .channels_helper()
+            .update(271, "abc", &ChannelUpdate::new().name("New title"))
+            .unwrap();
+        assert_eq!(Some("New title".to_owned()), channel.name);
+        m1.assert();
+    }
+
+    #[test]
+    fn test_update_invalid() {
+        let m1 = mock("PATCH", "/channels/272")
+            .match_body(Matcher::Json(json!({"typeId": 0, "audience": "18+"})))
+            .with_status(400)
+            .with_body(r#"[{"path": "typeId", "message": "Type not found"}]"#)
+            .create();
+        let rest = mock_rest("");
+        let update = ChannelUpdate::new().type_id(0).audience(Audience::Adult);
+        let err = rest
+            .channels_helper()
+            .update(272, "abc", &update)
+            .unwrap_err();
+        assert_eq!(Some(400), http_status(&err));
+        let api_error = err
+            .downcast_ref::<BadHttpResponseError>()
+            .unwrap()
+            .api_error
+            .clone()
+            .unwrap();
+        assert_eq!(Some("typeId".to_owned()), api_error.fields[0].field);
+        assert_eq!("Type not found", api_error.fields[0].message);
+        m1.assert();
+    }
+
+    #[test]
+    fn test_get_hosters() {
+        let m1 = mock("GET", "/channels/271/hosters?limit=50&page=0")
+            .with_body(r#"[{"id": 1, "token": "a", "hosteeId": 271}, {"id": 2, "token": "b", "hosteeId": 271}]"#)
+            .create();
+        let rest = mock_rest("");
+        let hosters = rest.channels_helper().get_hosters(271).unwrap();
+        let ids: Vec<usize> = hosters.iter().map(|c| c.id).collect();
+        assert_eq!(vec![1, 2], ids);
+        assert_eq!(Some(271), hosters[0].hostee_id);
+        m1.assert();
+    }
+
+    #[test]
+    fn test_get_hostee() {
+        let _m1 = mock("GET", "/channels/271/hostee")
+            .with_body(r#"{"id": 314, "token": "other"}"#)
+            .create();
+        let _m2 = mock("GET", "/channels/272/hostee")
+            .with_status(404)
+            .with_body(r#"{"statusCode": 404, "error": "Not Found", "message": "Channel is not hosting."}"#)
+            .create();
+        let _m3 = mock("GET", "/channels/273/hostee")
+            .with_status(500)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.channels_helper();
+        let hostee = helper.get_hostee(271).unwrap().unwrap();
+        assert_eq!(314, hostee.id);
+        assert_eq!(None, helper.get_hostee(272).unwrap());
+        let err = helper.get_hostee(273).unwrap_err();
+        assert_eq!(Some(500), http_status(&err));
+    }
+
+    #[test]
+    fn test_set_hostee() {
+        let m1 = mock("PUT", "/channels/271/hostee")
+            .match_header("authorization", "Bearer abc")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"id": 314})))
+            .with_body(r#"{"id": 271, "token": "someone", "hosteeId": 314}"#)
+            .create();
+        let rest = mock_rest("");
+        let channel = rest.channels_helper().set_hostee(271, 314, "abc").unwrap();
+        assert_eq!(Some(314), channel.hostee_id);
+        m1.assert();
+    }
+
+    #[test]
+    fn test_clear_hostee() {
+        let m1 = mock("DELETE", "/channels/271/hostee")
+            .match_header("authorization", "Bearer abc")
+            .with_status(204)
+            .create();
+        let rest = mock_rest("");
+        rest.channels_helper().clear_hostee(271, "abc").unwrap();
+        m1.assert();
+    }
+
+    #[test]
+    fn test_hostee_forbidden() {
+        let _m1 = mock("PUT", "/channels/275/hostee")
+            .with_status(403)
+            .with_body(r#"{"statusCode": 403, "error": "Forbidden", "message": "You don't have access to that."}"#)
+            .create();
+        let _m2 = mock("DELETE", "/channels/275/hostee")
+            .with_status(403)
+            .create();
+        let _m3 = mock("DELETE", "/channels/276/hostee")
+            .with_status(400)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.channels_helper();
+        let err = helper.set_hostee(275, 314, "abc").unwrap_err();
+        assert_eq!(Some(403), http_status(&err));
+        assert!(!err.to_string().contains("You don't have access to that."));
+        let forbidden = err.downcast_ref::<HostingForbidden>().unwrap();
+        assert_eq!(275, forbidden.channel_id);
+        assert_eq!("channels/275/hostee", forbidden.response.endpoint);
+        let api_error = forbidden.response.api_error.as_ref().unwrap();
+        assert_eq!(
+            Some("You don't have access to that."),
+            api_error.message.as_deref()
+        );
+        let cause = err.as_fail().cause().unwrap();
+        assert_eq!(
+            Some(&forbidden.response),
+            cause.downcast_ref::<BadHttpResponseError>()
+        );
+        assert!(cause.to_string().contains("You don't have access to that."));
+
+        let err = helper.clear_hostee(275, "abc").unwrap_err();
+        let forbidden = err.downcast_ref::<HostingForbidden>().unwrap();
+        assert_eq!(403, forbidden.response.status);
+        let err = helper.clear_hostee(276, "abc").unwrap_err();
+        assert!(err.downcast_ref::<HostingForbidden>().is_none());
+        assert_eq!(Some(400), http_status(&err));
+    }
+}
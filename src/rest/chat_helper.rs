@@ -1,16 +1,76 @@
 //! Helper for chat-related REST API endpoints.
 
-use super::REST;
-use failure::Error;
+use super::{
+    errors::http_status,
+    models::{ChatServer, ChatsResponse},
+    HttpMethod, REST,
+};
+use failure::{format_err, Error};
 use log::debug;
+use std::{cell::RefCell, collections::HashMap};
 
 /// Helper for chat-related REST API endpoints.
 pub struct ChatHelper<'a> {
     /// Reference to constructing REST struct
     pub rest: &'a REST,
+    cache: Option<RefCell<HashMap<String, usize>>>,
 }
 
 impl<'a> ChatHelper<'a> {
+    /// Create a new helper, without caching.
+    ///
+    /// # Arguments
+    ///
+    /// * `rest` - REST struct to make calls with
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::rest::{chat_helper::ChatHelper, REST};
+    /// let api = REST::new("");
+    /// let helper = ChatHelper::new(&api);
+    /// ```
+    pub fn new(rest: &'a REST) -> Self {
+        ChatHelper { rest, cache: None }
+    }
+
+    /// Enable caching of channel IDs looked up by `get_channel_id`.
+    ///
+    /// A channel's ID never changes, so it's safe to cache the result of
+    /// looking one up by username for as long as this helper is kept around.
+    /// Usernames are cached case-insensitively.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.chat_helper().with_cache();
+    /// let channel_id = helper.get_channel_id("some_username");
+    /// // doesn't make another API call
+    /// let channel_id = helper.get_channel_id("some_username");
+    /// ```
+    pub fn with_cache(mut self) -> Self {
+        self.cache = Some(RefCell::new(HashMap::new()));
+        self
+    }
+
+    /// Clear the channel ID cache, if enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.chat_helper().with_cache();
+    /// helper.clear_cache();
+    /// ```
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.borrow_mut().clear();
+        }
+    }
+
     /// Get the channel ID for a username.
     ///
     /// See docs for more information: https://dev.mixer.com/reference/chat/connection#connection
@@ -28,19 +88,68 @@ impl<'a> ChatHelper<'a> {
     /// let channel_id = helper.get_channel_id("some_username");
     /// ```
     pub fn get_channel_id(&self, username: &str) -> Result<usize, Error> {
+        let key = username.to_lowercase();
+        if let Some(cache) = &self.cache {
+            if let Some(channel_id) = cache.borrow().get(&key) {
+                debug!("Using cached channel id for username {}", username);
+                return Ok(*channel_id);
+            }
+        }
         debug!("Getting channel id for username {}", username);
         let text = self.rest.query(
-            "GET",
+            HttpMethod::Get,
             &format!("channels/{}?fields=id", username),
             None,
             None,
             None,
         )?;
         let json: serde_json::Value = serde_json::from_str(&text)?;
-        let channel_id = json["id"].as_u64().unwrap() as usize;
+        let channel_id = match json["id"].as_u64() {
+            Some(id) => id as usize,
+            None => return Err(format_err!("Response does not have an 'id' field")),
+        };
+        if let Some(cache) = &self.cache {
+            cache.borrow_mut().insert(key, channel_id);
+        }
         Ok(channel_id)
     }
 
+    /// Check whether a channel exists for a username, which it does for every user.
+    ///
+    /// Returns `false` if Mixer responds with a 404; other errors are returned as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - username to look up
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.chat_helper();
+    /// if !helper.channel_exists("some_username").unwrap() {
+    ///     println!("No such user");
+    /// }
+    /// ```
+    pub fn channel_exists(&self, username: &str) -> Result<bool, Error> {
+        debug!(
+            "Checking whether a channel exists for username {}",
+            username
+        );
+        match self.rest.query(
+            HttpMethod::Get,
+            &format!("channels/{}", username),
+            Some(&[("fields", "id")]),
+            None,
+            None,
+        ) {
+            Ok(_) => Ok(true),
+            Err(ref e) if http_status(e) == Some(404) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Gets a list of chat servers to connect to for the channel ID.
     ///
     /// See docs for more information: https://dev.mixer.com/reference/chat/connection#connection
@@ -58,24 +167,97 @@ impl<'a> ChatHelper<'a> {
     /// let servers = helper.get_servers(1234567890);
     /// ```
     pub fn get_servers(&self, channel_id: usize) -> Result<Vec<String>, Error> {
+        Ok(self
+            .get_servers_detailed(channel_id)?
+            .into_iter()
+            .map(|server| server.endpoint)
+            .collect())
+    }
+
+    /// Gets the chat servers to connect to for the channel ID, with what Mixer
+    /// reports about their load.
+    ///
+    /// Servers are in the order Mixer listed them.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel ID to connect to
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.chat_helper();
+    /// let servers = helper.get_servers_detailed(1234567890).unwrap();
+    /// if servers.iter().any(|s| s.load_shed) {
+    ///     // try again later
+    /// }
+    /// ```
+    pub fn get_servers_detailed(&self, channel_id: usize) -> Result<Vec<ChatServer>, Error> {
         debug!("Getting servers for channel ID {}", channel_id);
-        let text = self
-            .rest
-            .query("GET", &format!("chats/{}", channel_id), None, None, None)?;
+        let response: ChatsResponse = self.rest.query_json(
+            HttpMethod::Get,
+            &format!("chats/{}", channel_id),
+            None,
+            None,
+            None,
+        )?;
+        Ok(response.into_servers())
+    }
+
+    /// Get a user's roles in a channel's chat, e.g. "Mod", "Owner" or "Subscriber".
+    ///
+    /// See docs for more information: https://dev.mixer.com/rest/index.html#chats__channelId__users__userId__get
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel ID
+    /// * `user_id` - user ID
+    /// * `access_token` - OAuth access token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.chat_helper();
+    /// let roles = helper.get_user_roles(1234567890, 123, "access_token").unwrap();
+    /// let is_mod = roles.iter().any(|r| r == "Mod");
+    /// ```
+    pub fn get_user_roles(
+        &self,
+        channel_id: usize,
+        user_id: usize,
+        access_token: &str,
+    ) -> Result<Vec<String>, Error> {
+        debug!(
+            "Getting roles for user ID {} in channel ID {}",
+            user_id, channel_id
+        );
+        let text = self.rest.query(
+            HttpMethod::Get,
+            &format!("chats/{}/users/{}", channel_id, user_id),
+            None,
+            None,
+            Some(access_token),
+        )?;
         let json: serde_json::Value = serde_json::from_str(&text)?;
-        let endpoints: Vec<String> = json["endpoints"]
-            .as_array()
-            .unwrap()
+        let roles = match json["userRoles"].as_array() {
+            Some(r) => r,
+            None => return Err(format_err!("Response does not have a 'userRoles' field")),
+        };
+        Ok(roles
             .iter()
-            .map(|e| e.as_str().unwrap().to_owned())
-            .collect();
-        Ok(endpoints)
+            .filter_map(|r| r.as_str())
+            .map(|r| r.to_owned())
+            .collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::REST;
+    use crate::rest::{mock_rest, models::ChatServer};
     use mockito::mock;
 
     #[test]
@@ -83,20 +265,169 @@ mod tests {
         let _m1 = mock("GET", "/channels/aaaaaa?fields=id")
             .with_body(r#"{"id":123}"#)
             .create();
-        let rest = REST::new("");
+        let rest = mock_rest("");
         let helper = rest.chat_helper();
         let id = helper.get_channel_id("aaaaaa").unwrap();
         assert_eq!(123, id);
     }
 
+    #[test]
+    fn test_get_channel_id_missing() {
+        let _m1 = mock("GET", "/channels/dddddd?fields=id")
+            .with_body(r#"{"token":"dddddd"}"#)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        let err = helper.get_channel_id("dddddd").unwrap_err().to_string();
+        assert_eq!("Response does not have an 'id' field", err);
+    }
+
+    #[test]
+    fn test_channel_exists() {
+        let _m1 = mock("GET", "/channels/someone?fields=id")
+            .with_body(r#"{"id":123}"#)
+            .create();
+        let _m2 = mock("GET", "/channels/nobody?fields=id")
+            .with_status(404)
+            .with_body(r#"{"statusCode":404,"error":"Not Found","message":"Channel not found."}"#)
+            .create();
+        let _m3 = mock("GET", "/channels/broken?fields=id")
+            .with_status(500)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        assert!(helper.channel_exists("someone").unwrap());
+        assert!(!helper.channel_exists("nobody").unwrap());
+        assert!(helper.channel_exists("broken").is_err());
+    }
+
+    #[test]
+    fn test_get_channel_id_cached() {
+        let m1 = mock("GET", "/channels/bbbbbb?fields=id")
+            .with_body(r#"{"id":456}"#)
+            .expect(1)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper().with_cache();
+        assert_eq!(456, helper.get_channel_id("bbbbbb").unwrap());
+        assert_eq!(456, helper.get_channel_id("BBBBBB").unwrap());
+        m1.assert();
+    }
+
+    #[test]
+    fn test_clear_cache() {
+        let m1 = mock("GET", "/channels/cccccc?fields=id")
+            .with_body(r#"{"id":789}"#)
+            .expect(2)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper().with_cache();
+        helper.get_channel_id("cccccc").unwrap();
+        helper.clear_cache();
+        helper.get_channel_id("cccccc").unwrap();
+        m1.assert();
+    }
+
     #[test]
     fn test_get_servers() {
         let _m1 = mock("GET", "/chats/123")
             .with_body(r#"{"endpoints":["a","b","c"]}"#)
             .create();
-        let rest = REST::new("");
+        let rest = mock_rest("");
         let helper = rest.chat_helper();
         let servers = helper.get_servers(123).unwrap();
         assert_eq!(vec!["a", "b", "c"], servers);
     }
+
+    #[test]
+    fn test_get_channel_id_malformed() {
+        let _m1 = mock("GET", "/channels/eeeeee?fields=id")
+            .with_body(r#"{"id":"not a number"}"#)
+            .create();
+        let _m2 = mock("GET", "/channels/ffffff?fields=id")
+            .with_body(r#"[{"id":1}]"#)
+            .create();
+        let _m3 = mock("GET", "/channels/gggggg?fields=id")
+            .with_body("<html>Service Unavailable</html>")
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        for username in &["eeeeee", "ffffff", "gggggg"] {
+            assert!(helper.get_channel_id(username).is_err());
+        }
+    }
+
+    #[test]
+    fn test_get_servers_malformed() {
+        let _m1 = mock("GET", "/chats/1")
+            .with_body(r#"{"error":"Something went wrong"}"#)
+            .create();
+        let _m2 = mock("GET", "/chats/2")
+            .with_body(r#"{"endpoints":"wss://chat1.mixer.com:443"}"#)
+            .create();
+        let _m3 = mock("GET", "/chats/3")
+            .with_body(r#"{"endpoints":["wss://chat1.mixer.com:443", 5]}"#)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        let err = helper.get_servers(1).unwrap_err().to_string();
+        assert!(err.contains("endpoints"), "{}", err);
+        assert!(helper.get_servers(2).is_err());
+        assert!(helper.get_servers(3).is_err());
+    }
+
+    #[test]
+    fn test_get_servers_detailed() {
+        let _m1 = mock("GET", "/chats/456")
+            .with_body(
+                r#"{
+                    "roles": ["User"],
+                    "authkey": "abc",
+                    "permissions": ["chat", "connect"],
+                    "endpoints": ["wss://chat1.mixer.com:443", "wss://chat2.mixer.com:443"],
+                    "isLoadShed": true
+                }"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        let servers = helper.get_servers_detailed(456).unwrap();
+        assert_eq!(
+            vec![
+                ChatServer {
+                    endpoint: "wss://chat1.mixer.com:443".to_owned(),
+                    load_shed: true,
+                },
+                ChatServer {
+                    endpoint: "wss://chat2.mixer.com:443".to_owned(),
+                    load_shed: true,
+                },
+            ],
+            servers
+        );
+    }
+
+    #[test]
+    fn test_get_user_roles() {
+        let _m1 = mock("GET", "/chats/123/users/456")
+            .match_header("Authorization", "Bearer abc")
+            .with_body(
+                r#"{"userId":456,"userName":"someone","userRoles":["Mod","Subscriber","User"]}"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        let roles = helper.get_user_roles(123, 456, "abc").unwrap();
+        assert_eq!(vec!["Mod", "Subscriber", "User"], roles);
+    }
+
+    #[test]
+    fn test_get_user_roles_missing() {
+        let _m1 = mock("GET", "/chats/123/users/789")
+            .with_body(r#"{"userId":789}"#)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.chat_helper();
+        assert!(helper.get_user_roles(123, 789, "abc").is_err());
+    }
 }
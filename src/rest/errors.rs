@@ -1,26 +1,334 @@
 //! REST API error handling.
 
-use failure::Fail;
+use failure::{Error, Fail};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Number of characters of a response body included in error messages.
+const BODY_SNIPPET_LENGTH: usize = 200;
 
 /// Error for receiving a non-20X response from an endpoint.
 #[derive(Debug, Fail, PartialEq)]
-#[fail(display = "An error occurred with error code {}.", _0)]
-pub struct BadHttpResponseError(pub u16);
+pub struct BadHttpResponseError {
+    /// HTTP status code
+    pub status: u16,
+    /// Endpoint that was called
+    pub endpoint: String,
+    /// Response body, which usually has Mixer's description of the error
+    pub body: String,
+    /// Value of the `x-request-id` response header, if it was sent
+    pub request_id: Option<String>,
+    /// Number of times the call was sent, which is more than 1 if it was retried
+    pub attempts: u32,
+    /// Mixer's description of the error, if the body had one
+    pub api_error: Option<ApiError>,
+}
+
+impl BadHttpResponseError {
+    /// HTTP status code of the response.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{rest::errors::BadHttpResponseError, REST};
+    /// let api = REST::new("");
+    /// if let Err(e) = api.query("GET", "some/endpoint", None, None, None) {
+    ///     if let Some(e) = e.downcast_ref::<BadHttpResponseError>() {
+    ///         if e.status() == 404 {
+    ///             // ...
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Body of the response.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+/// Get the HTTP status code from an error, if it's a `BadHttpResponseError`
+/// or a `HostingForbidden` wrapping one.
+///
+/// # Arguments
+///
+/// * `err` - error from a call
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::{rest::errors::http_status, REST};
+/// let api = REST::new("");
+/// match api.query("GET", "some/endpoint", None, None, None) {
+///     Err(ref e) if http_status(e) == Some(404) => { /* ... */ }
+///     _ => {}
+/// }
+/// ```
+pub fn http_status(err: &Error) -> Option<u16> {
+    err.downcast_ref::<BadHttpResponseError>()
+        .or_else(|| err.downcast_ref::<HostingForbidden>().map(|e| &e.response))
+        .map(BadHttpResponseError::status)
+}
+
+impl fmt::Display for BadHttpResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "An error occurred with error code {} from '{}'",
+            self.status, self.endpoint
+        )?;
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request id {})", request_id)?;
+        }
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        match &self.api_error {
+            Some(api_error) => write!(f, ": {}", api_error),
+            None => write!(f, ": {}", snippet(&self.body)),
+        }
+    }
+}
+
+/// Mixer's description of an error, parsed from a response body.
+///
+/// Bodies are either an object like `{"statusCode": 400, "error": "Bad Request",
+/// "message": "..."}`, which may have the problems with each field under `details`,
+/// or just an array of the problems with each field.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    /// HTTP status code, as repeated in the body
+    pub status_code: Option<u16>,
+    /// Name of the status, e.g. "Bad Request"
+    pub error: Option<String>,
+    /// Description of the error
+    pub message: Option<String>,
+    /// Problems with the fields that were sent, for validation errors
+    #[serde(default, rename = "details")]
+    pub fields: Vec<FieldError>,
+}
+
+/// Problem with a field that was sent, from a validation error.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct FieldError {
+    /// Name of the field, e.g. "name"
+    #[serde(rename = "path")]
+    pub field: Option<String>,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl ApiError {
+    /// Parse a response body, returning `None` if it isn't one of Mixer's error bodies.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - response body
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::rest::errors::ApiError;
+    /// let error = ApiError::parse(r#"{"statusCode": 404, "message": "Not Found"}"#).unwrap();
+    /// assert_eq!(Some(404), error.status_code);
+    /// assert!(ApiError::parse("Not Found").is_none());
+    /// ```
+    pub fn parse(body: &str) -> Option<ApiError> {
+        match serde_json::from_str(body).ok()? {
+            Value::Array(fields) => Some(ApiError {
+                fields: fields
+                    .into_iter()
+                    .map(serde_json::from_value)
+                    .collect::<Result<_, _>>()
+                    .ok()?,
+                ..ApiError::default()
+            }),
+            value @ Value::Object(_) => {
+                let error: ApiError = serde_json::from_value(value).ok()?;
+                if error.message.is_none() && error.error.is_none() && error.fields.is_empty() {
+                    None
+                } else {
+                    Some(error)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.message.as_ref().or(self.error.as_ref());
+        if let Some(description) = description {
+            write!(f, "{}", description)?;
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 || description.is_some() {
+                write!(f, "; ")?;
+            }
+            match &field.field {
+                Some(name) => write!(f, "{}: {}", name, field.message)?,
+                None => write!(f, "{}", field.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error for a call that was rejected by the rate limit (HTTP 429).
+#[derive(Debug, Fail, PartialEq)]
+#[fail(display = "Rate limited until {:?}", reset_at)]
+pub struct RateLimited {
+    /// When the rate limit resets, in milliseconds since the Unix epoch,
+    /// if Mixer sent it
+    pub reset_at: Option<u64>,
+}
+
+/// Error for an empty response body (e.g. from a 204 response) where
+/// `REST::query_json` expected JSON that isn't `null`.
+#[derive(Debug, Fail, PartialEq)]
+#[fail(display = "Empty response from '{}' where JSON was expected", endpoint)]
+pub struct EmptyBody {
+    /// Endpoint that was called
+    pub endpoint: String,
+}
+
+/// Error for a token that isn't allowed to change who a channel is hosting.
+///
+/// Mixer's response is kept as the cause, as its message tells a token
+/// that's missing the scope apart from one for a user who isn't an editor.
+#[derive(Debug, Fail, PartialEq)]
+#[fail(
+    display = "Not allowed to change who channel {} is hosting; the token needs the \
+               'channel:update:self' scope and must be for the channel's owner or an editor",
+    channel_id
+)]
+pub struct HostingForbidden {
+    /// Id of the channel
+    pub channel_id: usize,
+    /// Mixer's 403 response
+    #[fail(cause)]
+    pub response: BadHttpResponseError,
+}
+
+/// Shorten a response body for an error message.
+///
+/// # Arguments
+///
+/// * `body` - response body
+pub(crate) fn snippet(body: &str) -> String {
+    let mut snippet: String = body.chars().take(BODY_SNIPPET_LENGTH).collect();
+    if snippet.len() < body.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
 
 #[cfg(test)]
 mod tests {
-    use super::BadHttpResponseError;
+    use super::{http_status, ApiError, BadHttpResponseError, FieldError, RateLimited};
+
+    fn error(body: &str) -> BadHttpResponseError {
+        BadHttpResponseError {
+            status: 400,
+            endpoint: "channels/foo".to_owned(),
+            body: body.to_owned(),
+            request_id: None,
+            attempts: 1,
+            api_error: ApiError::parse(body),
+        }
+    }
 
     #[test]
     fn has_display() {
-        let err = BadHttpResponseError(400);
-        let _ = format!("{}", err);
+        let err = error(r#"{"message": "Bad field"}"#);
+        let text = format!("{}", err);
+        assert!(text.contains("400"));
+        assert!(text.contains("channels/foo"));
+        assert!(text.contains("Bad field"));
+        assert!(!text.contains("attempts"));
+
+        let err = BadHttpResponseError {
+            request_id: Some("abc-123".to_owned()),
+            ..error(&"x".repeat(1000))
+        };
+        let text = format!("{}", err);
+        assert!(text.contains("abc-123"));
+        assert!(text.ends_with("..."));
+        assert!(text.len() < 300);
+
+        let err = BadHttpResponseError {
+            attempts: 3,
+            ..error("")
+        };
+        assert!(format!("{}", err).contains("after 3 attempts"));
+    }
+
+    #[test]
+    fn api_error() {
+        let err = error(r#"{"statusCode": 400, "error": "Bad Request", "message": "Bad field"}"#);
+        assert_eq!(
+            Some(ApiError {
+                status_code: Some(400),
+                error: Some("Bad Request".to_owned()),
+                message: Some("Bad field".to_owned()),
+                fields: vec![],
+            }),
+            err.api_error
+        );
+        assert!(format!("{}", err).ends_with(": Bad field"));
+    }
+
+    #[test]
+    fn api_error_validation() {
+        let err = error(
+            r#"{"statusCode": 400, "error": "Bad Request", "message": "Validation failed",
+                "details": [{"path": "name", "message": "is too long", "type": "string.max"}]}"#,
+        );
+        let api_error = err.api_error.clone().unwrap();
+        assert_eq!(
+            vec![FieldError {
+                field: Some("name".to_owned()),
+                message: "is too long".to_owned(),
+            }],
+            api_error.fields
+        );
+        assert!(format!("{}", err).ends_with(": Validation failed; name: is too long"));
+
+        let err = error(r#"[{"path": "audience", "message": "is invalid"}]"#);
+        let api_error = err.api_error.unwrap();
+        assert_eq!(None, api_error.message);
+        assert_eq!(Some("audience".to_owned()), api_error.fields[0].field);
+        assert_eq!("audience: is invalid", format!("{}", api_error));
+    }
+
+    #[test]
+    fn api_error_plain_text() {
+        let err = error("Service Unavailable");
+        assert_eq!(None, err.api_error);
+        assert!(format!("{}", err).ends_with(": Service Unavailable"));
+        assert_eq!(None, ApiError::parse(r#"{"id": 1}"#));
+        assert_eq!(None, ApiError::parse("[1, 2]"));
+    }
+
+    #[test]
+    fn accessors() {
+        let err = error("Not Found");
+        assert_eq!(400, err.status());
+        assert_eq!("Not Found", err.body());
+        assert_eq!(Some(400), http_status(&err.into()));
+        assert_eq!(None, http_status(&RateLimited { reset_at: None }.into()));
     }
 
     #[test]
     fn has_partial_eq() {
-        let err1 = BadHttpResponseError(400);
-        let err2 = BadHttpResponseError(400);
+        let err1 = error("");
+        let err2 = error("");
 
         assert_eq!(err1, err2);
     }
@@ -0,0 +1,196 @@
+//! HTTP methods that the REST API is called with.
+
+use failure::{format_err, Error};
+use reqwest::Method;
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    str::FromStr,
+};
+
+/// HTTP method for a call to the REST API.
+///
+/// The `query` functions on `REST` accept this or, for compatibility, a string
+/// like `"GET"` or a `reqwest::Method`, which are checked when the call is made.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::rest::HttpMethod;
+/// let method: HttpMethod = "patch".parse().unwrap();
+/// assert_eq!(HttpMethod::Patch, method);
+/// assert!("GTE".parse::<HttpMethod>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    /// `GET`
+    Get,
+    /// `POST`
+    Post,
+    /// `PUT`
+    Put,
+    /// `PATCH`
+    Patch,
+    /// `DELETE`
+    Delete,
+    /// `HEAD`
+    Head,
+    /// `OPTIONS`
+    Options,
+}
+
+impl HttpMethod {
+    const ALL: [HttpMethod; 7] = [
+        HttpMethod::Get,
+        HttpMethod::Post,
+        HttpMethod::Put,
+        HttpMethod::Patch,
+        HttpMethod::Delete,
+        HttpMethod::Head,
+        HttpMethod::Options,
+    ];
+
+    /// Name of the method, e.g. `"GET"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Options => "OPTIONS",
+        }
+    }
+
+    /// Whether sending the call more than once has the same effect as sending it once.
+    pub fn is_idempotent(self) -> bool {
+        !matches!(self, HttpMethod::Post | HttpMethod::Patch)
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for HttpMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HttpMethod::ALL
+            .iter()
+            .find(|m| m.as_str().eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| {
+                let valid: Vec<&str> = HttpMethod::ALL.iter().map(|m| m.as_str()).collect();
+                format_err!(
+                    "Invalid HTTP method '{}', expected one of {}",
+                    s,
+                    valid.join(", ")
+                )
+            })
+    }
+}
+
+impl TryFrom<&str> for HttpMethod {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<&String> for HttpMethod {
+    type Error = Error;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<Method> for HttpMethod {
+    type Error = Error;
+
+    fn try_from(value: Method) -> Result<Self, Self::Error> {
+        value.as_str().parse()
+    }
+}
+
+impl From<HttpMethod> for Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => Method::GET,
+            HttpMethod::Post => Method::POST,
+            HttpMethod::Put => Method::PUT,
+            HttpMethod::Patch => Method::PATCH,
+            HttpMethod::Delete => Method::DELETE,
+            HttpMethod::Head => Method::HEAD,
+            HttpMethod::Options => Method::OPTIONS,
+        }
+    }
+}
+
+/// Anything that can be checked and converted into an `HttpMethod`: the enum
+/// itself, a string, or a `reqwest::Method`.
+pub trait IntoHttpMethod {
+    /// Convert into an `HttpMethod`, returning an error for unsupported methods.
+    fn into_http_method(self) -> Result<HttpMethod, Error>;
+}
+
+impl<T> IntoHttpMethod for T
+where
+    T: TryInto<HttpMethod>,
+    T::Error: Into<Error>,
+{
+    fn into_http_method(self) -> Result<HttpMethod, Error> {
+        self.try_into().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HttpMethod, IntoHttpMethod};
+    use reqwest::Method;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(HttpMethod::Get, HttpMethod::try_from("GET").unwrap());
+        assert_eq!(HttpMethod::Delete, HttpMethod::try_from("delete").unwrap());
+        assert_eq!(
+            HttpMethod::Options,
+            HttpMethod::try_from(&"Options".to_owned()).unwrap()
+        );
+        let err = HttpMethod::try_from("GTE").unwrap_err().to_string();
+        assert!(err.contains("'GTE'"));
+        assert!(err.contains("GET, POST, PUT, PATCH, DELETE, HEAD, OPTIONS"));
+    }
+
+    #[test]
+    fn reqwest_method() {
+        for &method in HttpMethod::ALL.iter() {
+            let converted = Method::from(method);
+            assert_eq!(method.as_str(), converted.as_str());
+            assert_eq!(method, HttpMethod::try_from(converted).unwrap());
+        }
+        assert!(HttpMethod::try_from(Method::TRACE).is_err());
+    }
+
+    #[test]
+    fn into_http_method() {
+        assert_eq!(HttpMethod::Put, HttpMethod::Put.into_http_method().unwrap());
+        assert_eq!(HttpMethod::Put, "put".into_http_method().unwrap());
+        assert_eq!(HttpMethod::Put, Method::PUT.into_http_method().unwrap());
+        assert!("".into_http_method().is_err());
+    }
+
+    #[test]
+    fn idempotent() {
+        assert!(HttpMethod::Get.is_idempotent());
+        assert!(HttpMethod::Put.is_idempotent());
+        assert!(!HttpMethod::Post.is_idempotent());
+        assert!(!HttpMethod::Patch.is_idempotent());
+    }
+}
@@ -2,14 +2,23 @@
 //!
 //! This module contains a struct, `REST` that is contains various helper
 //! functions for making calls out to the API and processing the responses.
+//! `REST::new` uses the default settings; `REST::builder` allows setting the timeout,
+//! base URL, user agent, proxy, retry policy, ETag cache, maximum upload size, and
+//! whether full URLs may be on other hosts.
 //!
 //! The `ChatHelper` struct can be constructed through an instance of the `REST` struct,
 //! providing several handy methods for getting information about the chat server endpoint(s),
 //! required for [connecting to chat].
 //!
 //! The `WebHookHelper` struct can be constructed through an instance of the `REST` struct,
-//! providing several handy methods for registering webhooks, as the HTTP call to do so
-//! differs from the rest of the API endpoints.
+//! providing several handy methods for registering and listing webhooks, as the HTTP
+//! calls to do so differ from the rest of the API endpoints.
+//!
+//! The `UsersHelper` struct can be constructed through an instance of the `REST` struct,
+//! providing typed lookups of users, like the one an access token belongs to.
+//!
+//! The `ChannelsHelper` struct can be constructed through an instance of the `REST` struct,
+//! providing typed lookups of channels, their followers and emotes, and hosting.
 //!
 //! Some endpoints require OAuth. You can utilize this crate's [oauth module] for getting
 //! an access token from users.
@@ -17,28 +26,455 @@
 //! [connecting to chat]: ../chat/struct.ChatClient.html#method.connect
 //! [oauth module]: ../oauth
 
+mod cache;
+pub mod channels_helper;
 pub mod chat_helper;
 pub mod errors;
+mod method;
+/// Static models for JSON data
+pub mod models;
+mod query;
+mod token;
+mod upload;
+pub mod users_helper;
 pub mod webhook_helper;
 
-use failure::Error;
+pub use method::{HttpMethod, IntoHttpMethod};
+pub use query::{FilterOp, QueryBuilder, SortOrder};
+pub use token::TokenProvider;
+pub use upload::UploadSource;
+
+use failure::{format_err, Error};
 use log::debug;
 use reqwest::{
     header::{self, HeaderMap, HeaderName, HeaderValue},
-    Client, Method,
+    Client, Method, Proxy, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    iter,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use std::time::Duration;
+use typed_builder::TypedBuilder;
+use url::Url;
 
+use crate::internal::redact::{redact, redact_headers, redact_json};
+use cache::{CachedResponse, EtagCache};
+use channels_helper::ChannelsHelper;
 use chat_helper::ChatHelper;
-use errors::BadHttpResponseError;
+use errors::{ApiError, BadHttpResponseError, EmptyBody, RateLimited};
+use models::{Emote, EmoticonPack, Follower};
+use upload::{FilePart, MAX_UPLOAD_SIZE};
+use users_helper::UsersHelper;
 use webhook_helper::WebHookHelper;
 
 const TIMEOUT: u64 = 10;
 
+/// Default base URL of the REST API.
+pub const BASE_URL: &str = "https://mixer.com/api/v1";
+
+/// Status code Mixer responds with when a call needs a new CSRF token.
+const CSRF_STATUS: u16 = 461;
+
+/// Header that Mixer sends the CSRF token in, and expects it back in.
+const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Size of the chunks that `REST::download` reads and writes, in bytes.
+const DOWNLOAD_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Results per page requested by `query_paginated` if no `limit` param is passed.
+const PAGE_SIZE: usize = 50;
+
+/// Longest time to wait for a rate limit to reset before retrying.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
 /// API wrapper around the Mixer REST API.
+///
+/// The wrapper is `Send` and `Sync`, and cloning it is cheap: clones share the
+/// HTTP connection pool, rate limit status, CSRF token, ETag cache, and token
+/// provider. Settings changed on a clone afterwards, like the client ID or base
+/// URL, only apply to that clone.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::REST;
+/// # use std::thread;
+/// let api = REST::new("abcd");
+/// let for_thread = api.clone();
+/// let handle = thread::spawn(move || for_thread.query("GET", "some/endpoint", None, None, None));
+/// let text = api.query("GET", "other/endpoint", None, None, None).unwrap();
+/// handle.join().unwrap().unwrap();
+/// ```
+#[derive(Clone)]
 pub struct REST {
     client: Client,
     client_id: String,
+    base_url: String,
+    retry_on_rate_limit: bool,
+    retry_policy: Option<RetryPolicy>,
+    rate_limit: Arc<Mutex<RateLimit>>,
+    csrf_token: Arc<Mutex<Option<String>>>,
+    etag_cache: Option<Arc<Mutex<EtagCache>>>,
+    max_upload_size: u64,
+    allow_foreign_hosts: bool,
+    token_provider: Option<Arc<dyn TokenProvider + Send + Sync>>,
+}
+
+/// Policy for retrying calls that failed with a transient error.
+///
+/// Only calls with idempotent methods (`GET`, `HEAD`, `PUT`, `DELETE`, `OPTIONS`)
+/// are retried, unless they're made through `REST::query_idempotent`. The delay
+/// before each retry doubles, starting from `base_delay`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::rest::RetryPolicy;
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::builder()
+///     .max_attempts(5u32)
+///     .base_delay(Duration::from_millis(500))
+///     .build();
+/// ```
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct RetryPolicy {
+    /// Maximum number of times a call is sent, including the first
+    #[builder(default = 3)]
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    #[builder(default = Duration::from_millis(200))]
+    pub base_delay: Duration,
+    /// Response status codes that are retried
+    #[builder(default = vec![502, 503, 504])]
+    pub statuses: Vec<u16>,
+    /// Whether to retry calls that failed to connect or timed out
+    #[builder(default = true)]
+    pub retry_connection_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::builder().build()
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a call that failed with the error should be retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `err` - error from the call
+    fn should_retry(&self, err: &Error) -> bool {
+        if let Some(e) = err.downcast_ref::<BadHttpResponseError>() {
+            return self.statuses.contains(&e.status);
+        }
+        match err.downcast_ref::<reqwest::Error>() {
+            Some(e) => self.retry_connection_errors && (e.is_timeout() || e.is_http()),
+            None => false,
+        }
+    }
+
+    /// Delay before the next attempt.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt` - number of the attempt that failed, starting from 1
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay * 2u32.pow(attempt.saturating_sub(1).min(16))
+    }
+}
+
+/// Call to send, possibly more than once.
+struct Call<'a> {
+    method: HttpMethod,
+    endpoint: &'a str,
+    params: Option<&'a [(&'a str, &'a str)]>,
+    body: Option<&'a str>,
+    /// File sent as multipart form data instead of the body
+    file: Option<&'a FilePart>,
+    access_token: Option<&'a str>,
+    extra: &'a [(&'a str, &'a str)],
+    /// Whether the call can be retried even if its method isn't idempotent
+    idempotent: bool,
+}
+
+/// Rate limit values from the headers of a response.
+///
+/// See https://dev.mixer.com/guides/core/ratelimits
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimit {
+    /// Calls allowed in the endpoint's bucket, from `X-RateLimit-Limit`
+    pub limit: Option<u32>,
+    /// Calls left in the endpoint's bucket, from `X-RateLimit-Remaining`
+    pub remaining: Option<u32>,
+    /// When the bucket resets, in milliseconds since the Unix epoch, from `X-RateLimit-Reset`
+    pub reset_at: Option<u64>,
+}
+
+impl RateLimit {
+    /// Parse the rate limit headers, returning `None` if there aren't any.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - response headers
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use mixer_wrappers::rest::RateLimit;
+    /// # use reqwest::header::{HeaderMap, HeaderValue};
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-ratelimit-remaining", HeaderValue::from_static("5"));
+    /// let rate_limit = RateLimit::from_headers(&headers).unwrap();
+    /// assert_eq!(Some(5), rate_limit.remaining);
+    /// ```
+    pub fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        fn parse<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+        }
+        let rate_limit = RateLimit {
+            limit: parse(headers, "x-ratelimit-limit"),
+            remaining: parse(headers, "x-ratelimit-remaining"),
+            reset_at: parse(headers, "x-ratelimit-reset"),
+        };
+        if rate_limit == RateLimit::default() {
+            None
+        } else {
+            Some(rate_limit)
+        }
+    }
+}
+
+/// Response to a call, from `REST::query_full`.
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Endpoint that was called
+    pub endpoint: String,
+    /// Response headers
+    pub headers: HeaderMap,
+    /// Response body
+    pub body: String,
+}
+
+impl ApiResponse {
+    /// Whether the status is 20X.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Return the response if the status is 20X, or a `BadHttpResponseError` if not.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let body = api
+    ///     .query_full("GET", "some/endpoint", None, None, None)
+    ///     .and_then(|r| r.ensure_success())
+    ///     .map(|r| r.body);
+    /// ```
+    pub fn ensure_success(self) -> Result<ApiResponse, Error> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(self.into_error(1).into())
+        }
+    }
+
+    /// Convert the response into an error for its status.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempts` - number of times the call was sent
+    fn into_error(self, attempts: u32) -> BadHttpResponseError {
+        let request_id = self
+            .headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_owned());
+        BadHttpResponseError {
+            status: self.status,
+            endpoint: self.endpoint,
+            api_error: ApiError::parse(&self.body),
+            body: self.body,
+            request_id,
+            attempts,
+        }
+    }
+}
+
+/// Builder for a `REST` wrapper with non-default settings.
+///
+/// # Examples
+///
+/// ```rust
+/// use mixer_wrappers::rest::REST;
+/// use std::time::Duration;
+///
+/// let api = REST::builder("abcd")
+///     .timeout(Duration::from_secs(30))
+///     .user_agent("my-app/1.0")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct RESTBuilder {
+    client_id: String,
+    timeout: Duration,
+    base_url: String,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    retry_on_rate_limit: bool,
+    retry_policy: Option<RetryPolicy>,
+    etag_cache_capacity: Option<usize>,
+    max_upload_size: u64,
+    allow_foreign_hosts: bool,
+}
+
+impl RESTBuilder {
+    /// Set the timeout for each call; defaults to 10 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - maximum time for a call
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the base URL of the API, e.g. to point the wrapper at a mock server;
+    /// defaults to `BASE_URL`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - URL that endpoints are appended to
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Set the `User-Agent` header sent with each call.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - header value
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    /// Send all calls through a proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `proxy` - proxy URL, e.g. `http://localhost:8888`
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    /// Wait for the rate limit to reset and retry once when a call gets a 429 response,
+    /// rather than returning a `RateLimited` error straight away; defaults to `false`.
+    ///
+    /// Calls aren't retried if the reset is more than a minute away.
+    ///
+    /// # Arguments
+    ///
+    /// * `retry` - whether to retry
+    pub fn retry_on_rate_limit(mut self, retry: bool) -> Self {
+        self.retry_on_rate_limit = retry;
+        self
+    }
+
+    /// Retry calls that fail with a transient error, like a 503 response or a
+    /// timeout; calls aren't retried by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - when and how often to retry
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Cache the bodies of `GET` responses that have an `ETag`, sending
+    /// `If-None-Match` when the same URL is called again and returning the
+    /// cached body if Mixer responds that it hasn't changed (HTTP 304);
+    /// responses aren't cached by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - maximum number of responses kept, dropping the least
+    ///   recently used first
+    pub fn etag_cache(mut self, capacity: usize) -> Self {
+        self.etag_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Set the largest file that `REST::upload` sends; larger files are
+    /// rejected before calling the API. Defaults to 10 MiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - maximum size, in bytes
+    pub fn max_upload_size(mut self, bytes: u64) -> Self {
+        self.max_upload_size = bytes;
+        self
+    }
+
+    /// Allow full URLs passed as endpoints to be on a different host or scheme
+    /// than the base URL. Off by default, as the client ID and any access
+    /// token would be sent to that host.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - whether to send calls to other hosts
+    pub fn allow_foreign_hosts(mut self, allow: bool) -> Self {
+        self.allow_foreign_hosts = allow;
+        self
+    }
+
+    /// Build the wrapper, returning an error if any of the settings are invalid.
+    pub fn build(self) -> Result<REST, Error> {
+        Url::parse(&self.base_url)
+            .map_err(|e| format_err!("Invalid base URL '{}': {}", self.base_url, e))?;
+        let mut builder = Client::builder().timeout(self.timeout);
+        if let Some(user_agent) = &self.user_agent {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::USER_AGENT, HeaderValue::from_str(user_agent)?);
+            builder = builder.default_headers(headers);
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy.as_str())
+                .map_err(|e| format_err!("Invalid proxy URL '{}': {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(REST {
+            client: builder.build()?,
+            client_id: self.client_id,
+            base_url: self.base_url,
+            retry_on_rate_limit: self.retry_on_rate_limit,
+            retry_policy: self.retry_policy,
+            rate_limit: Arc::new(Mutex::new(RateLimit::default())),
+            csrf_token: Arc::new(Mutex::new(None)),
+            etag_cache: self
+                .etag_cache_capacity
+                .map(|capacity| Arc::new(Mutex::new(EtagCache::new(capacity)))),
+            max_upload_size: self.max_upload_size,
+            allow_foreign_hosts: self.allow_foreign_hosts,
+            token_provider: None,
+        })
+    }
 }
 
 impl REST {
@@ -56,21 +492,189 @@ impl REST {
     /// let api = REST::new("abcd");
     /// ```
     pub fn new(client_id: &str) -> Self {
-        REST {
-            client: Client::builder()
-                .timeout(Duration::from_secs(TIMEOUT))
-                .build()
-                .unwrap(),
-            client_id: client_id.to_string(),
+        REST::builder(client_id)
+            .build()
+            .expect("Could not create the HTTP client")
+    }
+
+    /// Start building an API wrapper with non-default settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your Mixer API client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mixer_wrappers::rest::REST;
+    ///
+    /// let api = REST::builder("abcd")
+    ///     .base_url("http://localhost:8080")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(client_id: &str) -> RESTBuilder {
+        RESTBuilder {
+            client_id: client_id.to_owned(),
+            timeout: Duration::from_secs(TIMEOUT),
+            base_url: BASE_URL.to_owned(),
+            user_agent: None,
+            proxy: None,
+            retry_on_rate_limit: false,
+            retry_policy: None,
+            etag_cache_capacity: None,
+            max_upload_size: MAX_UPLOAD_SIZE,
+            allow_foreign_hosts: false,
         }
     }
 
+    /// Change the client ID sent with subsequent calls, e.g. when rotating credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - your new Mixer API client ID
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mixer_wrappers::rest::REST;
+    ///
+    /// let mut api = REST::new("abcd");
+    /// api.set_client_id("efgh");
+    /// ```
+    pub fn set_client_id(&mut self, client_id: &str) {
+        self.client_id = client_id.to_owned();
+    }
+
+    /// Change the base URL of the API for subsequent calls, e.g. to point the
+    /// wrapper at a mock server.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - URL that endpoints are appended to
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mixer_wrappers::rest::REST;
+    ///
+    /// let mut api = REST::new("abcd");
+    /// api.set_base_url("http://localhost:8080/");
+    /// ```
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+    }
+
+    /// Get the OAuth access token for calls that don't pass one from a provider,
+    /// which is asked for a new token when a call gets a 401 response.
+    ///
+    /// Calls that pass an `access_token`, or set the `Authorization` header
+    /// themselves, are sent unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - source of access tokens
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{oauth::{get_mixer_token_from_code, RefreshingTokenProvider}, REST};
+    /// # let token = get_mixer_token_from_code("abcd", "secret", &["s_1"], "ccc", "code").unwrap();
+    /// let mut api = REST::new("abcd");
+    /// api.set_token_provider(Box::new(RefreshingTokenProvider::new("abcd", "secret", token)));
+    /// let text = api.query("GET", "users/current", None, None, None).unwrap();
+    /// ```
+    pub fn set_token_provider(&mut self, provider: Box<dyn TokenProvider + Send + Sync>) {
+        self.token_provider = Some(Arc::from(provider));
+    }
+
+    /// Rate limit values from the last response that had them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// # api.ping().unwrap();
+    /// if api.rate_limit_status().remaining == Some(0) {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn rate_limit_status(&self) -> RateLimit {
+        self.rate_limit.lock().unwrap().clone()
+    }
+
+    /// CSRF token that Mixer last asked for, which is sent with every call.
+    ///
+    /// Mixer responds with status 461 and a new token when a call needs one;
+    /// the call is then sent again with the token automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// # api.ping().unwrap();
+    /// if let Some(token) = api.csrf_token() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn csrf_token(&self) -> Option<String> {
+        self.csrf_token.lock().unwrap().clone()
+    }
+
+    /// Update the rate limit status from the headers of a response.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - response headers
+    fn record_rate_limit(&self, headers: &HeaderMap) -> Option<RateLimit> {
+        let rate_limit = RateLimit::from_headers(headers)?;
+        *self.rate_limit.lock().unwrap() = rate_limit.clone();
+        Some(rate_limit)
+    }
+
     /// Get the base REST API URL.
-    fn base_url(&self) -> String {
-        #[cfg(not(test))]
-        return "https://mixer.com/api/v1".to_owned();
-        #[cfg(test)]
-        return mockito::server_url();
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Get the full URL to send a call to, using the endpoint as-is if it's
+    /// already a full URL.
+    ///
+    /// Full URLs must be on the same host as the base URL, unless
+    /// `RESTBuilder::allow_foreign_hosts` was set.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - API endpoint or full URL
+    fn call_url(&self, endpoint: &str) -> Result<String, Error> {
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Ok(format!("{}/{}", self.base_url(), endpoint));
+        }
+        let url =
+            Url::parse(endpoint).map_err(|e| format_err!("Invalid URL '{}': {}", endpoint, e))?;
+        if !self.allow_foreign_hosts {
+            let base = Url::parse(self.base_url())?;
+            if url.host_str() != base.host_str()
+                || url.port_or_known_default() != base.port_or_known_default()
+            {
+                return Err(format_err!(
+                    "Refusing to send a call to '{}', which is not on the API's host",
+                    endpoint
+                ));
+            }
+            // the client ID and tokens must not be sent over a downgraded scheme
+            if url.scheme() != base.scheme() {
+                return Err(format_err!(
+                    "Refusing to send a call to '{}' over {}, as the API is on {}",
+                    endpoint,
+                    url.scheme(),
+                    base.scheme()
+                ));
+            }
+        }
+        Ok(endpoint.to_owned())
     }
 
     /// Build the required API headers.
@@ -84,11 +688,10 @@ impl REST {
             HeaderName::from_static("client-id"),
             HeaderValue::from_bytes(self.client_id.as_bytes()).unwrap(),
         );
-        if access_token.is_some() {
+        if let Some(token) = access_token {
             map.insert(
                 header::AUTHORIZATION,
-                HeaderValue::from_bytes(format!("Bearer {}", access_token.unwrap()).as_bytes())
-                    .unwrap(),
+                HeaderValue::from_bytes(format!("Bearer {}", token).as_bytes()).unwrap(),
             );
         }
         map
@@ -98,8 +701,9 @@ impl REST {
     ///
     /// # Arguments
     ///
-    /// * `method` - HTTP verb
-    /// * `endpoint` - API endpoint (do not include the API base URL)
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
     /// * `params` - query params to include (if none, just send `&[]`)
     /// * `body` - optional HTTP body String
     /// * `access_token` - optional OAuth token
@@ -111,101 +715,1587 @@ impl REST {
     /// let api = REST::new("");
     /// let text = api.query("GET", "some/endpoint", None, None, None).unwrap();
     /// ```
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{rest::HttpMethod, REST};
+    /// let api = REST::new("");
+    /// let text = api
+    ///     .query(HttpMethod::Get, "some/endpoint", None, None, None)
+    ///     .unwrap();
+    /// ```
     pub fn query(
         &self,
-        method: &str,
+        method: impl IntoHttpMethod,
         endpoint: &str,
         params: Option<&[(&str, &str)]>,
         body: Option<&str>,
         access_token: Option<&str>,
     ) -> Result<String, Error> {
-        let url = format!("{}/{}", self.base_url(), endpoint);
-        let method = Method::from_bytes(method.to_uppercase().as_bytes())?;
-        debug!("Making {} call to {}", method, url);
-        let mut builder = self
-            .client
-            .request(method, &url)
-            .headers(self.headers(access_token));
-        if params.is_some() {
-            builder = builder.query(params.unwrap());
-        }
-        if body.is_some() {
-            builder = builder.body(body.unwrap().to_owned());
-        }
-        let req = builder.build()?;
-        let mut resp = self.client.execute(req)?;
-        if !resp.status().is_success() {
-            let headers: Vec<String> = resp.headers().iter().map(|h| format!("{:?}", h)).collect();
-            debug!(
-                "Got status code {} from endpoint, headers: {}, text: {}",
-                resp.status().as_str(),
-                headers.join(", "),
-                resp.text()?
-            );
-            return Err(BadHttpResponseError(resp.status().as_u16()).into());
-        }
-        let text = resp.text()?;
-        Ok(text)
+        self.query_with_headers(method, endpoint, params, body, access_token, &[])
     }
 
-    /// Get a struct with several chat-related endpoint helpers.
+    /// Query an endpoint, deserializing the JSON response body.
+    ///
+    /// An empty body (e.g. from a 204 response) is treated as JSON `null`, so it
+    /// deserializes into `()`, an `Option` (as `None`), or `Value::Null`; for any
+    /// other type it's an `errors::EmptyBody` error. Use `query_no_content` for
+    /// endpoints that never return a body. Errors from deserializing include the
+    /// endpoint and the start of the body.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `body` - optional HTTP body String
+    /// * `access_token` - optional OAuth token
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use mixer_wrappers::REST;
+    /// # use serde_json::Value;
     /// let api = REST::new("");
-    /// let helper = api.chat_helper();
+    /// let json: Value = api.query_json("GET", "some/endpoint", None, None, None).unwrap();
     /// ```
-    pub fn chat_helper(&self) -> ChatHelper {
-        ChatHelper { rest: self }
+    pub fn query_json<T: DeserializeOwned>(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: Option<&str>,
+        access_token: Option<&str>,
+    ) -> Result<T, Error> {
+        let text = self.query(method, endpoint, params, body, access_token)?;
+        parse_json(endpoint, &text)
     }
 
-    /// Get a struct with several WebHook-related endpoint helpers.
+    /// Query an endpoint that responds with no content, e.g. a `DELETE` or `PUT`
+    /// that returns 204. Any body that is sent is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `body` - optional HTTP body String
+    /// * `access_token` - optional OAuth token
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// # use mixer_wrappers::REST;
     /// let api = REST::new("");
-    /// let helper = api.webhook_helper();
+    /// api.query_no_content("DELETE", "some/endpoint", None, None, Some("token"))
+    ///     .unwrap();
     /// ```
-    pub fn webhook_helper(&self) -> WebHookHelper {
-        WebHookHelper { rest: self }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::REST;
-    use mockito::mock;
-
-    #[test]
-    fn headers() {
-        let rest = REST::new("foobar");
-        let headers = rest.headers(None);
-        assert_eq!(1, headers.len());
-        assert_eq!(
-            "foobar",
-            headers.get("client-id").unwrap().to_str().unwrap()
-        );
+    pub fn query_no_content(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: Option<&str>,
+        access_token: Option<&str>,
+    ) -> Result<(), Error> {
+        self.query(method, endpoint, params, body, access_token)?;
+        Ok(())
     }
 
-    #[test]
-    fn query_good() {
+    /// Query an endpoint, sending additional headers.
+    ///
+    /// The `extra` headers are merged on top of the default headers, so
+    /// passing a header that's already set (like `client-id`) replaces it.
+    /// Headers that aren't passed, like the `Authorization` header from
+    /// `access_token`, are always kept. Names are case-insensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `body` - optional HTTP body String
+    /// * `access_token` - optional OAuth token
+    /// * `extra` - additional headers to send
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let text = api
+    ///     .query_with_headers("GET", "some/endpoint", None, None, None, &[("x-foo", "bar")])
+    ///     .unwrap();
+    /// ```
+    pub fn query_with_headers(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: Option<&str>,
+        access_token: Option<&str>,
+        extra: &[(&str, &str)],
+    ) -> Result<String, Error> {
+        self.execute(&Call {
+            method: method.into_http_method()?,
+            endpoint,
+            params,
+            body,
+            file: None,
+            access_token,
+            extra,
+            idempotent: false,
+        })
+    }
+
+    /// Query an endpoint, sending a value serialized to JSON as the body.
+    ///
+    /// The `Content-Type: application/json` header is set; otherwise this
+    /// behaves like `query`.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `body` - value to serialize as the body
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// # use serde_json::json;
+    /// let api = REST::new("");
+    /// let text = api
+    ///     .query_with_json("PATCH", "some/endpoint", None, &json!({"name": "foo"}), Some("token"))
+    ///     .unwrap();
+    /// ```
+    pub fn query_with_json<B: Serialize>(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: &B,
+        access_token: Option<&str>,
+    ) -> Result<String, Error> {
+        self.send_json(method, endpoint, params, body, access_token, &[])
+    }
+
+    /// Serialize the body to JSON and send it with the JSON content type and
+    /// any additional headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include
+    /// * `body` - value to serialize as the body
+    /// * `access_token` - optional OAuth token
+    /// * `extra` - additional headers to send
+    fn send_json<B: Serialize>(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: &B,
+        access_token: Option<&str>,
+        extra: &[(&str, &str)],
+    ) -> Result<String, Error> {
+        let text = serde_json::to_string(body)
+            .map_err(|e| format_err!("Could not serialize the body for '{}': {}", endpoint, e))?;
+        let mut headers = vec![("content-type", "application/json")];
+        headers.extend_from_slice(extra);
+        self.query_with_headers(
+            method,
+            endpoint,
+            params,
+            Some(&text),
+            access_token,
+            &headers,
+        )
+    }
+
+    /// Upload a file as multipart form data, e.g. a channel banner or user avatar.
+    ///
+    /// Files larger than the maximum upload size (see
+    /// `RESTBuilder::max_upload_size`) are rejected without calling the API.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"POST"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `field_name` - name of the form field to send the file in
+    /// * `source` - path or contents of the file
+    /// * `mime` - MIME type of the file, e.g. `image/png`
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{rest::UploadSource, REST};
+    /// # use std::path::Path;
+    /// let api = REST::new("");
+    /// let text = api
+    ///     .upload(
+    ///         "POST",
+    ///         "users/123/avatar",
+    ///         "avatar",
+    ///         UploadSource::Path(Path::new("avatar.png")),
+    ///         "image/png",
+    ///         Some("token"),
+    ///     )
+    ///     .unwrap();
+    /// ```
+    pub fn upload(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        field_name: &str,
+        source: UploadSource,
+        mime: &str,
+        access_token: Option<&str>,
+    ) -> Result<String, Error> {
+        let method = method.into_http_method()?;
+        let file = FilePart::read(field_name, source, mime, self.max_upload_size)?;
+        self.execute(&Call {
+            method,
+            endpoint,
+            params: None,
+            body: None,
+            file: Some(&file),
+            access_token,
+            extra: &[],
+            idempotent: false,
+        })
+    }
+
+    /// Download the response from an endpoint into a writer, without holding
+    /// the whole body in memory.
+    ///
+    /// Non-20X responses are returned as errors, like `query`. Downloads aren't
+    /// retried by the retry policy, since part of the body may already have
+    /// been written.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `access_token` - optional OAuth token
+    /// * `writer` - where to write the body
+    /// * `progress` - optional callback with the number of bytes written so far,
+    ///   called after each chunk
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// # use std::fs::File;
+    /// let api = REST::new("");
+    /// let mut file = File::create("manifest.json").unwrap();
+    /// let mut report = |bytes| println!("{} bytes so far", bytes);
+    /// let bytes = api
+    ///     .download("GET", "recordings/123", None, None, &mut file, Some(&mut report))
+    ///     .unwrap();
+    /// ```
+    pub fn download(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        access_token: Option<&str>,
+        writer: &mut impl Write,
+        mut progress: Option<&mut dyn FnMut(u64)>,
+    ) -> Result<u64, Error> {
+        let call = Call {
+            method: method.into_http_method()?,
+            endpoint,
+            params,
+            body: None,
+            file: None,
+            access_token,
+            extra: &[],
+            idempotent: false,
+        };
+        let (mut resp, request_url) = self.send_request(&self.call_headers(&call)?, &call)?;
+        if !resp.status().is_success() {
+            // errors, and 304s filled in from the ETag cache, are small enough to read whole
+            let response = self.read_response(resp, &request_url, &call)?;
+            let body = self.check_status(response, 1)?;
+            writer.write_all(body.as_bytes())?;
+            if let Some(progress) = progress.as_mut() {
+                progress(body.len() as u64);
+            }
+            return Ok(body.len() as u64);
+        }
+        let mut buffer = [0; DOWNLOAD_CHUNK_SIZE];
+        let mut written = 0;
+        loop {
+            let read = resp.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            written += read as u64;
+            if let Some(progress) = progress.as_mut() {
+                progress(written);
+            }
+        }
+        Ok(written)
+    }
+
+    /// Query an endpoint, allowing the call to be retried by the retry policy
+    /// even if its method isn't idempotent.
+    ///
+    /// Only use this for calls that are safe to send more than once, e.g. a
+    /// `POST` that sets a value rather than creating something.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `body` - optional HTTP body String
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let text = api
+    ///     .query_idempotent("POST", "some/endpoint", None, Some("{}"), None)
+    ///     .unwrap();
+    /// ```
+    pub fn query_idempotent(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: Option<&str>,
+        access_token: Option<&str>,
+    ) -> Result<String, Error> {
+        self.execute(&Call {
+            method: method.into_http_method()?,
+            endpoint,
+            params,
+            body,
+            file: None,
+            access_token,
+            extra: &[],
+            idempotent: true,
+        })
+    }
+
+    /// Query an endpoint, returning the status and headers of the response as
+    /// well as the body.
+    ///
+    /// Unlike `query`, responses with a non-20X status are returned rather than
+    /// made into errors, so that they can be inspected; use `ensure_success` to
+    /// turn them into errors. Calls aren't retried by the retry policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP verb, as an `HttpMethod` or a string like `"GET"`
+    /// * `endpoint` - API endpoint (do not include the API base URL), or a full URL
+    ///   on the API's host, such as a next-page link from a response
+    /// * `params` - query params to include (if none, just send `&[]`)
+    /// * `body` - optional HTTP body String
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let resp = api.query_full("GET", "some/endpoint", None, None, None).unwrap();
+    /// let total = resp.headers.get("x-total-count");
+    /// let body = resp.ensure_success().unwrap().body;
+    /// ```
+    pub fn query_full(
+        &self,
+        method: impl IntoHttpMethod,
+        endpoint: &str,
+        params: Option<&[(&str, &str)]>,
+        body: Option<&str>,
+        access_token: Option<&str>,
+    ) -> Result<ApiResponse, Error> {
+        let call = Call {
+            method: method.into_http_method()?,
+            endpoint,
+            params,
+            body,
+            file: None,
+            access_token,
+            extra: &[],
+            idempotent: false,
+        };
+        self.send_raw(&self.call_headers(&call)?, &call)
+    }
+
+    /// Get every page of results from a list endpoint, deserializing the
+    /// elements of each page's JSON array.
+    ///
+    /// Pages are requested with the `page` param, starting from 0, until one
+    /// has fewer results than the `limit` param or the total from the
+    /// `x-total-count` header has been reached. If `limit` isn't passed in
+    /// `base_params`, 50 results are requested per page; any `page` param is
+    /// replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - API endpoint (do not include the API base URL)
+    /// * `base_params` - query params to send with every page
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::{rest::models::Follower, REST};
+    /// let api = REST::new("");
+    /// let followers: Vec<Follower> = api
+    ///     .query_paginated("channels/1234/follow", &[("limit", "100")], None)
+    ///     .unwrap();
+    /// ```
+    pub fn query_paginated<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        base_params: &[(&str, &str)],
+        access_token: Option<&str>,
+    ) -> Result<Vec<T>, Error> {
+        let mut params: Vec<(&str, &str)> = base_params
+            .iter()
+            .filter(|(name, _)| *name != "page")
+            .cloned()
+            .collect();
+        let default_limit = PAGE_SIZE.to_string();
+        let limit: usize = match params.iter().find(|(name, _)| *name == "limit") {
+            Some((_, limit)) => limit
+                .parse()
+                .map_err(|e| format_err!("Invalid limit '{}': {}", limit, e))?,
+            None => {
+                params.push(("limit", &default_limit));
+                PAGE_SIZE
+            }
+        };
+        let mut results = Vec::new();
+        for page in 0.. {
+            let page = page.to_string();
+            let mut page_params = params.clone();
+            page_params.push(("page", &page));
+            let resp = self
+                .query_full(
+                    HttpMethod::Get,
+                    endpoint,
+                    Some(&page_params),
+                    None,
+                    access_token,
+                )?
+                .ensure_success()?;
+            let total: Option<usize> = resp
+                .headers
+                .get("x-total-count")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            let items: Vec<T> = parse_json(endpoint, &resp.body)?;
+            let count = items.len();
+            results.extend(items);
+            if count < limit || count == 0 || total.is_some_and(|t| results.len() >= t) {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Build the headers for a call, with its extra headers merged over the defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - call to send
+    fn call_headers(&self, call: &Call) -> Result<HeaderMap, Error> {
+        let mut headers = self.headers(call.access_token);
+        for (name, value) in call.extra {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(headers)
+    }
+
+    /// Send a call, retrying it as allowed by the retry policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - call to send
+    fn execute(&self, call: &Call) -> Result<String, Error> {
+        let headers = self.call_headers(call)?;
+        let policy = match &self.retry_policy {
+            Some(policy) if call.idempotent || call.method.is_idempotent() => Some(policy),
+            _ => None,
+        };
+        let mut attempt = 1;
+        loop {
+            let err = match self.send(&headers, call, attempt) {
+                Ok(text) => return Ok(text),
+                Err(e) => e,
+            };
+            match policy {
+                Some(policy) if attempt < policy.max_attempts && policy.should_retry(&err) => {
+                    let delay = policy.delay(attempt);
+                    debug!(
+                        "Attempt {} failed ({}), retrying in {:?}",
+                        attempt, err, delay
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                // status errors already include the number of attempts
+                _ if attempt > 1 && err.downcast_ref::<BadHttpResponseError>().is_none() => {
+                    return Err(format_err!("{} (after {} attempts)", err, attempt));
+                }
+                _ => return Err(err),
+            }
+        }
+    }
+
+    /// Send a call once, returning an error for a non-20X response.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - headers to send
+    /// * `call` - call to send
+    /// * `attempt` - number of this attempt, starting from 1
+    fn send(&self, headers: &HeaderMap, call: &Call, attempt: u32) -> Result<String, Error> {
+        let resp = self.send_raw(headers, call)?;
+        self.check_status(resp, attempt)
+    }
+
+    /// Return the body of a 20X response, or an error for any other status.
+    ///
+    /// # Arguments
+    ///
+    /// * `resp` - response to the call
+    /// * `attempt` - number of this attempt, starting from 1
+    fn check_status(&self, resp: ApiResponse, attempt: u32) -> Result<String, Error> {
+        if resp.status == StatusCode::TOO_MANY_REQUESTS.as_u16() {
+            let reset_at = RateLimit::from_headers(&resp.headers).and_then(|r| r.reset_at);
+            return Err(RateLimited { reset_at }.into());
+        }
+        if !resp.is_success() {
+            debug!(
+                "Got status code {} from endpoint, headers: {}, text: {}",
+                resp.status,
+                redact_headers(&resp.headers),
+                redact_json(&resp.body)
+            );
+            return Err(resp.into_error(attempt).into());
+        }
+        Ok(resp.body)
+    }
+
+    /// Send a call once, apart from waiting out the rate limit and sending it
+    /// again with a new CSRF token, returning the response whatever its status.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - headers to send
+    /// * `call` - call to send
+    fn send_raw(&self, headers: &HeaderMap, call: &Call) -> Result<ApiResponse, Error> {
+        let (resp, request_url) = self.send_request(headers, call)?;
+        self.read_response(resp, &request_url, call)
+    }
+
+    /// Read the whole of a response, filling in the body from the ETag cache
+    /// if it's a 304.
+    ///
+    /// # Arguments
+    ///
+    /// * `resp` - response to the call
+    /// * `request_url` - full URL the call was sent to
+    /// * `call` - call that was sent
+    fn read_response(
+        &self,
+        mut resp: reqwest::Response,
+        request_url: &str,
+        call: &Call,
+    ) -> Result<ApiResponse, Error> {
+        let mut response = ApiResponse {
+            status: resp.status().as_u16(),
+            endpoint: call.endpoint.to_owned(),
+            body: resp.text()?,
+            headers: resp.headers().clone(),
+        };
+        if call.method == HttpMethod::Get {
+            self.update_etag_cache(request_url, &mut response);
+        }
+        Ok(response)
+    }
+
+    /// Send a call, returning the response before its body has been read,
+    /// along with the full URL it was sent to.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - headers to send
+    /// * `call` - call to send
+    fn send_request(
+        &self,
+        headers: &HeaderMap,
+        call: &Call,
+    ) -> Result<(reqwest::Response, String), Error> {
+        let method = Method::from(call.method);
+        let url = self.call_url(call.endpoint)?;
+        let mut retried = false;
+        let mut csrf_retried = false;
+        let mut token_refreshed = false;
+        let provider = self
+            .token_provider
+            .as_ref()
+            .filter(|_| !headers.contains_key(header::AUTHORIZATION));
+        let mut provided_token = match provider {
+            Some(provider) => Some(provider.access_token()?),
+            None => None,
+        };
+        let (resp, request_url) = loop {
+            let mut headers = headers.clone();
+            if let Some(token) = self.csrf_token() {
+                headers.insert(CSRF_HEADER, HeaderValue::from_str(&token)?);
+            }
+            if let Some(token) = &provided_token {
+                headers.insert(
+                    header::AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {}", token))?,
+                );
+            }
+            let mut builder = self.client.request(method.clone(), &url).headers(headers);
+            if let Some(params) = call.params {
+                builder = builder.query(params);
+            }
+            if let Some(body) = call.body {
+                builder = builder.body(body.to_owned());
+            }
+            if let Some(file) = call.file {
+                builder = builder.multipart(file.form()?);
+            }
+            let mut req = builder.build()?;
+            let request_url = req.url().to_string();
+            if let Some(cached) = self.cached_response(&req) {
+                req.headers_mut()
+                    .insert(header::IF_NONE_MATCH, HeaderValue::from_str(&cached.etag)?);
+            }
+            debug!("Making call: {}", redact(&req));
+            let resp = self.client.execute(req)?;
+            let rate_limit = self.record_rate_limit(resp.headers());
+            if resp.status().as_u16() == CSRF_STATUS && !csrf_retried {
+                let token = resp
+                    .headers()
+                    .get(CSRF_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_owned());
+                if let Some(token) = token {
+                    debug!("Got a new CSRF token, retrying");
+                    *self.csrf_token.lock().unwrap() = Some(token);
+                    csrf_retried = true;
+                    continue;
+                }
+            }
+            if resp.status() == StatusCode::UNAUTHORIZED && !token_refreshed {
+                if let Some(provider) = provider {
+                    debug!("Access token was rejected, refreshing it and retrying");
+                    provided_token = Some(provider.refresh()?);
+                    token_refreshed = true;
+                    continue;
+                }
+            }
+            if resp.status() != StatusCode::TOO_MANY_REQUESTS {
+                break (resp, request_url);
+            }
+            let wait = rate_limit.and_then(|r| r.reset_at).map(|reset_at| {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default();
+                Duration::from_millis(reset_at.saturating_sub(now))
+            });
+            match wait {
+                Some(wait)
+                    if self.retry_on_rate_limit && !retried && wait <= MAX_RATE_LIMIT_WAIT =>
+                {
+                    debug!("Rate limited, retrying in {:?}", wait);
+                    thread::sleep(wait);
+                    retried = true;
+                }
+                _ => break (resp, request_url),
+            }
+        };
+        Ok((resp, request_url))
+    }
+
+    /// Get the cached response for a `GET`, if the ETag cache is enabled and has one.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - request about to be sent
+    fn cached_response(&self, req: &reqwest::Request) -> Option<CachedResponse> {
+        if req.method() != Method::GET {
+            return None;
+        }
+        self.etag_cache
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .get(req.url().as_str())
+    }
+
+    /// Cache a response with an ETag, or fill in the cached body if the
+    /// response is a 304.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - full URL of the call
+    /// * `response` - response to the call
+    fn update_etag_cache(&self, url: &str, response: &mut ApiResponse) {
+        let mut cache = match &self.etag_cache {
+            Some(cache) => cache.lock().unwrap(),
+            None => return,
+        };
+        if response.status == StatusCode::NOT_MODIFIED.as_u16() {
+            if let Some(cached) = cache.get(url) {
+                debug!("Using the cached response for {}", url);
+                response.status = StatusCode::OK.as_u16();
+                response.body = cached.body;
+            }
+            return;
+        }
+        if !response.is_success() {
+            return;
+        }
+        let etag = response
+            .headers
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok());
+        if let Some(etag) = etag {
+            cache.insert(
+                url,
+                CachedResponse {
+                    etag: etag.to_owned(),
+                    body: response.body.clone(),
+                },
+            );
+        }
+    }
+
+    /// Check that the API is reachable and accepts the client ID.
+    ///
+    /// This makes a `GET` to `channels?fields=id&limit=1`, which is public
+    /// and returns a single small result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// if let Err(e) = api.ping() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn ping(&self) -> Result<(), Error> {
+        self.query(
+            HttpMethod::Get,
+            "channels",
+            Some(&[("fields", "id"), ("limit", "1")]),
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// Get a channel's most recent followers, newest first.
+    ///
+    /// This is the first page of `ChannelsHelper::get_followers`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    /// * `limit` - maximum number of followers to get
+    /// * `access_token` - optional OAuth token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// for follower in api.get_followers(1234, 10, None).unwrap() {
+    ///     println!("{} at {}", follower.username, follower.followed.created_at);
+    /// }
+    /// ```
+    pub fn get_followers(
+        &self,
+        channel_id: usize,
+        limit: usize,
+        access_token: Option<&str>,
+    ) -> Result<Vec<Follower>, Error> {
+        self.channels_helper()
+            .get_followers(channel_id, None, Some(limit as u32), access_token)
+    }
+
+    /// Get the emotes that can be used in a channel's chat, for rendering messages.
+    ///
+    /// Each emote has the URL of the sprite sheet it's in and its position there;
+    /// these are the packs from `ChannelsHelper::get_emoticons`, flattened.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// for emote in api.get_channel_emotes(1234).unwrap() {
+    ///     println!("{} is at ({}, {}) in {}", emote.name, emote.x, emote.y, emote.url);
+    /// }
+    /// ```
+    pub fn get_channel_emotes(&self, channel_id: usize) -> Result<Vec<Emote>, Error> {
+        let packs = self.channels_helper().get_emoticons(channel_id, None)?;
+        Ok(packs
+            .into_iter()
+            .flat_map(EmoticonPack::into_emotes)
+            .collect())
+    }
+
+    /// Get the id of the authenticated user's own channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_token` - OAuth access token of the user
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let channel_id = api.get_current_channel_id("access_token").unwrap();
+    /// ```
+    pub fn get_current_channel_id(&self, access_token: &str) -> Result<usize, Error> {
+        debug!("Getting the current user's channel ID");
+        let json: serde_json::Value = self.query_json(
+            HttpMethod::Get,
+            "users/current",
+            Some(&[("fields", "channel")]),
+            None,
+            Some(access_token),
+        )?;
+        match json["channel"]["id"].as_u64() {
+            Some(id) => Ok(id as usize),
+            None => Err(format_err!("Response does not have a 'channel.id' field")),
+        }
+    }
+
+    /// Get a struct with several chat-related endpoint helpers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let helper = api.chat_helper();
+    /// ```
+    pub fn chat_helper(&self) -> ChatHelper<'_> {
+        ChatHelper::new(self)
+    }
+
+    /// Get a struct with several WebHook-related endpoint helpers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let helper = api.webhook_helper();
+    /// ```
+    pub fn webhook_helper(&self) -> WebHookHelper<'_> {
+        WebHookHelper { rest: self }
+    }
+
+    /// Get a struct with several user-related endpoint helpers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// ```
+    pub fn users_helper(&self) -> UsersHelper<'_> {
+        UsersHelper { rest: self }
+    }
+
+    /// Get a struct with several channel-related endpoint helpers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::REST;
+    /// let api = REST::new("");
+    /// let helper = api.channels_helper();
+    /// ```
+    pub fn channels_helper(&self) -> ChannelsHelper<'_> {
+        ChannelsHelper { rest: self }
+    }
+}
+
+/// Deserialize a response body, treating an empty body as JSON `null`.
+///
+/// # Arguments
+///
+/// * `endpoint` - endpoint that was called, for errors
+/// * `text` - response body
+fn parse_json<T: DeserializeOwned>(endpoint: &str, text: &str) -> Result<T, Error> {
+    if text.trim().is_empty() {
+        return serde_json::from_str("null").map_err(|_| {
+            EmptyBody {
+                endpoint: endpoint.to_owned(),
+            }
+            .into()
+        });
+    }
+    serde_json::from_str(text).map_err(|e| {
+        format_err!(
+            "Could not parse the response from '{}': {} (body: '{}')",
+            endpoint,
+            e,
+            errors::snippet(text)
+        )
+    })
+}
+
+/// Iterate over the results of a list endpoint, getting each page of
+/// `PAGE_SIZE` as it's needed until one comes back short.
+///
+/// If getting a page fails, the error is the last item.
+///
+/// # Arguments
+///
+/// * `get_page` - gets a page by number, starting from 0, with `PAGE_SIZE` results
+fn iter_pages<'a, T: 'a>(
+    mut get_page: impl FnMut(u32) -> Result<Vec<T>, Error> + 'a,
+) -> impl Iterator<Item = Result<T, Error>> + 'a {
+    let mut buffer = VecDeque::new();
+    let mut page = 0;
+    let mut done = false;
+    iter::from_fn(move || {
+        if buffer.is_empty() && !done {
+            match get_page(page) {
+                Ok(items) => {
+                    done = items.len() < PAGE_SIZE;
+                    buffer.extend(items);
+                    page += 1;
+                }
+                Err(e) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        buffer.pop_front().map(Ok)
+    })
+}
+
+/// Create a wrapper that calls the local mock server.
+#[cfg(test)]
+pub(crate) fn mock_rest(client_id: &str) -> REST {
+    let mut rest = REST::new(client_id);
+    rest.set_base_url(&mockito::server_url());
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        errors::{self, ApiError, BadHttpResponseError, EmptyBody, RateLimited},
+        mock_rest,
+        models::{Emote, Follow, Follower},
+        HttpMethod, RateLimit, RetryPolicy, TokenProvider, UploadSource, BASE_URL, REST,
+    };
+    use mockito::{mock, Matcher};
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        Method,
+    };
+    use serde_derive::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Channel {
+        id: usize,
+        token: String,
+    }
+
+    #[test]
+    fn query_json() {
+        let _m1 = mock("GET", "/channels/foo")
+            .with_body(r#"{"id": 1, "token": "foo", "online": true}"#)
+            .create();
+        let rest = mock_rest("");
+        let channel: Channel = rest
+            .query_json("GET", "channels/foo", None, None, None)
+            .unwrap();
+        assert_eq!(
+            Channel {
+                id: 1,
+                token: "foo".to_owned()
+            },
+            channel
+        );
+        let value: Value = rest
+            .query_json("GET", "channels/foo", None, None, None)
+            .unwrap();
+        assert_eq!(json!(true), value["online"]);
+    }
+
+    #[test]
+    fn query_json_errors() {
+        let long = format!("<html>{}</html>", "x".repeat(500));
+        let _m1 = mock("GET", "/channels/bad").with_body(&long).create();
+        let rest = mock_rest("");
+        let err = rest
+            .query_json::<Channel>("GET", "channels/bad", None, None, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("channels/bad"));
+        assert!(err.contains("..."));
+        assert!(err.len() < 400);
+    }
+
+    #[test]
+    fn query_json_empty() {
+        let _m1 = mock("DELETE", "/channels/empty").with_status(204).create();
+        let rest = mock_rest("");
+        rest.query_json::<()>("DELETE", "channels/empty", None, None, None)
+            .unwrap();
+        let value: Option<Channel> = rest
+            .query_json("DELETE", "channels/empty", None, None, None)
+            .unwrap();
+        assert_eq!(None, value);
+        let err = rest
+            .query_json::<Channel>("DELETE", "channels/empty", None, None, None)
+            .unwrap_err();
+        assert_eq!(
+            Some(&EmptyBody {
+                endpoint: "channels/empty".to_owned()
+            }),
+            err.downcast_ref::<EmptyBody>()
+        );
+    }
+
+    #[test]
+    fn query_paginated() {
+        let m1 = mock("GET", "/channels/1/follow?fields=id%2Ctoken&limit=2&page=0")
+            .with_header("x-total-count", "3")
+            .with_body(r#"[{"id": 1, "token": "a"}, {"id": 2, "token": "b"}]"#)
+            .create();
+        let m2 = mock("GET", "/channels/1/follow?fields=id%2Ctoken&limit=2&page=1")
+            .with_header("x-total-count", "3")
+            .with_body(r#"[{"id": 3, "token": "c"}]"#)
+            .create();
+        let rest = mock_rest("");
+        let channels: Vec<Channel> = rest
+            .query_paginated(
+                "channels/1/follow",
+                &[("fields", "id,token"), ("limit", "2"), ("page", "5")],
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            vec![(1, "a"), (2, "b"), (3, "c")],
+            channels
+                .iter()
+                .map(|c| (c.id, c.token.as_str()))
+                .collect::<Vec<_>>()
+        );
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn query_paginated_total_reached() {
+        let m1 = mock("GET", "/channels?limit=50&page=0")
+            .with_header("x-total-count", "1")
+            .with_body(r#"[{"id": 1, "token": "a"}]"#)
+            .create();
+        let m2 = mock("GET", "/channels?limit=50&page=1")
+            .with_status(500)
+            .expect(0)
+            .create();
+        let rest = mock_rest("");
+        let channels: Vec<Channel> = rest.query_paginated("channels", &[], None).unwrap();
+        assert_eq!(1, channels.len());
+        m1.assert();
+        m2.assert();
+
+        assert!(rest
+            .query_paginated::<Channel>("channels", &[("limit", "many")], None)
+            .is_err());
+    }
+
+    #[test]
+    fn query_no_content() {
+        let m1 = mock("PUT", "/channels/empty")
+            .with_status(204)
+            .expect(2)
+            .create();
+        let rest = mock_rest("");
+        rest.query_no_content("PUT", "channels/empty", None, Some("{}"), None)
+            .unwrap();
+        rest.query_json::<()>("PUT", "channels/empty", None, Some("{}"), None)
+            .unwrap();
+        m1.assert();
+
+        let _m2 = mock("DELETE", "/channels/missing")
+            .with_status(404)
+            .create();
+        assert_eq!(
+            Some(404),
+            errors::http_status(
+                &rest
+                    .query_no_content("DELETE", "channels/missing", None, None, None)
+                    .unwrap_err()
+            )
+        );
+    }
+
+    #[test]
+    fn set_client_id() {
+        let mut rest = mock_rest("foobar");
+        rest.set_client_id("bazqux");
+        let headers = rest.headers(None);
+        assert_eq!(
+            "bazqux",
+            headers.get("client-id").unwrap().to_str().unwrap()
+        );
+
+        let m1 = mock("GET", "/rotated")
+            .match_header("client-id", "bazqux")
+            .with_body("ok")
+            .create();
+        assert_eq!(
+            "ok",
+            rest.query("GET", "rotated", None, None, None).unwrap()
+        );
+        m1.assert();
+    }
+
+    #[test]
+    fn query_no_params_or_body() {
+        let m1 = mock("POST", Matcher::Regex(r"^/bare$".to_owned()))
+            .match_header("content-length", Matcher::Missing)
+            .match_body("")
+            .with_body("ok")
+            .create();
+        let rest = mock_rest("");
+        assert_eq!("ok", rest.query("POST", "bare", None, None, None).unwrap());
+        m1.assert();
+    }
+
+    #[test]
+    fn query_empty_params() {
+        let m1 = mock("GET", Matcher::Regex(r"^/bare$".to_owned()))
+            .with_body("ok")
+            .create();
+        let rest = mock_rest("");
+        assert_eq!(
+            "ok",
+            rest.query("GET", "bare", Some(&[]), None, None).unwrap()
+        );
+        m1.assert();
+    }
+
+    #[test]
+    fn headers() {
+        let rest = mock_rest("foobar");
+        let headers = rest.headers(None);
+        assert_eq!(1, headers.len());
+        assert_eq!(
+            "foobar",
+            headers.get("client-id").unwrap().to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn query_good() {
         let body = "hello world";
         let _m1 = mock("GET", "/somewhere?foo=bar").with_body(body).create();
-        let rest = REST::new("");
+        let rest = mock_rest("");
+        let resp = rest
+            .query(
+                "GET",
+                "somewhere",
+                Some(&[("foo", "bar")]),
+                Some("hello world"),
+                Some("the_token"),
+            )
+            .unwrap();
+        assert_eq!(body, resp);
+    }
+
+    #[test]
+    fn query_with_headers_extra() {
+        let _m1 = mock("GET", "/somewhere")
+            .match_header("client-id", "foobar")
+            .match_header("x-foo", "bar")
+            .with_body("hello world")
+            .create();
+        let rest = mock_rest("foobar");
+        let resp = rest
+            .query_with_headers("GET", "somewhere", None, None, None, &[("x-foo", "bar")])
+            .unwrap();
+        assert_eq!("hello world", resp);
+    }
+
+    #[test]
+    fn query_with_headers_override() {
+        let _m1 = mock("GET", "/somewhere")
+            .match_header("client-id", "other")
+            .with_body("hello world")
+            .create();
+        let rest = mock_rest("foobar");
         let resp = rest
-            .query(
+            .query_with_headers(
                 "GET",
                 "somewhere",
-                Some(&[("foo", "bar")]),
-                Some("hello world"),
-                Some("the_token"),
+                None,
+                None,
+                None,
+                &[("client-id", "other")],
             )
             .unwrap();
-        assert_eq!(body, resp);
+        assert_eq!("hello world", resp);
+    }
+
+    #[test]
+    fn query_with_headers_keeps_auth() {
+        let m1 = mock("GET", "/somewhere/authed")
+            .match_header("client-id", "foobar")
+            .match_header("authorization", "Bearer token")
+            .match_header("accept", "application/json, text/html")
+            .match_header("x-experiment", "on")
+            .with_body("hello world")
+            .expect(1)
+            .create();
+        let rest = mock_rest("foobar");
+        let resp = rest
+            .query_with_headers(
+                "GET",
+                "somewhere/authed",
+                None,
+                None,
+                Some("token"),
+                &[
+                    ("Accept", "application/json, text/html"),
+                    ("X-Experiment", "on"),
+                ],
+            )
+            .unwrap();
+        assert_eq!("hello world", resp);
+        m1.assert();
+    }
+
+    #[test]
+    fn query_method_kinds() {
+        let m1 = mock("PATCH", "/methods").with_body("ok").expect(3).create();
+        let rest = mock_rest("");
+        assert!(rest
+            .query(HttpMethod::Patch, "methods", None, None, None)
+            .is_ok());
+        assert!(rest.query("patch", "methods", None, None, None).is_ok());
+        assert!(rest
+            .query(Method::PATCH, "methods", None, None, None)
+            .is_ok());
+        let err = rest
+            .query("PTACH", "methods", None, None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid HTTP method 'PTACH'"));
+        m1.assert();
+    }
+
+    #[test]
+    fn query_with_json() {
+        #[derive(Serialize)]
+        struct Update<'a> {
+            name: &'a str,
+            tags: Vec<&'a str>,
+        }
+        let _m = mock("PATCH", "/channels/1")
+            .match_header("content-type", "application/json")
+            .match_header("authorization", "Bearer abc")
+            .match_body(Matcher::Json(json!({"name": "foo", "tags": ["a", "b"]})))
+            .with_body("{}")
+            .create();
+        let rest = mock_rest("");
+        let update = Update {
+            name: "foo",
+            tags: vec!["a", "b"],
+        };
+        let text = rest
+            .query_with_json(HttpMethod::Patch, "channels/1", None, &update, Some("abc"))
+            .unwrap();
+        assert_eq!("{}", text);
+    }
+
+    #[test]
+    fn query_with_json_unserializable() {
+        let mut body = std::collections::HashMap::new();
+        body.insert(vec![1], "not a string key");
+        let rest = mock_rest("");
+        let err = rest
+            .query_with_json("POST", "some/endpoint", None, &body, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("'some/endpoint'"));
+    }
+
+    #[test]
+    fn upload() {
+        let _m = mock("POST", "/users/1/avatar")
+            .match_header("authorization", "Bearer abc")
+            .match_header(
+                "content-type",
+                Matcher::Regex("^multipart/form-data; boundary=.+$".to_owned()),
+            )
+            .match_body(Matcher::Regex(
+                "Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar\"\r\n\
+                 Content-Type: image/png\r\n\r\nnot really a png\r\n"
+                    .to_owned(),
+            ))
+            .with_body("{}")
+            .create();
+        let rest = mock_rest("");
+        let text = rest
+            .upload(
+                HttpMethod::Post,
+                "users/1/avatar",
+                "avatar",
+                UploadSource::Bytes(b"not really a png"),
+                "image/png",
+                Some("abc"),
+            )
+            .unwrap();
+        assert_eq!("{}", text);
+    }
+
+    #[test]
+    fn upload_too_large() {
+        let _m = mock("POST", "/users/1/avatar").expect(0).create();
+        let mut rest = REST::builder("").max_upload_size(4).build().unwrap();
+        rest.set_base_url(&mockito::server_url());
+        let err = rest
+            .upload(
+                "POST",
+                "users/1/avatar",
+                "avatar",
+                UploadSource::Bytes(b"12345"),
+                "image/png",
+                None,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("larger than the maximum of 4 bytes"));
+        _m.assert();
+    }
+
+    /// Provider that counts its calls, handing out "old" and then "new".
+    #[derive(Clone, Default)]
+    struct CountingProvider {
+        fetched: Arc<AtomicUsize>,
+        refreshed: Arc<AtomicUsize>,
+    }
+
+    impl TokenProvider for CountingProvider {
+        fn access_token(&self) -> Result<String, failure::Error> {
+            self.fetched.fetch_add(1, Ordering::SeqCst);
+            Ok("old".to_owned())
+        }
+
+        fn refresh(&self) -> Result<String, failure::Error> {
+            self.refreshed.fetch_add(1, Ordering::SeqCst);
+            Ok("new".to_owned())
+        }
+    }
+
+    #[test]
+    fn token_provider_attached() {
+        let _m1 = mock("GET", "/users/current")
+            .match_header("authorization", "Bearer old")
+            .with_body("provided")
+            .create();
+        let _m2 = mock("GET", "/users/current")
+            .match_header("authorization", "Bearer mine")
+            .with_body("passed")
+            .create();
+        let provider = CountingProvider::default();
+        let mut rest = mock_rest("");
+        rest.set_token_provider(Box::new(provider.clone()));
+        let text = rest
+            .query("GET", "users/current", None, None, None)
+            .unwrap();
+        assert_eq!("provided", text);
+        let text = rest
+            .query("GET", "users/current", None, None, Some("mine"))
+            .unwrap();
+        assert_eq!("passed", text);
+        assert_eq!(1, provider.fetched.load(Ordering::SeqCst));
+        assert_eq!(0, provider.refreshed.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn token_provider_refresh() {
+        let _m1 = mock("GET", "/users/current")
+            .with_status(401)
+            .expect(1)
+            .create();
+        let _m2 = mock("GET", "/users/current")
+            .match_header("authorization", "Bearer new")
+            .with_body("refreshed")
+            .expect(1)
+            .create();
+        let provider = CountingProvider::default();
+        let mut rest = mock_rest("");
+        rest.set_token_provider(Box::new(provider.clone()));
+        let text = rest
+            .query("GET", "users/current", None, None, None)
+            .unwrap();
+        assert_eq!("refreshed", text);
+        assert_eq!(1, provider.refreshed.load(Ordering::SeqCst));
+        _m1.assert();
+        _m2.assert();
+    }
+
+    #[test]
+    fn token_provider_refresh_once() {
+        let _m = mock("GET", "/users/current")
+            .with_status(401)
+            .expect(2)
+            .create();
+        let provider = CountingProvider::default();
+        let mut rest = mock_rest("");
+        rest.set_token_provider(Box::new(provider.clone()));
+        let err = rest
+            .query("GET", "users/current", None, None, None)
+            .unwrap_err();
+        assert_eq!(Some(401), errors::http_status(&err));
+        assert_eq!(1, provider.refreshed.load(Ordering::SeqCst));
+        _m.assert();
+    }
+
+    #[test]
+    fn download() {
+        let body: String = (0..300_000)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        let _m = mock("GET", "/recordings/1?format=json")
+            .match_header("authorization", "Bearer abc")
+            .with_body(&body)
+            .create();
+        let rest = mock_rest("");
+        let mut out = Vec::new();
+        let mut reports = Vec::new();
+        let mut progress = |bytes| reports.push(bytes);
+        let written = rest
+            .download(
+                HttpMethod::Get,
+                "recordings/1",
+                Some(&[("format", "json")]),
+                Some("abc"),
+                &mut out,
+                Some(&mut progress),
+            )
+            .unwrap();
+        assert_eq!(300_000, written);
+        assert_eq!(body.as_bytes(), &out[..]);
+        assert!(reports.len() > 1);
+        assert_eq!(Some(&300_000), reports.last());
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn download_error_status() {
+        let _m = mock("GET", "/recordings/2")
+            .with_status(404)
+            .with_body(r#"{"statusCode":404,"error":"Not Found","message":"Recording not found."}"#)
+            .create();
+        let rest = mock_rest("");
+        let mut out = Vec::new();
+        let err = rest
+            .download("GET", "recordings/2", None, None, &mut out, None)
+            .unwrap_err();
+        assert_eq!(Some(404), errors::http_status(&err));
+        assert!(out.is_empty());
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<REST>();
+    }
+
+    #[test]
+    fn concurrent_queries() {
+        let _m = mock("GET", Matcher::Regex(r"^/shared/\d+$".to_owned()))
+            .with_body("ok")
+            .expect(8)
+            .create();
+        let rest = mock_rest("");
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let rest = rest.clone();
+                thread::spawn(move || rest.query("GET", &format!("shared/{}", i), None, None, None))
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!("ok", handle.join().unwrap().unwrap());
+        }
+        _m.assert();
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let _m = mock("GET", "/shared")
+            .with_header("x-ratelimit-remaining", "7")
+            .create();
+        let rest = mock_rest("");
+        let clone = rest.clone();
+        clone.query("GET", "shared", None, None, None).unwrap();
+        assert_eq!(Some(7), rest.rate_limit_status().remaining);
+    }
+
+    #[test]
+    fn query_with_headers_invalid_name() {
+        let rest = mock_rest("");
+        let resp =
+            rest.query_with_headers("GET", "somewhere", None, None, None, &[("bad name", "a")]);
+        assert!(resp.is_err());
     }
 
     #[test]
@@ -214,9 +2304,642 @@ mod tests {
         let _m1 = mock("GET", "/somewhere?hello=world")
             .with_body(body)
             .create();
-        let rest = REST::new("");
+        let rest = mock_rest("");
         let resp = rest.query("GET", "somewhere", Some(&[("foo", "bar")]), None, None);
-        assert_eq!(true, resp.is_err());
+        assert!(resp.is_err());
         let _ = resp.unwrap_err();
     }
+
+    #[test]
+    fn builder_defaults() {
+        let rest = REST::new("foobar");
+        assert_eq!(BASE_URL, rest.base_url());
+        let rest = REST::builder("foobar")
+            .base_url("http://localhost:8080/")
+            .build()
+            .unwrap();
+        assert_eq!("http://localhost:8080", rest.base_url());
+    }
+
+    #[test]
+    fn set_base_url() {
+        let mut rest = REST::new("foobar");
+        rest.set_base_url("http://localhost:8080/api/");
+        assert_eq!("http://localhost:8080/api", rest.base_url());
+    }
+
+    #[test]
+    fn builder_user_agent() {
+        let m1 = mock("GET", "/agent")
+            .match_header("user-agent", "my-app/1.0")
+            .with_body("ok")
+            .create();
+        let rest = REST::builder("")
+            .base_url(&mockito::server_url())
+            .user_agent("my-app/1.0")
+            .build()
+            .unwrap();
+        assert_eq!("ok", rest.query("GET", "agent", None, None, None).unwrap());
+        m1.assert();
+    }
+
+    #[test]
+    fn builder_timeout() {
+        // accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let rest = REST::builder("")
+            .base_url(&format!("http://{}", listener.local_addr().unwrap()))
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        assert!(rest.query("GET", "slow", None, None, None).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn builder_proxy() {
+        assert!(REST::builder("")
+            .proxy("http://localhost:8888")
+            .build()
+            .is_ok());
+        assert!(REST::builder("").proxy("not a url").build().is_err());
+    }
+
+    #[test]
+    fn builder_invalid() {
+        assert!(REST::builder("").base_url("not a url").build().is_err());
+        assert!(REST::builder("").user_agent("bad\nagent").build().is_err());
+    }
+
+    #[test]
+    fn ping() {
+        let rest = mock_rest("pingid");
+        let m1 = mock("GET", "/channels?fields=id&limit=1")
+            .match_header("client-id", "pingid")
+            .with_body(r#"[{"id": 1}]"#)
+            .create();
+        rest.ping().unwrap();
+        m1.assert();
+    }
+
+    #[test]
+    fn ping_error() {
+        let rest = mock_rest("pingerr");
+        let _m1 = mock("GET", "/channels?fields=id&limit=1")
+            .match_header("client-id", "pingerr")
+            .with_status(500)
+            .with_header("x-request-id", "req-1")
+            .with_body(r#"{"statusCode": 500, "message": "Oops"}"#)
+            .create();
+        let err = rest.ping().unwrap_err();
+        assert_eq!(
+            BadHttpResponseError {
+                status: 500,
+                endpoint: "channels".to_owned(),
+                body: r#"{"statusCode": 500, "message": "Oops"}"#.to_owned(),
+                request_id: Some("req-1".to_owned()),
+                attempts: 1,
+                api_error: Some(ApiError {
+                    status_code: Some(500),
+                    message: Some("Oops".to_owned()),
+                    ..ApiError::default()
+                }),
+            },
+            err.downcast::<BadHttpResponseError>().unwrap()
+        );
+    }
+
+    /// Create a wrapper that calls the local mock server and retries on 429s.
+    fn retrying_rest() -> REST {
+        REST::builder("")
+            .base_url(&mockito::server_url())
+            .retry_on_rate_limit(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn rate_limit_status() {
+        let rest = mock_rest("");
+        assert_eq!(RateLimit::default(), rest.rate_limit_status());
+        let _m1 = mock("GET", "/limited/status")
+            .with_header("X-RateLimit-Remaining", "99")
+            .with_header("X-RateLimit-Reset", "1566000000000")
+            .with_body("ok")
+            .create();
+        rest.query("GET", "limited/status", None, None, None)
+            .unwrap();
+        assert_eq!(
+            RateLimit {
+                limit: None,
+                remaining: Some(99),
+                reset_at: Some(1_566_000_000_000)
+            },
+            rest.rate_limit_status()
+        );
+    }
+
+    #[test]
+    fn rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, RateLimit::from_headers(&headers));
+
+        headers.insert("X-RateLimit-Limit", HeaderValue::from_static("100"));
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from_static("42"));
+        headers.insert(
+            "X-RateLimit-Reset",
+            HeaderValue::from_static("1566000000000"),
+        );
+        assert_eq!(
+            Some(RateLimit {
+                limit: Some(100),
+                remaining: Some(42),
+                reset_at: Some(1_566_000_000_000)
+            }),
+            RateLimit::from_headers(&headers)
+        );
+
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from_static("lots"));
+        assert_eq!(None, RateLimit::from_headers(&headers).unwrap().remaining);
+    }
+
+    #[test]
+    fn rate_limited() {
+        let m1 = mock("GET", "/limited/once")
+            .with_status(429)
+            .with_header("X-RateLimit-Remaining", "0")
+            .with_header("X-RateLimit-Reset", "1000")
+            .expect(1)
+            .create();
+        let rest = mock_rest("");
+        let err = rest
+            .query("GET", "limited/once", None, None, None)
+            .unwrap_err();
+        assert_eq!(
+            RateLimited {
+                reset_at: Some(1000)
+            },
+            err.downcast::<RateLimited>().unwrap()
+        );
+        assert_eq!(Some(0), rest.rate_limit_status().remaining);
+        m1.assert();
+    }
+
+    #[test]
+    fn rate_limited_retry() {
+        let m1 = mock("GET", "/limited/retry")
+            .with_status(429)
+            .with_header("X-RateLimit-Remaining", "0")
+            .with_header("X-RateLimit-Reset", "1000")
+            .expect(2)
+            .create();
+        let err = retrying_rest()
+            .query("GET", "limited/retry", None, None, None)
+            .unwrap_err();
+        assert!(err.downcast::<RateLimited>().is_ok());
+        m1.assert();
+    }
+
+    #[test]
+    fn rate_limited_retry_succeeds() {
+        // mockito always answers with the last matching mock, so this serves
+        // one 429 and then one 200, and checks that nothing else is sent
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let reset_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+            + 200;
+        let server = thread::spawn(move || {
+            let responses = [
+                format!(
+                    "HTTP/1.1 429 Too Many Requests\r\nX-RateLimit-Remaining: 0\r\n\
+                     X-RateLimit-Reset: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    reset_at
+                ),
+                "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\nsecond"
+                    .to_owned(),
+            ];
+            for response in &responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            listener
+        });
+        let rest = REST::builder("")
+            .base_url(&format!("http://{}", addr))
+            .retry_on_rate_limit(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            "second",
+            rest.query("GET", "limited", None, None, None).unwrap()
+        );
+        let listener = server.join().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.accept().is_err());
+    }
+
+    #[test]
+    fn rate_limited_retry_too_long() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let m1 = mock("GET", "/limited/long")
+            .with_status(429)
+            .with_header("X-RateLimit-Reset", &(now + 600_000).to_string())
+            .expect(1)
+            .create();
+        let start = Instant::now();
+        assert!(retrying_rest()
+            .query("GET", "limited/long", None, None, None)
+            .is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        m1.assert();
+    }
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::builder()
+            .base_delay(Duration::from_millis(10))
+            .build()
+    }
+
+    #[test]
+    fn retry_transient_status() {
+        // mockito can't send different responses to the same path, so this
+        // serves 503 twice and then 200
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            for status in &[
+                "503 Service Unavailable",
+                "503 Service Unavailable",
+                "200 OK",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        let rest = REST::builder("")
+            .base_url(&format!("http://{}", addr))
+            .retry_policy(retry_policy())
+            .build()
+            .unwrap();
+        assert_eq!("ok", rest.query("GET", "flaky", None, None, None).unwrap());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn retry_gives_up() {
+        let m1 = mock("GET", "/retry/down")
+            .with_status(503)
+            .expect(3)
+            .create();
+        let rest = REST::builder("")
+            .base_url(&mockito::server_url())
+            .retry_policy(retry_policy())
+            .build()
+            .unwrap();
+        let err = rest
+            .query("GET", "retry/down", None, None, None)
+            .unwrap_err()
+            .downcast::<BadHttpResponseError>()
+            .unwrap();
+        assert_eq!(503, err.status());
+        assert_eq!(3, err.attempts);
+        m1.assert();
+    }
+
+    #[test]
+    fn retry_skips_post() {
+        let m1 = mock("POST", "/retry/post")
+            .with_status(503)
+            .expect(1)
+            .create();
+        let rest = REST::builder("")
+            .base_url(&mockito::server_url())
+            .retry_policy(retry_policy())
+            .build()
+            .unwrap();
+        let err = rest
+            .query("POST", "retry/post", None, Some("{}"), None)
+            .unwrap_err()
+            .downcast::<BadHttpResponseError>()
+            .unwrap();
+        assert_eq!(1, err.attempts);
+        m1.assert();
+    }
+
+    #[test]
+    fn retry_idempotent_post() {
+        let m1 = mock("POST", "/retry/idempotent")
+            .with_status(502)
+            .expect(2)
+            .create();
+        let rest = REST::builder("")
+            .base_url(&mockito::server_url())
+            .retry_policy(RetryPolicy {
+                max_attempts: 2,
+                ..retry_policy()
+            })
+            .build()
+            .unwrap();
+        assert!(rest
+            .query_idempotent("POST", "retry/idempotent", None, Some("{}"), None)
+            .is_err());
+        m1.assert();
+    }
+
+    #[test]
+    fn retry_connection_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let rest = REST::builder("")
+            .base_url(&format!("http://{}", addr))
+            .retry_policy(retry_policy())
+            .build()
+            .unwrap();
+        let err = rest.query("GET", "refused", None, None, None).unwrap_err();
+        assert!(err.to_string().contains("after 3 attempts"));
+    }
+
+    #[test]
+    fn csrf_token() {
+        let m1 = mock("POST", "/csrf/needed")
+            .with_status(461)
+            .with_header("X-CSRF-Token", "abc123")
+            .expect(1)
+            .create();
+        let m2 = mock("POST", "/csrf/needed")
+            .match_header("x-csrf-token", "abc123")
+            .with_body("ok")
+            .expect(1)
+            .create();
+        let rest = mock_rest("");
+        assert_eq!(None, rest.csrf_token());
+        let text = rest
+            .query("POST", "csrf/needed", None, Some("{}"), None)
+            .unwrap();
+        assert_eq!("ok", text);
+        assert_eq!(Some("abc123".to_owned()), rest.csrf_token());
+        m1.assert();
+        m2.assert();
+
+        // the token keeps being sent
+        let m3 = mock("GET", "/csrf/later")
+            .match_header("x-csrf-token", "abc123")
+            .with_body("ok")
+            .create();
+        assert!(rest.query("GET", "csrf/later", None, None, None).is_ok());
+        m3.assert();
+    }
+
+    #[test]
+    fn csrf_without_token() {
+        let _m1 = mock("GET", "/csrf/missing").with_status(461).create();
+        let err = mock_rest("")
+            .query("GET", "csrf/missing", None, None, None)
+            .unwrap_err();
+        assert_eq!(Some(461), errors::http_status(&err));
+    }
+
+    #[test]
+    fn get_followers() {
+        let m1 = mock(
+            "GET",
+            Matcher::Regex(r"^/channels/1234/follow\?limit=2&order=".to_owned()),
+        )
+        .with_body(
+            r#"[
+                {"id": 1, "username": "first", "level": 5, "followed": {"createdAt": "2019-07-01T12:00:00.000Z", "user": 1}},
+                {"id": 2, "username": "second", "level": 9, "followed": {"createdAt": "2019-06-30T08:30:00.000Z", "user": 2}}
+            ]"#,
+        )
+        .create();
+        let followers = mock_rest("").get_followers(1234, 2, None).unwrap();
+        assert_eq!(
+            vec![
+                Follower {
+                    id: 1,
+                    username: "first".to_owned(),
+                    followed: Follow {
+                        created_at: "2019-07-01T12:00:00.000Z".to_owned()
+                    },
+                },
+                Follower {
+                    id: 2,
+                    username: "second".to_owned(),
+                    followed: Follow {
+                        created_at: "2019-06-30T08:30:00.000Z".to_owned()
+                    },
+                },
+            ],
+            followers
+        );
+        m1.assert();
+    }
+
+    #[test]
+    fn get_current_channel_id() {
+        let _m1 = mock("GET", "/users/current?fields=channel")
+            .match_header("authorization", "Bearer abc")
+            .with_body(r#"{"channel":{"id":1234,"token":"someone","online":false}}"#)
+            .create();
+        let _m2 = mock("GET", "/users/current?fields=channel")
+            .match_header("authorization", "Bearer def")
+            .with_body(r#"{"id":5}"#)
+            .create();
+        let rest = mock_rest("");
+        assert_eq!(1234, rest.get_current_channel_id("abc").unwrap());
+        assert!(rest.get_current_channel_id("def").is_err());
+    }
+
+    #[test]
+    fn query_absolute_url() {
+        let m1 = mock("GET", "/absolute/next?page=2&limit=5")
+            .match_header("client-id", "abc")
+            .with_body("page 2")
+            .create();
+        let rest = mock_rest("abc");
+        let url = format!("{}/absolute/next?page=2", mockito::server_url());
+        let text = rest
+            .query("GET", &url, Some(&[("limit", "5")]), None, None)
+            .unwrap();
+        assert_eq!("page 2", text);
+        m1.assert();
+    }
+
+    #[test]
+    fn query_foreign_host() {
+        let rest = mock_rest("abc");
+        let err = rest
+            .query("GET", "https://example.com/steal", None, None, None)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "Refusing to send a call to 'https://example.com/steal', which is not on the API's host",
+            err
+        );
+        let port_changed = mockito::server_url().replace("127.0.0.1:", "127.0.0.1:1");
+        assert!(rest.query("GET", &port_changed, None, None, None).is_err());
+
+        let rest = REST::new("abc");
+        let err = rest
+            .query("GET", "http://mixer.com:443/api/v1/steal", None, None, None)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "Refusing to send a call to 'http://mixer.com:443/api/v1/steal' over http, \
+             as the API is on https",
+            err
+        );
+
+        let m1 = mock("GET", "/foreign").with_body("ok").create();
+        let rest = REST::builder("abc")
+            .base_url("http://localhost:1")
+            .allow_foreign_hosts(true)
+            .build()
+            .unwrap();
+        let url = format!("{}/foreign", mockito::server_url());
+        assert_eq!("ok", rest.query("GET", &url, None, None, None).unwrap());
+        m1.assert();
+    }
+
+    #[test]
+    fn get_channel_emotes() {
+        let _m1 = mock("GET", "/channels/1234/emoticons")
+            .with_body(
+                r#"[
+                    {
+                        "channelId": 1234,
+                        "url": "https://uploads.mixer.com/emoticons/pack1.png",
+                        "emoticons": {
+                            "wave": {"x": 24, "y": 0, "width": 24, "height": 24},
+                            "hype": {"x": 0, "y": 0, "width": 24, "height": 24}
+                        }
+                    },
+                    {
+                        "channelId": 1234,
+                        "url": "https://uploads.mixer.com/emoticons/pack2.png",
+                        "emoticons": {
+                            "gg": {"x": 0, "y": 28, "width": 28, "height": 28}
+                        }
+                    }
+                ]"#,
+            )
+            .create();
+        let emotes = mock_rest("").get_channel_emotes(1234).unwrap();
+        let names: Vec<&str> = emotes.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["hype", "wave", "gg"], names);
+        assert_eq!(
+            Emote {
+                name: "wave".to_owned(),
+                url: "https://uploads.mixer.com/emoticons/pack1.png".to_owned(),
+                x: 24,
+                y: 0,
+                width: 24,
+                height: 24,
+            },
+            emotes[1]
+        );
+        assert_eq!(
+            "https://uploads.mixer.com/emoticons/pack2.png",
+            emotes[2].url
+        );
+    }
+
+    #[test]
+    fn query_full() {
+        let _m1 = mock("GET", "/full/ok")
+            .with_header("x-total-count", "42")
+            .with_header("x-request-id", "req-2")
+            .with_body("[]")
+            .create();
+        let resp = mock_rest("")
+            .query_full("GET", "full/ok", None, None, None)
+            .unwrap();
+        assert_eq!(200, resp.status);
+        assert_eq!("42", resp.headers["x-total-count"]);
+        assert_eq!("req-2", resp.headers["x-request-id"]);
+        assert_eq!("[]", resp.ensure_success().unwrap().body);
+    }
+
+    #[test]
+    fn query_full_error_status() {
+        let _m1 = mock("GET", "/full/missing")
+            .with_status(404)
+            .with_header("x-request-id", "req-3")
+            .with_body(r#"{"statusCode": 404, "message": "Not Found"}"#)
+            .create();
+        let resp = mock_rest("")
+            .query_full("GET", "full/missing", None, None, None)
+            .unwrap();
+        assert_eq!(404, resp.status);
+        assert!(!resp.is_success());
+        assert!(resp.body.contains("Not Found"));
+        let err = resp
+            .ensure_success()
+            .unwrap_err()
+            .downcast::<BadHttpResponseError>()
+            .unwrap();
+        assert_eq!(404, err.status());
+        assert_eq!("full/missing", err.endpoint);
+        assert_eq!(Some("req-3".to_owned()), err.request_id);
+    }
+
+    #[test]
+    fn etag_cache() {
+        let m1 = mock("GET", "/cached/channel")
+            .with_header("ETag", r#""abc""#)
+            .with_body(r#"{"id": 1}"#)
+            .expect(1)
+            .create();
+        let m2 = mock("GET", "/cached/channel")
+            .match_header("if-none-match", r#""abc""#)
+            .with_status(304)
+            .expect(2)
+            .create();
+        let rest = REST::builder("")
+            .base_url(&mockito::server_url())
+            .etag_cache(10)
+            .build()
+            .unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                r#"{"id": 1}"#,
+                rest.query("GET", "cached/channel", None, None, None)
+                    .unwrap()
+            );
+        }
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn etag_cache_disabled() {
+        let m1 = mock("GET", "/cached/off")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("ETag", r#""abc""#)
+            .with_body("ok")
+            .expect(2)
+            .create();
+        let rest = mock_rest("");
+        for _ in 0..2 {
+            assert!(rest.query("GET", "cached/off", None, None, None).is_ok());
+        }
+        m1.assert();
+    }
 }
@@ -0,0 +1,521 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A Mixer user, from `users/current` and `users/{id}`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    /// User's id
+    pub id: usize,
+    /// User's username
+    pub username: String,
+    /// Whether the user's email address is verified
+    pub verified: bool,
+    /// Experience points the user has earned
+    pub experience: u64,
+    /// Level from the user's experience
+    pub level: u32,
+    /// Links to the user's other accounts
+    #[serde(default)]
+    pub social: Social,
+    /// User's channel, which every user has
+    pub channel: UserChannel,
+}
+
+impl User {
+    /// Id of the user's channel, e.g. for connecting to its chat.
+    pub fn channel_id(&self) -> usize {
+        self.channel.id
+    }
+}
+
+/// A channel, from `channels/{id}`.
+///
+/// Only the id is always sent; the other fields are `None` if they were left
+/// out, e.g. by a `fields` param.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    /// Channel's id
+    pub id: usize,
+    /// Channel's name, which is its owner's username
+    pub token: Option<String>,
+    /// Title of the stream
+    pub name: Option<String>,
+    /// Audience rating
+    pub audience: Option<Audience>,
+    /// Number of people watching
+    pub viewers_current: Option<u64>,
+    /// Whether the channel is streaming
+    pub online: Option<bool>,
+    /// Id of the game being played
+    pub type_id: Option<usize>,
+    /// Whether the channel is a Mixer partner
+    pub partnered: Option<bool>,
+    /// Id of the channel's owner
+    pub user_id: Option<usize>,
+    /// Id of the channel being hosted, if any
+    pub hostee_id: Option<usize>,
+    /// When the channel was last updated, as an ISO 8601 timestamp
+    pub updated_at: Option<String>,
+}
+
+/// Audience rating of a channel.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum Audience {
+    /// Suitable for everyone
+    #[serde(rename = "family")]
+    Family,
+    /// Suitable for teenagers
+    #[serde(rename = "teen")]
+    Teen,
+    /// Adults only
+    #[serde(rename = "18+")]
+    Adult,
+    /// A rating that isn't one of the above, e.g. one Mixer added later;
+    /// Mixer won't accept it in a `ChannelUpdate`
+    #[serde(other)]
+    Unknown,
+}
+
+/// Changes to make to a channel with `ChannelsHelper::update`; only the
+/// fields that are set are sent.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::rest::models::{Audience, ChannelUpdate};
+/// let update = ChannelUpdate::new()
+///     .name("Speedrunning all day")
+///     .audience(Audience::Teen);
+/// assert_eq!(
+///     r#"{"name":"Speedrunning all day","audience":"teen"}"#,
+///     serde_json::to_string(&update).unwrap()
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelUpdate {
+    /// New title of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Id of the new game
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_id: Option<usize>,
+    /// New audience rating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Audience>,
+}
+
+impl ChannelUpdate {
+    /// Create an update that changes nothing.
+    pub fn new() -> Self {
+        ChannelUpdate::default()
+    }
+
+    /// Change the title of the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - new title
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Change the game being played.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_id` - id of the game
+    pub fn type_id(mut self, type_id: usize) -> Self {
+        self.type_id = Some(type_id);
+        self
+    }
+
+    /// Change the audience rating.
+    ///
+    /// # Arguments
+    ///
+    /// * `audience` - new rating
+    pub fn audience(mut self, audience: Audience) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+}
+
+/// Summary of a channel, e.g. one that a user follows.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelSummary {
+    /// Channel's id
+    pub id: usize,
+    /// Channel's name, which is its owner's username
+    pub token: String,
+    /// Title of the stream
+    pub name: String,
+    /// Number of views the channel has had
+    pub viewers_total: u64,
+    /// Whether the channel is streaming
+    pub online: bool,
+}
+
+/// A user found by `UsersHelper::search`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSearchResult {
+    /// User's id
+    pub id: usize,
+    /// User's username
+    pub username: String,
+    /// URL of the user's avatar, if they uploaded one
+    pub avatar_url: Option<String>,
+    /// User's channel
+    pub channel: UserChannel,
+}
+
+/// A notification sent to a user, from `users/{id}/notifications`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Notification's id, for marking it as read
+    pub id: String,
+    /// What caused the notification, e.g. "followed", "hosted" or "subscribed"
+    #[serde(rename = "trigger", alias = "type")]
+    pub kind: String,
+    /// When the notification was sent, as an ISO 8601 timestamp
+    pub sent_at: String,
+    /// Details of the notification, which differ between kinds
+    #[serde(default)]
+    pub payload: Value,
+    /// Whether the user has read the notification
+    #[serde(default)]
+    pub read: bool,
+}
+
+/// Links to a user's accounts on other sites; each is only set if the
+/// user added it.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Social {
+    /// Twitter profile URL
+    pub twitter: Option<String>,
+    /// Facebook profile URL
+    pub facebook: Option<String>,
+    /// YouTube channel URL
+    pub youtube: Option<String>,
+    /// Player.me profile URL
+    pub player: Option<String>,
+    /// Discord username
+    pub discord: Option<String>,
+    /// Names of the accounts that Mixer verified, e.g. "twitter"
+    #[serde(default)]
+    pub verified: Vec<String>,
+}
+
+/// The channel part of a `User`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct UserChannel {
+    /// Channel's id
+    pub id: usize,
+    /// Channel's name, which is the user's username
+    pub token: String,
+}
+
+/// A user following a channel, from `channels/{id}/follow`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Follower {
+    /// User's id
+    pub id: usize,
+    /// User's username
+    pub username: String,
+    /// Details of the follow
+    pub followed: Follow,
+}
+
+/// When a user followed a channel.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Follow {
+    /// When the follow happened, as an ISO 8601 timestamp
+    pub created_at: String,
+}
+
+/// A channel emote, with where to find it in its sprite sheet.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Emote {
+    /// Text that's replaced by the emote in chat messages
+    pub name: String,
+    /// URL of the sprite sheet image the emote is in
+    pub url: String,
+    /// Horizontal position of the emote in the image, in pixels
+    pub x: u32,
+    /// Vertical position of the emote in the image, in pixels
+    pub y: u32,
+    /// Width of the emote, in pixels
+    pub width: u32,
+    /// Height of the emote, in pixels
+    pub height: u32,
+}
+
+/// Sprite sheet of emotes, from `channels/{id}/emoticons`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct EmoticonPack {
+    /// URL of the sprite sheet image
+    pub url: String,
+    /// Where each emote is in the image, by the text it replaces
+    pub emoticons: BTreeMap<String, EmotePosition>,
+}
+
+/// Position of an emote in its sprite sheet.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct EmotePosition {
+    /// Horizontal position in the image, in pixels
+    pub x: u32,
+    /// Vertical position in the image, in pixels
+    pub y: u32,
+    /// Width of the emote, in pixels
+    pub width: u32,
+    /// Height of the emote, in pixels
+    pub height: u32,
+}
+
+impl EmoticonPack {
+    /// Split the pack into its emotes, ordered by name.
+    pub fn into_emotes(self) -> Vec<Emote> {
+        let url = self.url;
+        self.emoticons
+            .into_iter()
+            .map(|(name, position)| Emote {
+                name,
+                url: url.clone(),
+                x: position.x,
+                y: position.y,
+                width: position.width,
+                height: position.height,
+            })
+            .collect()
+    }
+}
+
+/// A webhook registered for an OAuth app, from `hooks`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisteredWebHook {
+    /// Webhook's id
+    pub id: String,
+    /// Events the webhook receives
+    pub events: Vec<String>,
+    /// Kind of webhook, which is "web" for ones called over HTTP
+    pub kind: String,
+    /// URL the events are sent to
+    pub url: String,
+    /// Whether events are being sent to the webhook
+    #[serde(default)]
+    pub is_active: bool,
+    /// Why Mixer stopped sending events to the webhook, if it did
+    pub deactivation_reason: Option<String>,
+}
+
+/// A chat server that can be connected to, from `chats/{id}`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ChatServer {
+    /// Websocket URL of the server
+    pub endpoint: String,
+    /// Whether Mixer reported that chat is shedding load, in which case
+    /// connections may be rejected and should be retried later
+    pub load_shed: bool,
+}
+
+/// Response from `chats/{id}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ChatsResponse {
+    pub endpoints: Vec<String>,
+    #[serde(default)]
+    pub is_load_shed: bool,
+}
+
+impl ChatsResponse {
+    /// Split the response into its servers, in the order Mixer listed them.
+    pub fn into_servers(self) -> Vec<ChatServer> {
+        let load_shed = self.is_load_shed;
+        self.endpoints
+            .into_iter()
+            .map(|endpoint| ChatServer {
+                endpoint,
+                load_shed,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Audience, Channel, EmotePosition, EmoticonPack, Social, User, UserChannel};
+
+    #[test]
+    fn user() {
+        let user: User = serde_json::from_str(
+            r#"{
+                "level": 47,
+                "social": {
+                    "twitter": "https://twitter.com/someone",
+                    "discord": "someone#1234",
+                    "verified": ["twitter"]
+                },
+                "id": 314,
+                "username": "someone",
+                "verified": true,
+                "experience": 81072,
+                "sparks": 1243,
+                "avatarUrl": "https://uploads.mixer.com/avatar/314.jpg",
+                "bio": null,
+                "primaryTeam": null,
+                "createdAt": "2016-01-20T14:01:00.000Z",
+                "channel": {
+                    "id": 271,
+                    "userId": 314,
+                    "token": "someone",
+                    "online": false,
+                    "viewersTotal": 5120
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            User {
+                id: 314,
+                username: "someone".to_owned(),
+                verified: true,
+                experience: 81072,
+                level: 47,
+                social: Social {
+                    twitter: Some("https://twitter.com/someone".to_owned()),
+                    discord: Some("someone#1234".to_owned()),
+                    verified: vec!["twitter".to_owned()],
+                    ..Social::default()
+                },
+                channel: UserChannel {
+                    id: 271,
+                    token: "someone".to_owned(),
+                },
+            },
+            user
+        );
+        assert_eq!(271, user.channel_id());
+    }
+
+    #[test]
+    fn channel() {
+        let channel: Channel = serde_json::from_str(
+            r#"{
+                "featured": false,
+                "id": 271,
+                "userId": 314,
+                "token": "someone",
+                "online": true,
+                "featureLevel": 0,
+                "partnered": true,
+                "transcodingProfileId": 1,
+                "suspended": false,
+                "name": "Speedrunning all day",
+                "audience": "teen",
+                "viewersTotal": 51200,
+                "viewersCurrent": 87,
+                "numFollowers": 3400,
+                "description": "<p>Welcome!</p>",
+                "typeId": 1234,
+                "interactive": false,
+                "interactiveGameId": null,
+                "ftl": 0,
+                "hasVod": true,
+                "languageId": "en",
+                "coverId": null,
+                "thumbnailId": null,
+                "badgeId": null,
+                "bannerUrl": null,
+                "hosteeId": null,
+                "hasTranscodes": true,
+                "vodsEnabled": true,
+                "costreamId": null,
+                "createdAt": "2016-01-20T14:01:00.000Z",
+                "updatedAt": "2019-06-01T12:00:00.000Z",
+                "deletedAt": null,
+                "type": {"id": 1234, "name": "Some Game"},
+                "user": {"id": 314, "username": "someone"}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Channel {
+                id: 271,
+                token: Some("someone".to_owned()),
+                name: Some("Speedrunning all day".to_owned()),
+                audience: Some(Audience::Teen),
+                viewers_current: Some(87),
+                online: Some(true),
+                type_id: Some(1234),
+                partnered: Some(true),
+                user_id: Some(314),
+                hostee_id: None,
+                updated_at: Some("2019-06-01T12:00:00.000Z".to_owned()),
+            },
+            channel
+        );
+
+        let channel: Channel = serde_json::from_str(r#"{"id": 271, "online": false}"#).unwrap();
+        assert_eq!(Some(false), channel.online);
+        assert_eq!(None, channel.token);
+
+        let channel: Channel =
+            serde_json::from_str(r#"{"id": 271, "audience": "everyone"}"#).unwrap();
+        assert_eq!(Some(Audience::Unknown), channel.audience);
+    }
+
+    #[test]
+    fn emoticon_packs() {
+        let packs: Vec<EmoticonPack> = serde_json::from_str(
+            r#"[
+                {
+                    "channelId": 271,
+                    "id": "pack1",
+                    "url": "https://uploads.mixer.com/emoticons/pack1.png",
+                    "emoticons": {
+                        ":wave": {"x": 0, "y": 0, "width": 24, "height": 24},
+                        ":cheer": {"x": 24, "y": 0, "width": 24, "height": 24}
+                    }
+                },
+                {
+                    "url": "https://uploads.mixer.com/emoticons/pack2.png",
+                    "emoticons": {
+                        ":hype": {"x": 0, "y": 28, "width": 28, "height": 28}
+                    }
+                }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(2, packs.len());
+        assert_eq!(
+            "https://uploads.mixer.com/emoticons/pack1.png",
+            packs[0].url
+        );
+        assert_eq!(
+            Some(&EmotePosition {
+                x: 24,
+                y: 0,
+                width: 24,
+                height: 24
+            }),
+            packs[0].emoticons.get(":cheer")
+        );
+        assert_eq!(28, packs[1].emoticons[":hype"].y);
+        let names: Vec<String> = packs[0]
+            .clone()
+            .into_emotes()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(vec![":cheer", ":wave"], names);
+    }
+}
@@ -0,0 +1,260 @@
+//! Builder for the filtering, field selection, sorting, and paging params
+//! that list endpoints accept.
+
+use std::fmt;
+
+/// Comparison for a `where` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterOp {
+    /// `eq` - equal to
+    Eq,
+    /// `ne` - not equal to
+    Ne,
+    /// `lt` - less than
+    Lt,
+    /// `lte` - less than or equal to
+    Lte,
+    /// `gt` - greater than
+    Gt,
+    /// `gte` - greater than or equal to
+    Gte,
+}
+
+impl FilterOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Lt => "lt",
+            FilterOp::Lte => "lte",
+            FilterOp::Gt => "gt",
+            FilterOp::Gte => "gte",
+        }
+    }
+}
+
+/// Direction to sort results in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    /// Smallest first
+    Asc,
+    /// Largest first
+    Desc,
+}
+
+impl SortOrder {
+    fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Builder for the `where`, `fields`, `order`, `limit`, and `page` params of
+/// list endpoints.
+///
+/// Filters and sort orders are combined in the order they're added, so one
+/// field can be filtered more than once, e.g. for a range. Values are sent as
+/// given; the params are URL-encoded when the call is made.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::rest::{FilterOp, QueryBuilder, SortOrder};
+/// let query = QueryBuilder::new()
+///     .filter("viewersCurrent", FilterOp::Gte, 10)
+///     .fields(&["id", "token"])
+///     .order("viewersCurrent", SortOrder::Desc)
+///     .limit(20);
+/// assert_eq!(
+///     vec![
+///         ("where", "viewersCurrent:gte:10"),
+///         ("fields", "id,token"),
+///         ("order", "viewersCurrent:DESC"),
+///         ("limit", "20"),
+///     ],
+///     query.params()
+/// );
+/// ```
+///
+/// ```rust,no_run
+/// # use mixer_wrappers::{rest::{FilterOp, QueryBuilder}, REST};
+/// # use serde_json::Value;
+/// let api = REST::new("");
+/// let query = QueryBuilder::new().filter("online", FilterOp::Eq, true);
+/// let channels: Value = api
+///     .query_json("GET", "channels", Some(&query.params()), None, None)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryBuilder {
+    params: Vec<(&'static str, String)>,
+}
+
+impl QueryBuilder {
+    /// Create a builder with no params.
+    pub fn new() -> Self {
+        QueryBuilder::default()
+    }
+
+    /// Only return results where the field compares to the value.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - field to compare, e.g. `viewersCurrent`
+    /// * `op` - comparison
+    /// * `value` - value to compare against
+    pub fn filter(self, field: &str, op: FilterOp, value: impl fmt::Display) -> Self {
+        self.append("where", format!("{}:{}:{}", field, op.as_str(), value))
+    }
+
+    /// Only return results where the field is one of the values.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - field to compare
+    /// * `values` - allowed values
+    pub fn filter_in<T: fmt::Display>(self, field: &str, values: &[T]) -> Self {
+        self.append("where", format!("{}:in:{}", field, join(values, ";")))
+    }
+
+    /// Only return results where the field is none of the values.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - field to compare
+    /// * `values` - excluded values
+    pub fn filter_not_in<T: fmt::Display>(self, field: &str, values: &[T]) -> Self {
+        self.append("where", format!("{}:notin:{}", field, join(values, ";")))
+    }
+
+    /// Only include these fields in each result.
+    ///
+    /// # Arguments
+    ///
+    /// * `fields` - names of the fields
+    pub fn fields(self, fields: &[&str]) -> Self {
+        self.append("fields", fields.join(","))
+    }
+
+    /// Sort the results by the field; later orders break ties in earlier ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - field to sort by
+    /// * `order` - direction
+    pub fn order(self, field: &str, order: SortOrder) -> Self {
+        self.append("order", format!("{}:{}", field, order.as_str()))
+    }
+
+    /// Set the number of results per page.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - results per page
+    pub fn limit(self, limit: usize) -> Self {
+        self.set("limit", limit.to_string())
+    }
+
+    /// Set the page of results to return, starting from 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - page number
+    pub fn page(self, page: usize) -> Self {
+        self.set("page", page.to_string())
+    }
+
+    /// Params to pass to `REST::query` and the other query functions.
+    pub fn params(&self) -> Vec<(&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect()
+    }
+
+    /// Add to a comma-separated param, creating it if it isn't set.
+    fn append(mut self, name: &'static str, value: String) -> Self {
+        match self.params.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => {
+                existing.push(',');
+                existing.push_str(&value);
+            }
+            None => self.params.push((name, value)),
+        }
+        self
+    }
+
+    /// Set a param, replacing it if it's already set.
+    fn set(mut self, name: &'static str, value: String) -> Self {
+        match self.params.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.params.push((name, value)),
+        }
+        self
+    }
+}
+
+fn join<T: fmt::Display>(values: &[T], separator: &str) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterOp, QueryBuilder, SortOrder};
+    use url::form_urlencoded::Serializer;
+
+    fn encode(query: &QueryBuilder) -> String {
+        Serializer::new(String::new())
+            .extend_pairs(query.params())
+            .finish()
+    }
+
+    #[test]
+    fn empty() {
+        assert!(QueryBuilder::new().params().is_empty());
+    }
+
+    #[test]
+    fn filters_combined() {
+        let query = QueryBuilder::new()
+            .filter("viewersCurrent", FilterOp::Gte, 10)
+            .filter("viewersCurrent", FilterOp::Lt, 100)
+            .filter_in("languageId", &["en", "de"])
+            .filter_not_in("id", &[1, 2]);
+        assert_eq!(
+            vec![(
+                "where",
+                "viewersCurrent:gte:10,viewersCurrent:lt:100,languageId:in:en;de,id:notin:1;2"
+            )],
+            query.params()
+        );
+        assert_eq!(
+            "where=viewersCurrent%3Agte%3A10%2CviewersCurrent%3Alt%3A100\
+             %2ClanguageId%3Ain%3Aen%3Bde%2Cid%3Anotin%3A1%3B2",
+            encode(&query)
+        );
+    }
+
+    #[test]
+    fn all_params() {
+        let query = QueryBuilder::new()
+            .limit(10)
+            .order("viewersCurrent", SortOrder::Desc)
+            .fields(&["id", "token"])
+            .page(1)
+            .order("id", SortOrder::Asc)
+            .filter("online", FilterOp::Eq, true)
+            .page(2);
+        assert_eq!(
+            "limit=10&order=viewersCurrent%3ADESC%2Cid%3AASC&fields=id%2Ctoken&page=2\
+             &where=online%3Aeq%3Atrue",
+            encode(&query)
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Source of OAuth access tokens for calls that don't pass one.
+
+use failure::Error;
+
+/// Source of the OAuth access token that `REST` sends with calls that don't
+/// pass one, set with `REST::set_token_provider`.
+///
+/// When a call with a provided token gets a 401 response, `refresh` is called
+/// and the call is sent once more with the new token.
+///
+/// `oauth::RefreshingTokenProvider` refreshes a `MixerToken` through Mixer's
+/// token endpoint.
+///
+/// # Examples
+///
+/// ```rust
+/// # use failure::Error;
+/// # use mixer_wrappers::{rest::TokenProvider, REST};
+/// struct FixedToken(String);
+///
+/// impl TokenProvider for FixedToken {
+///     fn access_token(&self) -> Result<String, Error> {
+///         Ok(self.0.clone())
+///     }
+/// }
+///
+/// let mut api = REST::new("");
+/// api.set_token_provider(Box::new(FixedToken("abc".to_owned())));
+/// ```
+pub trait TokenProvider {
+    /// Current access token, refreshing it first if it's about to expire.
+    fn access_token(&self) -> Result<String, Error>;
+
+    /// Get a new access token after the current one was rejected; defaults
+    /// to calling `access_token` again.
+    fn refresh(&self) -> Result<String, Error> {
+        self.access_token()
+    }
+}
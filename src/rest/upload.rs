@@ -0,0 +1,139 @@
+//! Files sent to the REST API as multipart form data.
+
+use failure::{format_err, Error};
+use reqwest::multipart::{Form, Part};
+use std::{fs, path::Path};
+
+/// Default largest file that `REST::upload` sends, in bytes.
+pub(crate) const MAX_UPLOAD_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Contents of a file to upload with `REST::upload`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use mixer_wrappers::rest::UploadSource;
+/// # use std::path::Path;
+/// let from_disk = UploadSource::Path(Path::new("avatar.png"));
+/// let in_memory = UploadSource::Bytes(b"not really a png");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum UploadSource<'a> {
+    /// File on disk, read when the call is made; its name is sent as the file name
+    Path(&'a Path),
+    /// Contents already in memory; the field name is sent as the file name
+    Bytes(&'a [u8]),
+}
+
+/// File read into memory, ready to be sent as a form.
+#[derive(Debug)]
+pub(crate) struct FilePart {
+    field_name: String,
+    file_name: String,
+    mime: String,
+    bytes: Vec<u8>,
+}
+
+impl FilePart {
+    /// Read the file, returning an error if it's larger than the limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `field_name` - name of the form field
+    /// * `source` - file contents
+    /// * `mime` - MIME type of the file, e.g. `image/png`
+    /// * `max_size` - largest allowed size, in bytes
+    pub fn read(
+        field_name: &str,
+        source: UploadSource,
+        mime: &str,
+        max_size: u64,
+    ) -> Result<FilePart, Error> {
+        let check_size = |size: u64| {
+            if size > max_size {
+                Err(format_err!(
+                    "Upload of {} bytes is larger than the maximum of {} bytes",
+                    size,
+                    max_size
+                ))
+            } else {
+                Ok(())
+            }
+        };
+        let (file_name, bytes) = match source {
+            UploadSource::Path(path) => {
+                let read_error = |e| format_err!("Could not read '{}': {}", path.display(), e);
+                check_size(fs::metadata(path).map_err(read_error)?.len())?;
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| field_name.to_owned());
+                (file_name, fs::read(path).map_err(read_error)?)
+            }
+            UploadSource::Bytes(bytes) => {
+                check_size(bytes.len() as u64)?;
+                (field_name.to_owned(), bytes.to_vec())
+            }
+        };
+        Ok(FilePart {
+            field_name: field_name.to_owned(),
+            file_name,
+            mime: mime.to_owned(),
+            bytes,
+        })
+    }
+
+    /// Build the form to send, which can be done again for each attempt.
+    pub fn form(&self) -> Result<Form, Error> {
+        let part = Part::bytes(self.bytes.clone())
+            .file_name(self.file_name.clone())
+            .mime_str(&self.mime)
+            .map_err(|e| format_err!("Invalid MIME type '{}': {}", self.mime, e))?;
+        Ok(Form::new().part(self.field_name.clone(), part))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilePart, UploadSource};
+    use std::{env, fs, path::Path};
+
+    #[test]
+    fn read_bytes() {
+        let part = FilePart::read("avatar", UploadSource::Bytes(b"abc"), "image/png", 3).unwrap();
+        assert_eq!("avatar", part.file_name);
+        assert_eq!(b"abc".to_vec(), part.bytes);
+        assert!(part.form().is_ok());
+
+        let err = FilePart::read("avatar", UploadSource::Bytes(b"abcd"), "image/png", 3)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "Upload of 4 bytes is larger than the maximum of 3 bytes",
+            err
+        );
+    }
+
+    #[test]
+    fn read_path() {
+        let path = env::temp_dir().join("mixer_wrappers_upload_read_path.png");
+        fs::write(&path, b"abcdef").unwrap();
+        let part = FilePart::read("banner", UploadSource::Path(&path), "image/png", 6).unwrap();
+        assert_eq!("mixer_wrappers_upload_read_path.png", part.file_name);
+        assert_eq!(b"abcdef".to_vec(), part.bytes);
+        assert!(FilePart::read("banner", UploadSource::Path(&path), "image/png", 5).is_err());
+        fs::remove_file(&path).unwrap();
+
+        let missing = Path::new("/does/not/exist.png");
+        let err = FilePart::read("banner", UploadSource::Path(missing), "image/png", 5)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("/does/not/exist.png"));
+    }
+
+    #[test]
+    fn invalid_mime() {
+        let part = FilePart::read("avatar", UploadSource::Bytes(b"abc"), "not a mime", 3).unwrap();
+        assert!(part.form().is_err());
+    }
+}
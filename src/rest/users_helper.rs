@@ -0,0 +1,811 @@
+//! Helper for user-related REST API endpoints.
+
+use super::{
+    iter_pages,
+    models::{ChannelSummary, Notification, User, UserSearchResult},
+    HttpMethod, UploadSource, PAGE_SIZE, REST,
+};
+use failure::{format_err, Error};
+use log::debug;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// MIME types that avatars can be uploaded as.
+const AVATAR_MIME_TYPES: [&str; 3] = ["image/png", "image/jpeg", "image/gif"];
+/// Largest avatar that can be uploaded, in bytes.
+const MAX_AVATAR_SIZE: usize = 5 * 1024 * 1024;
+
+/// Helper for user-related REST API endpoints.
+pub struct UsersHelper<'a> {
+    /// Reference to constructing REST struct
+    pub rest: &'a REST,
+}
+
+impl<'a> UsersHelper<'a> {
+    /// Get the user that an OAuth token belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_token` - OAuth access token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let user = helper.get_current("access_token").unwrap();
+    /// println!("Logged in as {} (channel {})", user.username, user.channel_id());
+    /// ```
+    pub fn get_current(&self, access_token: &str) -> Result<User, Error> {
+        debug!("Getting the current user");
+        self.rest.query_json(
+            HttpMethod::Get,
+            "users/current",
+            None,
+            None,
+            Some(access_token),
+        )
+    }
+
+    /// Search for users whose usernames start with the query.
+    ///
+    /// Only one page of results is returned; use `iter_search` to go through
+    /// all of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - start of the username, at least 2 characters
+    /// * `limit` - optional number of results to return, instead of the API's default
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// for user in helper.search("some", Some(10)).unwrap() {
+    ///     println!("{} ({})", user.username, user.id);
+    /// }
+    /// ```
+    pub fn search(&self, query: &str, limit: Option<u32>) -> Result<Vec<UserSearchResult>, Error> {
+        self.search_page(query, None, limit)
+    }
+
+    /// Iterate over every user whose username starts with the query, getting
+    /// each page of 50 as it's needed.
+    ///
+    /// If getting a page fails, the error is the last item.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - start of the username, at least 2 characters
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let first_hundred: Vec<_> = helper
+    ///     .iter_search("some")
+    ///     .take(100)
+    ///     .filter_map(Result::ok)
+    ///     .collect();
+    /// ```
+    pub fn iter_search(
+        &self,
+        query: &str,
+    ) -> impl Iterator<Item = Result<UserSearchResult, Error>> + 'a {
+        let helper = UsersHelper { rest: self.rest };
+        let query = query.to_owned();
+        iter_pages(move |page| helper.search_page(&query, Some(page), Some(PAGE_SIZE as u32)))
+    }
+
+    /// Get a page of users whose usernames start with the query.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - start of the username
+    /// * `page` - optional page number, starting from 0
+    /// * `limit` - optional number of results per page
+    fn search_page(
+        &self,
+        query: &str,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Vec<UserSearchResult>, Error> {
+        debug!("Searching for users matching {}", query);
+        let page = page.map(|p| p.to_string());
+        let limit = limit.map(|l| l.to_string());
+        let mut params = vec![("query", query), ("noCount", "true")];
+        if let Some(page) = &page {
+            params.push(("page", page.as_str()));
+        }
+        if let Some(limit) = &limit {
+            params.push(("limit", limit.as_str()));
+        }
+        self.rest
+            .query_json(HttpMethod::Get, "users/search", Some(&params), None, None)
+    }
+
+    /// Get a page of the channels that a user follows.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `page` - page of results, starting from 0; defaults to the first
+    /// * `limit` - results per page; defaults to Mixer's default
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// for channel in helper.get_follows(314, None, Some(20)).unwrap() {
+    ///     println!("{} is {}", channel.token, if channel.online { "live" } else { "offline" });
+    /// }
+    /// ```
+    pub fn get_follows(
+        &self,
+        user_id: usize,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Vec<ChannelSummary>, Error> {
+        debug!("Getting follows for user ID {}", user_id);
+        let page = page.map(|p| p.to_string());
+        let limit = limit.map(|l| l.to_string());
+        let mut params = Vec::new();
+        if let Some(page) = &page {
+            params.push(("page", page.as_str()));
+        }
+        if let Some(limit) = &limit {
+            params.push(("limit", limit.as_str()));
+        }
+        self.rest.query_json(
+            HttpMethod::Get,
+            &format!("users/{}/follows", user_id),
+            Some(&params),
+            None,
+            None,
+        )
+    }
+
+    /// Iterate over every channel that a user follows, getting each page of
+    /// 50 as it's needed.
+    ///
+    /// If getting a page fails, the error is the last item.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let live = helper
+    ///     .iter_follows(314)
+    ///     .filter_map(Result::ok)
+    ///     .filter(|channel| channel.online)
+    ///     .count();
+    /// ```
+    pub fn iter_follows(
+        &self,
+        user_id: usize,
+    ) -> impl Iterator<Item = Result<ChannelSummary, Error>> + 'a {
+        let helper = UsersHelper { rest: self.rest };
+        iter_pages(move |page| helper.get_follows(user_id, Some(page), Some(PAGE_SIZE as u32)))
+    }
+
+    /// Get a user's most recent notifications, newest first.
+    ///
+    /// Needs a token with the `user:notification:self` scope.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `access_token` - OAuth access token
+    /// * `limit` - most notifications to return
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// for notification in helper.get_notifications(314, "access_token", 5).unwrap() {
+    ///     println!("{} at {}", notification.kind, notification.sent_at);
+    /// }
+    /// ```
+    pub fn get_notifications(
+        &self,
+        user_id: usize,
+        access_token: &str,
+        limit: u32,
+    ) -> Result<Vec<Notification>, Error> {
+        debug!("Getting notifications for user ID {}", user_id);
+        self.rest.query_json(
+            HttpMethod::Get,
+            &format!("users/{}/notifications", user_id),
+            Some(&[("limit", &limit.to_string()), ("noCount", "true")]),
+            None,
+            Some(access_token),
+        )
+    }
+
+    /// Mark some of a user's notifications as read.
+    ///
+    /// Needs a token with the `user:notification:self` scope.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `access_token` - OAuth access token
+    /// * `ids` - ids of the notifications
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let unread: Vec<String> = helper
+    ///     .get_notifications(314, "access_token", 20)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .filter(|n| !n.read)
+    ///     .map(|n| n.id)
+    ///     .collect();
+    /// helper.mark_notifications_read(314, "access_token", &unread).unwrap();
+    /// ```
+    pub fn mark_notifications_read<S: AsRef<str>>(
+        &self,
+        user_id: usize,
+        access_token: &str,
+        ids: &[S],
+    ) -> Result<(), Error> {
+        debug!(
+            "Marking {} notifications read for user ID {}",
+            ids.len(),
+            user_id
+        );
+        let ids: Vec<&str> = ids.iter().map(AsRef::as_ref).collect();
+        self.rest.query_with_json(
+            HttpMethod::Patch,
+            &format!("users/{}/notifications", user_id),
+            None,
+            &json!({ "ids": ids, "read": true }),
+            Some(access_token),
+        )?;
+        Ok(())
+    }
+
+    /// Get a user's preferences, e.g. `chat:sounds:play`, keyed by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `access_token` - OAuth access token
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let preferences = helper.get_preferences(314, "access_token").unwrap();
+    /// let sounds = preferences.get("chat:sounds:play");
+    /// ```
+    pub fn get_preferences(
+        &self,
+        user_id: usize,
+        access_token: &str,
+    ) -> Result<HashMap<String, Value>, Error> {
+        debug!("Getting preferences for user ID {}", user_id);
+        self.rest.query_json(
+            HttpMethod::Get,
+            &format!("users/{}/preferences", user_id),
+            None,
+            None,
+            Some(access_token),
+        )
+    }
+
+    /// Change some of a user's preferences; ones that aren't passed are kept.
+    ///
+    /// If Mixer rejects a value, the error is a `BadHttpResponseError` whose
+    /// `api_error` lists the problems with each preference.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `access_token` - OAuth access token
+    /// * `prefs` - preferences to change, keyed by name
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # use serde_json::json;
+    /// # use std::collections::HashMap;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let mut prefs = HashMap::new();
+    /// prefs.insert("chat:sounds:play".to_owned(), json!(false));
+    /// helper.update_preferences(314, "access_token", &prefs).unwrap();
+    /// ```
+    pub fn update_preferences(
+        &self,
+        user_id: usize,
+        access_token: &str,
+        prefs: &HashMap<String, Value>,
+    ) -> Result<(), Error> {
+        debug!("Updating preferences for user ID {}", user_id);
+        self.rest.query_with_json(
+            HttpMethod::Post,
+            &format!("users/{}/preferences", user_id),
+            None,
+            prefs,
+            Some(access_token),
+        )?;
+        Ok(())
+    }
+
+    /// Upload a new avatar for a user.
+    ///
+    /// Images that aren't PNG, JPEG or GIF, or are larger than 5 MiB, are
+    /// rejected without calling the API.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user's id
+    /// * `access_token` - OAuth access token
+    /// * `image` - contents of the image
+    /// * `mime` - MIME type of the image: `image/png`, `image/jpeg` or `image/gif`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.users_helper();
+    /// let image = std::fs::read("avatar.png").unwrap();
+    /// helper.upload_avatar(314, "access_token", &image, "image/png").unwrap();
+    /// ```
+    pub fn upload_avatar(
+        &self,
+        user_id: usize,
+        access_token: &str,
+        image: &[u8],
+        mime: &str,
+    ) -> Result<(), Error> {
+        if !AVATAR_MIME_TYPES.contains(&mime) {
+            return Err(format_err!(
+                "Avatars must be one of {}, not '{}'",
+                AVATAR_MIME_TYPES.join(", "),
+                mime
+            ));
+        }
+        if image.len() > MAX_AVATAR_SIZE {
+            return Err(format_err!(
+                "Avatar of {} bytes is larger than the maximum of {} bytes",
+                image.len(),
+                MAX_AVATAR_SIZE
+            ));
+        }
+        debug!("Uploading avatar for user ID {}", user_id);
+        self.rest.upload(
+            HttpMethod::Post,
+            &format!("users/{}/avatar", user_id),
+            "avatar",
+            UploadSource::Bytes(image),
+            mime,
+            Some(access_token),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_AVATAR_SIZE;
+    use crate::rest::{
+        errors::{BadHttpResponseError, FieldError},
+        mock_rest,
+        models::{ChannelSummary, Notification, UserChannel, UserSearchResult},
+        PAGE_SIZE,
+    };
+    use mockito::{mock, Matcher};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_get_current() {
+        let m1 = mock("GET", "/users/current")
+            .match_header("authorization", "Bearer abc")
+            .with_body(
+                r#"{
+                    "id": 314,
+                    "username": "someone",
+                    "verified": false,
+                    "experience": 10,
+                    "level": 1,
+                    "social": {"verified": []},
+                    "channel": {"id": 271, "token": "someone"}
+                }"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let user = rest.users_helper().get_current("abc").unwrap();
+        assert_eq!(314, user.id);
+        assert_eq!("someone", user.username);
+        assert_eq!(271, user.channel_id());
+        assert_eq!(None, user.social.twitter);
+        m1.assert();
+    }
+
+    #[test]
+    fn test_get_current_unauthorized() {
+        let _m1 = mock("GET", "/users/current")
+            .match_header("authorization", "Bearer expired")
+            .with_status(401)
+            .create();
+        let rest = mock_rest("");
+        assert!(rest.users_helper().get_current("expired").is_err());
+    }
+
+    const SEARCH_RESULTS: &str = r#"[
+        {
+            "level": 12,
+            "social": {"verified": []},
+            "id": 1001,
+            "username": "someone",
+            "verified": true,
+            "experience": 2048,
+            "sparks": 300,
+            "avatarUrl": "https://uploads.mixer.com/avatar/1001.jpg",
+            "channel": {"id": 2001, "userId": 1001, "token": "someone", "online": true}
+        },
+        {
+            "level": 3,
+            "social": {"verified": []},
+            "id": 1002,
+            "username": "someone_else",
+            "verified": false,
+            "experience": 40,
+            "sparks": 5,
+            "avatarUrl": null,
+            "channel": {"id": 2002, "userId": 1002, "token": "someone_else", "online": false}
+        }
+    ]"#;
+
+    #[test]
+    fn test_search() {
+        let m1 = mock("GET", "/users/search?query=some&noCount=true&limit=2")
+            .with_body(SEARCH_RESULTS)
+            .create();
+        let rest = mock_rest("");
+        let users = rest.users_helper().search("some", Some(2)).unwrap();
+        assert_eq!(
+            vec![
+                UserSearchResult {
+                    id: 1001,
+                    username: "someone".to_owned(),
+                    avatar_url: Some("https://uploads.mixer.com/avatar/1001.jpg".to_owned()),
+                    channel: UserChannel {
+                        id: 2001,
+                        token: "someone".to_owned(),
+                    },
+                },
+                UserSearchResult {
+                    id: 1002,
+                    username: "someone_else".to_owned(),
+                    avatar_url: None,
+                    channel: UserChannel {
+                        id: 2002,
+                        token: "someone_else".to_owned(),
+                    },
+                },
+            ],
+            users
+        );
+        m1.assert();
+    }
+
+    #[test]
+    fn test_search_default_limit() {
+        let m1 = mock("GET", "/users/search?query=some&noCount=true")
+            .with_body(SEARCH_RESULTS)
+            .create();
+        let rest = mock_rest("");
+        let users = rest.users_helper().search("some", None).unwrap();
+        assert_eq!(
+            vec![1001, 1002],
+            users.iter().map(|u| u.id).collect::<Vec<_>>()
+        );
+        m1.assert();
+    }
+
+    #[test]
+    fn test_iter_search() {
+        let page = |ids: std::ops::Range<usize>| {
+            let users: Vec<_> = ids
+                .map(|id| json!({"id": id, "username": format!("user{}", id), "channel": {"id": id, "token": "t"}}))
+                .collect();
+            json!(users).to_string()
+        };
+        let m1 = mock("GET", "/users/search?query=us&noCount=true&page=0&limit=50")
+            .with_body(page(0..50))
+            .expect(2)
+            .create();
+        let m2 = mock("GET", "/users/search?query=us&noCount=true&page=1&limit=50")
+            .with_body(page(50..60))
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.users_helper();
+        let first: Vec<_> = helper
+            .iter_search("us")
+            .take(3)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            vec![0, 1, 2],
+            first.iter().map(|u| u.id).collect::<Vec<_>>()
+        );
+        let all = helper
+            .iter_search("us")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(60, all.len());
+        m1.assert();
+        m2.assert();
+    }
+
+    fn channels(ids: std::ops::Range<usize>) -> Vec<ChannelSummary> {
+        ids.map(|id| ChannelSummary {
+            id,
+            token: format!("channel{}", id),
+            name: "Stream".to_owned(),
+            viewers_total: 100,
+            online: id % 2 == 0,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_get_follows() {
+        let m1 = mock("GET", "/users/314/follows?page=1&limit=2")
+            .with_body(
+                r#"[
+                    {"id": 7, "userId": 70, "token": "someone", "name": "Speedruns",
+                     "viewersTotal": 1500, "viewersCurrent": 12, "online": true},
+                    {"id": 8, "userId": 80, "token": "someone_else", "name": "Chatting",
+                     "viewersTotal": 30, "viewersCurrent": 0, "online": false}
+                ]"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let follows = rest
+            .users_helper()
+            .get_follows(314, Some(1), Some(2))
+            .unwrap();
+        assert_eq!(
+            ChannelSummary {
+                id: 7,
+                token: "someone".to_owned(),
+                name: "Speedruns".to_owned(),
+                viewers_total: 1500,
+                online: true,
+            },
+            follows[0]
+        );
+        assert_eq!(2, follows.len());
+        m1.assert();
+    }
+
+    #[test]
+    fn test_iter_follows() {
+        let first = channels(0..PAGE_SIZE);
+        let second = channels(PAGE_SIZE..PAGE_SIZE + 3);
+        let m1 = mock("GET", "/users/315/follows?page=0&limit=50")
+            .with_body(serde_json::to_string(&first).unwrap())
+            .create();
+        let m2 = mock("GET", "/users/315/follows?page=1&limit=50")
+            .with_body(serde_json::to_string(&second).unwrap())
+            .create();
+        let rest = mock_rest("");
+        let follows: Vec<ChannelSummary> = rest
+            .users_helper()
+            .iter_follows(315)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(PAGE_SIZE + 3, follows.len());
+        assert_eq!(first[..], follows[..PAGE_SIZE]);
+        assert_eq!(second[..], follows[PAGE_SIZE..]);
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn test_iter_follows_error() {
+        let _m1 = mock("GET", "/users/316/follows?page=0&limit=50")
+            .with_status(404)
+            .create();
+        let rest = mock_rest("");
+        let results: Vec<_> = rest.users_helper().iter_follows(316).collect();
+        assert_eq!(1, results.len());
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn test_get_notifications() {
+        let m1 = mock("GET", "/users/314/notifications?limit=2&noCount=true")
+            .match_header("authorization", "Bearer abc")
+            .with_body(
+                r#"[
+                    {
+                        "id": "5c1f1a2e",
+                        "userId": 314,
+                        "sentAt": "2019-06-01T12:00:00.000Z",
+                        "trigger": "followed",
+                        "payload": {"user": {"id": 7, "username": "someone"}},
+                        "read": false
+                    },
+                    {
+                        "id": "5c1f1a2f",
+                        "userId": 314,
+                        "sentAt": "2019-06-01T11:00:00.000Z",
+                        "trigger": "some_new_kind",
+                        "payload": {"anything": [1, 2, 3]},
+                        "read": true
+                    }
+                ]"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let notifications = rest
+            .users_helper()
+            .get_notifications(314, "abc", 2)
+            .unwrap();
+        assert_eq!(
+            vec![
+                Notification {
+                    id: "5c1f1a2e".to_owned(),
+                    kind: "followed".to_owned(),
+                    sent_at: "2019-06-01T12:00:00.000Z".to_owned(),
+                    payload: json!({"user": {"id": 7, "username": "someone"}}),
+                    read: false,
+                },
+                Notification {
+                    id: "5c1f1a2f".to_owned(),
+                    kind: "some_new_kind".to_owned(),
+                    sent_at: "2019-06-01T11:00:00.000Z".to_owned(),
+                    payload: json!({"anything": [1, 2, 3]}),
+                    read: true,
+                },
+            ],
+            notifications
+        );
+        m1.assert();
+    }
+
+    #[test]
+    fn test_mark_notifications_read() {
+        let m1 = mock("PATCH", "/users/314/notifications")
+            .match_header("authorization", "Bearer abc")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({
+                "ids": ["5c1f1a2e", "5c1f1a2f"],
+                "read": true,
+            })))
+            .with_status(204)
+            .create();
+        let rest = mock_rest("");
+        rest.users_helper()
+            .mark_notifications_read(314, "abc", &["5c1f1a2e", "5c1f1a2f"])
+            .unwrap();
+        m1.assert();
+    }
+
+    #[test]
+    fn test_preferences() {
+        let m1 = mock("POST", "/users/314/preferences")
+            .match_header("authorization", "Bearer abc")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"chat:sounds:play": false})))
+            .with_body(r#"{"chat:sounds:play": false, "chat:timestamps": true}"#)
+            .create();
+        let m2 = mock("GET", "/users/314/preferences")
+            .match_header("authorization", "Bearer abc")
+            .with_body(r#"{"chat:sounds:play": false, "chat:timestamps": true}"#)
+            .create();
+        let rest = mock_rest("");
+        let helper = rest.users_helper();
+        let mut prefs = HashMap::new();
+        prefs.insert("chat:sounds:play".to_owned(), json!(false));
+        helper.update_preferences(314, "abc", &prefs).unwrap();
+
+        let prefs = helper.get_preferences(314, "abc").unwrap();
+        assert_eq!(Some(&json!(false)), prefs.get("chat:sounds:play"));
+        assert_eq!(Some(&json!(true)), prefs.get("chat:timestamps"));
+        m1.assert();
+        m2.assert();
+    }
+
+    #[test]
+    fn test_update_preferences_invalid() {
+        let _m1 = mock("POST", "/users/315/preferences")
+            .with_status(400)
+            .with_body(
+                r#"{
+                    "statusCode": 400,
+                    "error": "Bad Request",
+                    "message": "Validation failed",
+                    "details": [{"path": "chat:sounds:play", "message": "must be a boolean"}]
+                }"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let mut prefs = HashMap::new();
+        prefs.insert("chat:sounds:play".to_owned(), json!("loud"));
+        let err = rest
+            .users_helper()
+            .update_preferences(315, "abc", &prefs)
+            .unwrap_err();
+        let err = err.downcast_ref::<BadHttpResponseError>().unwrap();
+        assert_eq!(400, err.status);
+        assert_eq!(
+            vec![FieldError {
+                field: Some("chat:sounds:play".to_owned()),
+                message: "must be a boolean".to_owned(),
+            }],
+            err.api_error.as_ref().unwrap().fields
+        );
+    }
+
+    #[test]
+    fn test_upload_avatar() {
+        let m1 = mock("POST", "/users/314/avatar")
+            .match_header("authorization", "Bearer abc")
+            .match_header(
+                "content-type",
+                Matcher::Regex("^multipart/form-data; boundary=.+$".to_owned()),
+            )
+            .match_body(Matcher::Regex(
+                "Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar\"\r\n\
+                 Content-Type: image/gif\r\n\r\nGIF89a\r\n"
+                    .to_owned(),
+            ))
+            .with_body(r#"{"avatarUrl":"https://uploads.mixer.com/avatar/314.gif"}"#)
+            .create();
+        let rest = mock_rest("");
+        rest.users_helper()
+            .upload_avatar(314, "abc", b"GIF89a", "image/gif")
+            .unwrap();
+        m1.assert();
+    }
+
+    #[test]
+    fn test_upload_avatar_rejected() {
+        let m1 = mock("POST", "/users/315/avatar").expect(0).create();
+        let rest = mock_rest("");
+        let helper = rest.users_helper();
+        let err = helper
+            .upload_avatar(315, "abc", b"BM", "image/bmp")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "Avatars must be one of image/png, image/jpeg, image/gif, not 'image/bmp'",
+            err
+        );
+        let too_large = vec![0; MAX_AVATAR_SIZE + 1];
+        assert!(helper
+            .upload_avatar(315, "abc", &too_large, "image/png")
+            .is_err());
+        m1.assert();
+    }
+}
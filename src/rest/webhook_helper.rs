@@ -1,9 +1,8 @@
 //! Helper for webhook-related REST API endpoints.
 
-use super::REST;
+use super::{models::RegisteredWebHook, parse_json, HttpMethod, REST};
 use failure::Error;
 use log::debug;
-use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
 use serde_json::json;
 
 /// Helper for webhook-related REST API endpoints.
@@ -15,7 +14,8 @@ pub struct WebHookHelper<'a> {
 impl<'a> WebHookHelper<'a> {
     /// Register webhooks.
     ///
-    /// See the [documentation] for more information.
+    /// See the [documentation] for more information. If Mixer rejects the
+    /// registration, the error is a `BadHttpResponseError`.
     ///
     /// # Arguments
     ///
@@ -29,50 +29,82 @@ impl<'a> WebHookHelper<'a> {
     /// # use mixer_wrappers::rest::REST;
     /// # let api = REST::new("");
     /// let helper = api.webhook_helper();
-    /// let channel_id = helper.register(&["event_1", "event_2"], "http://example.com/callback", "your_client_secret").unwrap();
+    /// helper.register(&["event_1", "event_2"], "http://example.com/callback", "your_client_secret").unwrap();
     /// ```
     ///
     /// [documentation]: https://dev.mixer.com/reference/webhooks
     pub fn register(&self, events: &[&str], url: &str, client_secret: &str) -> Result<(), Error> {
-        // This request has to be constructed explicitly here, as it doesn't share many
-        // similarities with the normal API requests, namely the headers.
+        // Webhooks are authorized with the client secret rather than an OAuth token
         debug!(
             "Making webhook register call with events: {}",
             events.join(", ")
         );
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            HeaderName::from_static("client-id"),
-            HeaderValue::from_bytes(self.rest.client_id.as_bytes()).unwrap(),
-        );
-        headers.insert(
-            header::AUTHORIZATION,
-            HeaderValue::from_bytes(format!("Secret {}", client_secret).as_bytes()).unwrap(),
-        );
         let body = json!({
             "events": events,
             "kind": "web",
             "url": url,
         });
-        self.rest
-            .client
-            .post(&format!("{}/hooks", self.rest.base_url()))
-            .headers(headers)
-            .body(serde_json::to_string(&body).unwrap())
-            .send()?;
+        let secret = format!("Secret {}", client_secret);
+        self.rest.send_json(
+            HttpMethod::Post,
+            "hooks",
+            None,
+            &body,
+            None,
+            &[("authorization", &secret)],
+        )?;
         Ok(())
     }
+
+    /// List the webhooks registered for your OAuth app.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_secret` - your OAuth app's client_secret
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use mixer_wrappers::rest::REST;
+    /// # let api = REST::new("");
+    /// let helper = api.webhook_helper();
+    /// for hook in helper.list("your_client_secret").unwrap() {
+    ///     println!("{} -> {}", hook.events.join(", "), hook.url);
+    /// }
+    /// ```
+    pub fn list(&self, client_secret: &str) -> Result<Vec<RegisteredWebHook>, Error> {
+        debug!("Listing webhooks");
+        let secret = format!("Secret {}", client_secret);
+        let text = self.rest.query_with_headers(
+            HttpMethod::Get,
+            "hooks",
+            None,
+            None,
+            None,
+            &[("authorization", &secret)],
+        )?;
+        parse_json("hooks", &text)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::REST;
-    use mockito::mock;
+    use crate::rest::{errors::BadHttpResponseError, mock_rest, models::RegisteredWebHook};
+    use mockito::{mock, Matcher};
+    use serde_json::json;
 
     #[test]
     fn test_register() {
-        let _m1 = mock("POST", "/hook").create();
-        let rest = REST::new("");
+        let m1 = mock("POST", "/hooks")
+            .match_header("authorization", "Secret aaaaaa")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({
+                "events": ["event_1", "event_2"],
+                "kind": "web",
+                "url": "http://example.com/callback",
+            })))
+            .create();
+        let rest = mock_rest("");
         let helper = rest.webhook_helper();
         helper
             .register(
@@ -81,5 +113,73 @@ mod tests {
                 "aaaaaa",
             )
             .unwrap();
+        m1.assert();
+    }
+
+    #[test]
+    fn test_register_rejected() {
+        let _m1 = mock("POST", "/hooks")
+            .with_status(400)
+            .with_body(r#"{"statusCode": 400, "error": "Bad Request", "message": "Invalid url"}"#)
+            .create();
+        let rest = mock_rest("");
+        let err = rest
+            .webhook_helper()
+            .register(&["event_1"], "not a url", "aaaaaa")
+            .unwrap_err();
+        let err = err.downcast_ref::<BadHttpResponseError>().unwrap();
+        assert_eq!(400, err.status);
+    }
+
+    #[test]
+    fn test_list() {
+        let m1 = mock("GET", "/hooks")
+            .match_header("authorization", "Secret aaaaaa")
+            .with_body(
+                r#"[
+                    {
+                        "id": "2b7bc3d2-1d5f-4b6a-9f5e-0c6a1c2b3d4e",
+                        "events": ["channel:1:followed"],
+                        "kind": "web",
+                        "url": "http://example.com/callback",
+                        "isActive": true,
+                        "deactivationReason": null,
+                        "expiresAt": 1577836800000
+                    },
+                    {
+                        "id": "8f1e2d3c-4b5a-6978-8a9b-0c1d2e3f4a5b",
+                        "events": ["channel:2:update", "channel:2:hosted"],
+                        "kind": "web",
+                        "url": "http://example.com/other",
+                        "isActive": false,
+                        "deactivationReason": "Too many failed deliveries"
+                    }
+                ]"#,
+            )
+            .create();
+        let rest = mock_rest("");
+        let hooks = rest.webhook_helper().list("aaaaaa").unwrap();
+        assert_eq!(
+            vec![
+                RegisteredWebHook {
+                    id: "2b7bc3d2-1d5f-4b6a-9f5e-0c6a1c2b3d4e".to_owned(),
+                    events: vec!["channel:1:followed".to_owned()],
+                    kind: "web".to_owned(),
+                    url: "http://example.com/callback".to_owned(),
+                    is_active: true,
+                    deactivation_reason: None,
+                },
+                RegisteredWebHook {
+                    id: "8f1e2d3c-4b5a-6978-8a9b-0c1d2e3f4a5b".to_owned(),
+                    events: vec!["channel:2:update".to_owned(), "channel:2:hosted".to_owned()],
+                    kind: "web".to_owned(),
+                    url: "http://example.com/other".to_owned(),
+                    is_active: false,
+                    deactivation_reason: Some("Too many failed deliveries".to_owned()),
+                },
+            ],
+            hooks
+        );
+        m1.assert();
     }
 }